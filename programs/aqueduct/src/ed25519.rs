@@ -0,0 +1,138 @@
+use anchor_lang::prelude::*;
+use solana_program::ed25519_program;
+use solana_program::sysvar::instructions::{load_current_index_checked, load_instruction_at_checked};
+
+use crate::errors::AqueductError;
+
+const SIGNATURE_OFFSETS_START: usize = 2;
+const SIGNATURE_OFFSETS_SIZE: usize = 14;
+const PUBKEY_SIZE: usize = 32;
+
+// Looks through the instructions preceding the current one for an ed25519 program instruction
+// proving that `signer` signed exactly `message`. The native program has already checked the
+// signature itself by the time we run, so only the signer and message have to be matched here.
+pub fn verify_signed_message(instructions_sysvar: &AccountInfo, signer: &Pubkey, message: &[u8]) -> Result<()> {
+    let current_index = load_current_index_checked(instructions_sysvar)?;
+
+    for index in 0..current_index {
+        let ix = load_instruction_at_checked(index as usize, instructions_sysvar)?;
+        if ix.program_id != ed25519_program::ID {
+            continue;
+        }
+
+        if signs_message(&ix.data, signer, message) {
+            return Ok(());
+        }
+    }
+
+    Err(error!(AqueductError::InvalidAttestation))
+}
+
+fn signs_message(data: &[u8], signer: &Pubkey, message: &[u8]) -> bool {
+    if data.len() < SIGNATURE_OFFSETS_START {
+        return false;
+    }
+
+    let num_signatures = data[0] as usize;
+
+    for i in 0..num_signatures {
+        let start = SIGNATURE_OFFSETS_START + i * SIGNATURE_OFFSETS_SIZE;
+        let offsets = match data.get(start..start + SIGNATURE_OFFSETS_SIZE) {
+            Some(offsets) => offsets,
+            None => return false,
+        };
+
+        let read_u16 = |at: usize| u16::from_le_bytes([offsets[at], offsets[at + 1]]);
+
+        let signature_instruction_index = read_u16(2);
+        let public_key_offset = read_u16(4) as usize;
+        let public_key_instruction_index = read_u16(6);
+        let message_data_offset = read_u16(8) as usize;
+        let message_data_size = read_u16(10) as usize;
+        let message_instruction_index = read_u16(12);
+
+        // everything has to live inside the ed25519 instruction itself
+        if signature_instruction_index != u16::MAX
+            || public_key_instruction_index != u16::MAX
+            || message_instruction_index != u16::MAX {
+            continue;
+        }
+
+        let public_key = data.get(public_key_offset..public_key_offset + PUBKEY_SIZE);
+        let signed_message = data.get(message_data_offset..message_data_offset + message_data_size);
+
+        if public_key == Some(signer.as_ref()) && signed_message == Some(message) {
+            return true;
+        }
+    }
+
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIGNATURE_SIZE: usize = 64;
+
+    // single signature ed25519 instruction data laid out the way the native program expects it
+    fn instruction_data(signer: &Pubkey, message: &[u8], instruction_index: u16) -> Vec<u8> {
+        let public_key_offset = SIGNATURE_OFFSETS_START + SIGNATURE_OFFSETS_SIZE;
+        let signature_offset = public_key_offset + PUBKEY_SIZE;
+        let message_data_offset = signature_offset + SIGNATURE_SIZE;
+
+        let mut data = vec![1u8, 0u8];
+        for value in [
+            signature_offset as u16,
+            instruction_index,
+            public_key_offset as u16,
+            instruction_index,
+            message_data_offset as u16,
+            message.len() as u16,
+            instruction_index,
+        ] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        data.extend_from_slice(signer.as_ref());
+        data.extend_from_slice(&[0u8; SIGNATURE_SIZE]);
+        data.extend_from_slice(message);
+
+        data
+    }
+
+    #[test]
+    fn matches_signer_and_message() {
+        let signer = Pubkey::new_unique();
+        let data = instruction_data(&signer, b"intent", u16::MAX);
+
+        assert!(signs_message(&data, &signer, b"intent"));
+    }
+
+    #[test]
+    fn rejects_other_signer_or_message() {
+        let signer = Pubkey::new_unique();
+        let data = instruction_data(&signer, b"intent", u16::MAX);
+
+        assert!(!signs_message(&data, &Pubkey::new_unique(), b"intent"));
+        assert!(!signs_message(&data, &signer, b"other"));
+        assert!(!signs_message(&data, &signer, b"inten"));
+    }
+
+    #[test]
+    fn rejects_data_in_other_instructions() {
+        let signer = Pubkey::new_unique();
+        let data = instruction_data(&signer, b"intent", 0);
+
+        assert!(!signs_message(&data, &signer, b"intent"));
+    }
+
+    #[test]
+    fn rejects_truncated_data() {
+        let signer = Pubkey::new_unique();
+        let data = instruction_data(&signer, b"intent", u16::MAX);
+
+        assert!(!signs_message(&[], &signer, b"intent"));
+        assert!(!signs_message(&data[..SIGNATURE_OFFSETS_START + 4], &signer, b"intent"));
+        assert!(!signs_message(&data[..data.len() - 1], &signer, b"intent"));
+    }
+}
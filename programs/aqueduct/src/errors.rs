@@ -0,0 +1,145 @@
+use anchor_lang::prelude::*;
+
+#[error_code]
+pub enum AqueductError {
+    #[msg("math overflow")]
+    MathOverflow,
+    #[msg("mint does not match the vault quote mint")]
+    VaultMintMismatch,
+    #[msg("first deposit into a vault is below the minimum")]
+    DepositTooSmall,
+    #[msg("deposit would mint zero shares")]
+    ZeroShares,
+    #[msg("minted shares are below the requested minimum")]
+    SharesBelowMinimum,
+    #[msg("withdrawn amount is below the requested minimum")]
+    AmountBelowMinimum,
+    #[msg("position accounts do not match the vault position")]
+    VaultPositionMismatch,
+    #[msg("vault already has an open position")]
+    VaultHasPosition,
+    #[msg("vault has no open position")]
+    VaultHasNoPosition,
+    #[msg("fraction must be between 1 and RESOLUTION")]
+    InvalidFraction,
+    #[msg("position accounts do not belong to the user and whirlpool")]
+    InvalidPositionAccounts,
+    #[msg("treasury account does not belong to the service")]
+    InvalidTreasuryAccount,
+    #[msg("the service has acted too recently for an emergency exit")]
+    ServiceStillActive,
+    #[msg("emergency exit delay is below the minimum")]
+    EmergencyExitSlotsTooLow,
+    #[msg("account is not a legacy user account")]
+    InvalidLegacyAccount,
+    #[msg("user still has open positions")]
+    UserHasOpenPositions,
+    #[msg("token accounts do not belong to the user")]
+    InvalidTokenAccounts,
+    #[msg("signer is not the pending owner")]
+    NotPendingOwner,
+    #[msg("signer is neither the owner nor a delegate with this permission")]
+    DelegateNotPermitted,
+    #[msg("mint is not part of an approved whirlpool")]
+    MintNotApproved,
+    #[msg("whirlpool is not approved")]
+    PoolNotApproved,
+    #[msg("swap output is not the quote mint")]
+    OutputNotQuote,
+    #[msg("quote mint is not approved")]
+    QuoteMintNotApproved,
+    #[msg("quote mint is not part of the whirlpool")]
+    QuoteMintNotInPool,
+    #[msg("deposit exceeds the per user cap for this mint")]
+    UserDepositCapExceeded,
+    #[msg("deposit exceeds the protocol wide cap for this mint")]
+    ProtocolDepositCapExceeded,
+    #[msg("liquidity exceeds the cap for this pool")]
+    PoolLiquidityCapExceeded,
+    #[msg("funding exceeds the max allocation for this mint")]
+    MaxAllocationExceeded,
+    #[msg("allocation percent must be at most 100")]
+    InvalidAllocationPercent,
+    #[msg("user creation requires an allowlist entry or an admin attestation")]
+    AttestationRequired,
+    #[msg("no matching ed25519 signature found in the transaction")]
+    InvalidAttestation,
+    #[msg("user is in exit only mode")]
+    UserExitOnly,
+    #[msg("user requires a signed intent for this action")]
+    IntentRequired,
+    #[msg("intent does not match the requested action")]
+    IntentMismatch,
+    #[msg("intent nonce does not match the user")]
+    IntentNonceMismatch,
+    #[msg("intent has expired")]
+    IntentExpired,
+    #[msg("rebalance cooldown has not elapsed")]
+    RebalanceCooldown,
+    #[msg("daily rebalance limit reached")]
+    RebalanceLimitReached,
+    #[msg("daily swap limit reached")]
+    SwapLimitReached,
+    #[msg("daily swap notional limit reached")]
+    SwapNotionalLimitReached,
+    #[msg("fee rate must be at most 10000 bps")]
+    InvalidFeeRate,
+    #[msg("no free fee override slot")]
+    FeeOverridesFull,
+    #[msg("unknown performance fee mode")]
+    InvalidFeeMode,
+    #[msg("users can not refer themselves")]
+    SelfReferral,
+    #[msg("referral account does not belong to the user's referrer")]
+    InvalidReferralAccount,
+    #[msg("too many fee tiers or discount above 10000 bps")]
+    InvalidFeeTiers,
+    #[msg("stake account does not belong to the user")]
+    InvalidStakeAccount,
+    #[msg("stake is still locked")]
+    StakeLocked,
+    #[msg("not enough stake")]
+    InsufficientStake,
+    #[msg("route whirlpool does not trade the reward mint")]
+    InvalidRewardRoute,
+    #[msg("reward mint is not whitelisted")]
+    RewardNotWhitelisted,
+    #[msg("user has not opted into reward conversion")]
+    RewardConversionDisabled,
+    #[msg("unknown reward conversion mode")]
+    InvalidRewardConversion,
+    #[msg("reward route does not end in the user's quote or pair tokens")]
+    RewardRouteMismatch,
+    #[msg("slippage must be at most 10000")]
+    InvalidSlippage,
+    #[msg("reward accounts do not match the whirlpool reward slot")]
+    InvalidRewardAccounts,
+    #[msg("user already has a position bundle")]
+    PositionBundleExists,
+    #[msg("position bundle does not belong to the user")]
+    InvalidPositionBundle,
+    #[msg("tick index is not a multiple of the whirlpool tick spacing")]
+    TickNotAligned,
+    #[msg("tick index is outside the whirlpool tick bounds")]
+    TickOutOfBounds,
+    #[msg("position range is narrower than the minimum width")]
+    PositionRangeTooNarrow,
+    #[msg("range width must be positive and skew within 10000 bps")]
+    InvalidRangeSpec,
+    #[msg("range does not match the user's range settings")]
+    RangeSpecMismatch,
+    #[msg("tick array is not the whirlpool tick array for the position range")]
+    InvalidTickArray,
+    #[msg("pool price is too far from the vault reference price")]
+    VaultPriceDeviation,
+    #[msg("open positions of the user have not been counted yet")]
+    OpenPositionsNotCounted,
+    #[msg("position is listed more than once")]
+    DuplicatePosition,
+    #[msg("attestation has expired")]
+    AttestationExpired,
+    #[msg("pool reference price has not been set")]
+    PoolPriceNotSet,
+    #[msg("current tick is outside the position range")]
+    CurrentTickOutOfRange,
+}
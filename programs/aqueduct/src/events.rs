@@ -0,0 +1,208 @@
+use anchor_lang::prelude::*;
+
+use crate::state::User;
+
+// `user` is always the owner wallet, `balance` the user_pda balance of `mint` after the transfer
+
+#[event]
+pub struct DepositEvent {
+    pub user: Pubkey,
+    pub user_pda: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub balance: u64,
+    pub slot: u64,
+}
+
+#[event]
+pub struct WithdrawEvent {
+    pub user: Pubkey,
+    pub user_pda: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub balance: u64,
+    pub slot: u64,
+}
+
+// vault share mints and burns, `user` is the share holder's wallet and `share_supply` the supply afterwards
+
+#[event]
+pub struct VaultDeposited {
+    pub user: Pubkey,
+    pub vault: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub shares: u64,
+    pub share_supply: u64,
+    pub slot: u64,
+}
+
+#[event]
+pub struct VaultWithdrawn {
+    pub user: Pubkey,
+    pub vault: Pubkey,
+    pub shares: u64,
+    pub amount_a: u64,
+    pub amount_b: u64,
+    pub share_supply: u64,
+    pub slot: u64,
+}
+
+#[event]
+pub struct LossGuardTriggered {
+    pub user: Pubkey,
+    pub user_pda: Pubkey,
+    pub loss_in_window: u64,
+    pub loss_guard_threshold: u64,
+    pub loss_window_start: i64,
+    pub slot: u64,
+}
+
+pub fn emit_loss_guard_triggered(user_pda: &Account<User>) -> Result<()> {
+    emit!(LossGuardTriggered {
+        user: user_pda.user,
+        user_pda: user_pda.key(),
+        loss_in_window: user_pda.loss_in_window,
+        loss_guard_threshold: user_pda.loss_guard_threshold,
+        loss_window_start: user_pda.loss_window_start,
+        slot: Clock::get()?.slot,
+    });
+
+    Ok(())
+}
+
+// position lifecycle, prices are whirlpool sqrt_price_x64 values;
+// for pooled vault positions `user` and `user_pda` are both the vault
+
+#[event]
+pub struct PositionOpened {
+    pub user: Pubkey,
+    pub user_pda: Pubkey,
+    pub whirlpool: Pubkey,
+    pub position: Pubkey,
+    pub position_mint: Pubkey,
+    pub tick_lower_index: i32,
+    pub tick_upper_index: i32,
+    pub tick_current_index: i32,
+    pub sqrt_price: u128,
+    pub slot: u64,
+}
+
+#[event]
+pub struct LiquidityAdded {
+    pub user: Pubkey,
+    pub user_pda: Pubkey,
+    pub whirlpool: Pubkey,
+    pub position: Pubkey,
+    pub liquidity: u128,
+    pub amount_a: u64,
+    pub amount_b: u64,
+    pub sqrt_price: u128,
+    pub slot: u64,
+}
+
+#[event]
+pub struct LiquidityRemoved {
+    pub user: Pubkey,
+    pub user_pda: Pubkey,
+    pub whirlpool: Pubkey,
+    pub position: Pubkey,
+    pub liquidity: u128,
+    pub amount_a: u64,
+    pub amount_b: u64,
+    pub sqrt_price: u128,
+    pub slot: u64,
+}
+
+#[event]
+pub struct Swapped {
+    pub user: Pubkey,
+    pub user_pda: Pubkey,
+    pub whirlpool: Pubkey,
+    pub a_to_b: bool,
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub sqrt_price_before: u128,
+    pub sqrt_price_after: u128,
+    pub slot: u64,
+}
+
+#[event]
+pub struct FeesCollected {
+    pub user: Pubkey,
+    pub user_pda: Pubkey,
+    pub whirlpool: Pubkey,
+    pub position: Pubkey,
+    pub amount_a: u64,
+    pub amount_b: u64,
+    pub slot: u64,
+}
+
+#[event]
+pub struct RewardCollected {
+    pub user: Pubkey,
+    pub user_pda: Pubkey,
+    pub whirlpool: Pubkey,
+    pub position: Pubkey,
+    pub reward_index: u8,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub slot: u64,
+}
+
+#[event]
+pub struct ProtocolFeeTaken {
+    pub user: Pubkey,
+    pub user_pda: Pubkey,
+    pub position: Pubkey,
+    pub mint: Pubkey,
+    pub destination: Pubkey,
+    pub amount: u64,
+    pub slot: u64,
+}
+
+#[event]
+pub struct PositionClosed {
+    pub user: Pubkey,
+    pub user_pda: Pubkey,
+    pub whirlpool: Pubkey,
+    pub position: Pubkey,
+    pub tick_lower_index: i32,
+    pub tick_upper_index: i32,
+    pub liquidity: u128,
+    pub amount_a: u64,
+    pub amount_b: u64,
+    pub sqrt_price: u128,
+    pub slot: u64,
+}
+
+#[event]
+pub struct ReferralFeeShared {
+    pub user: Pubkey,
+    pub user_pda: Pubkey,
+    pub referrer: Pubkey,
+    pub mint: Pubkey,
+    pub destination: Pubkey,
+    pub amount: u64,
+    pub slot: u64,
+}
+
+#[event]
+pub struct ReferralClaimed {
+    pub referrer: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub slot: u64,
+}
+
+#[event]
+pub struct RewardSwapped {
+    pub user: Pubkey,
+    pub user_pda: Pubkey,
+    pub whirlpool: Pubkey,
+    pub reward_mint: Pubkey,
+    pub output_mint: Pubkey,
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub slot: u64,
+}
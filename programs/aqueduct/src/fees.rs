@@ -0,0 +1,246 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token;
+use anchor_spl::token::TokenAccount;
+
+use crate::errors::AqueductError;
+use crate::events::{ProtocolFeeTaken, ReferralFeeShared};
+use crate::math::mul_div;
+use crate::state::{FeeSchedule, FeeTiers, Stake, User, FEE_BPS_DENOMINATOR, PROGRAM_ID, REFERRAL_PDA_PREFIX, SECONDS_PER_YEAR, STAKE_PDA_PREFIX};
+
+// the fee schedule PDA, or the defaults while the admin has not created it, so exits never depend on it
+pub fn load_fee_schedule(info: &AccountInfo) -> Result<FeeSchedule> {
+    if *info.owner != PROGRAM_ID || info.data_is_empty() {
+        return Ok(FeeSchedule::default());
+    }
+
+    let data = info.try_borrow_data()?;
+    FeeSchedule::try_deserialize(&mut &data[..])
+}
+
+// the fee tiers PDA, or no tiers and so no discount while it does not exist
+pub fn load_fee_tiers(info: &AccountInfo) -> Result<FeeTiers> {
+    if *info.owner != PROGRAM_ID || info.data_is_empty() {
+        return Ok(FeeTiers::default());
+    }
+
+    let data = info.try_borrow_data()?;
+    FeeTiers::try_deserialize(&mut &data[..])
+}
+
+pub fn fee_amount(amount: u64, fee_bps: u16) -> Result<u64> {
+    mul_div(amount, fee_bps as u64, FEE_BPS_DENOMINATOR)
+}
+
+// rate left after taking `discount_bps` of it off
+pub fn discounted_bps(fee_bps: u16, discount_bps: u16) -> u16 {
+    let discount = (fee_bps as u64 * discount_bps.min(FEE_BPS_DENOMINATOR as u16) as u64) / FEE_BPS_DENOMINATOR;
+    fee_bps - discount as u16
+}
+
+// the user's tier discount, `stake_info` is the user's stake PDA which may not have been created yet
+pub fn user_fee_discount_bps(fee_tiers: &FeeTiers, user_pda: &Account<User>, stake_info: &AccountInfo, program_id: &Pubkey) -> Result<u16> {
+    let (stake_pda, _) = Pubkey::find_program_address(&[STAKE_PDA_PREFIX, user_pda.key().as_ref()], program_id);
+    require!(stake_info.key() == stake_pda, AqueductError::InvalidStakeAccount);
+
+    // only a stake in the current stake mint counts towards the tiers
+    let stake = if stake_info.owner == program_id && !stake_info.data_is_empty() {
+        let stake: Account<Stake> = Account::try_from(stake_info)?;
+        if stake.mint == fee_tiers.stake_mint { stake.amount } else { 0 }
+    } else {
+        0
+    };
+
+    Ok(fee_tiers.discount_bps_for(user_pda.net_deposit, stake))
+}
+
+// splits a fee valued in the quote asset across a pair of amounts worth `total_value`, each side pays its share
+pub fn split_fee_value(amount_a: u64, amount_b: u64, fee_value: u64, total_value: u64) -> Result<(u64, u64)> {
+    if fee_value == 0 || total_value == 0 {
+        return Ok((0, 0));
+    }
+
+    let fee_value = fee_value.min(total_value);
+
+    Ok((mul_div(amount_a, fee_value, total_value)?, mul_div(amount_b, fee_value, total_value)?))
+}
+
+// pro-rata share of an annual rate for the seconds elapsed since the last accrual
+pub fn management_fee_amount(balance: u64, fee_bps: u16, elapsed_seconds: i64) -> Result<u64> {
+    if elapsed_seconds <= 0 || fee_bps == 0 {
+        return Ok(0);
+    }
+
+    let fee = (balance as u128)
+        .checked_mul(fee_bps as u128)
+        .and_then(|v| v.checked_mul(elapsed_seconds as u128))
+        .and_then(|v| v.checked_div((FEE_BPS_DENOMINATOR as u128) * (SECONDS_PER_YEAR as u128)))
+        .ok_or(AqueductError::MathOverflow)?;
+
+    // never more than the balance, however long the accrual gap
+    Ok((fee as u64).min(balance))
+}
+
+// the referrer's token account for `mint`, owned by its referral PDA, or None when the user was not referred
+pub fn referral_token_account<'info>(user_pda: &User, info: &AccountInfo<'info>, mint: &Pubkey, program_id: &Pubkey) -> Result<Option<Account<'info, TokenAccount>>> {
+    if user_pda.referrer == Pubkey::default() {
+        return Ok(None);
+    }
+
+    let (referral_pda, _) = Pubkey::find_program_address(&[REFERRAL_PDA_PREFIX, user_pda.referrer.as_ref()], program_id);
+    let account: Account<TokenAccount> = Account::try_from(info)?;
+    require!(account.owner == referral_pda, AqueductError::InvalidReferralAccount);
+    require!(account.mint == *mint, AqueductError::InvalidReferralAccount);
+
+    Ok(Some(account))
+}
+
+// pays the referrer's share of `service_amount` out of `from` and returns it, the rest is left for the treasury;
+// `authority` owns `from`, the user PDA itself or the vault for share withdrawals
+#[allow(clippy::too_many_arguments)]
+pub fn pay_referral_share<'info>(
+    user_pda: &Account<'info, User>,
+    authority: AccountInfo<'info>,
+    from: AccountInfo<'info>,
+    referral: Option<&Account<'info, TokenAccount>>,
+    token_program: AccountInfo<'info>,
+    signer_seeds: &[&[&[u8]]],
+    service_amount: u64,
+    referral_share_bps: u16,
+) -> Result<u64> {
+    let referral = match referral {
+        Some(referral) => referral,
+        None => return Ok(0),
+    };
+
+    let amount = fee_amount(service_amount, referral_share_bps)?;
+    if amount == 0 {
+        return Ok(0);
+    }
+
+    token::transfer(
+        CpiContext::new(
+            token_program,
+            token::Transfer {
+                from,
+                to: referral.to_account_info(),
+                authority,
+            },
+        ).with_signer(signer_seeds),
+        amount,
+    )?;
+
+    emit!(ReferralFeeShared {
+        user: user_pda.user,
+        user_pda: user_pda.key(),
+        referrer: user_pda.referrer,
+        mint: referral.mint,
+        destination: referral.key(),
+        amount,
+        slot: Clock::get()?.slot,
+    });
+
+    Ok(amount)
+}
+
+// moves a service fee out of a user_pda token account, the referrer's share to them and the rest into the treasury
+#[allow(clippy::too_many_arguments)]
+pub fn take_service_fee<'info>(
+    user_pda: &Account<'info, User>,
+    from: &Account<'info, TokenAccount>,
+    treasury: &Account<'info, TokenAccount>,
+    referral: Option<&Account<'info, TokenAccount>>,
+    referral_share_bps: u16,
+    token_program: AccountInfo<'info>,
+    signer_seeds: &[&[&[u8]]],
+    position: Pubkey,
+    amount: u64,
+) -> Result<()> {
+    if amount == 0 {
+        return Ok(());
+    }
+
+    let amount = amount - pay_referral_share(
+        user_pda,
+        user_pda.to_account_info(),
+        from.to_account_info(),
+        referral,
+        token_program.clone(),
+        signer_seeds,
+        amount,
+        referral_share_bps,
+    )?;
+
+    token::transfer(
+        CpiContext::new(
+            token_program,
+            token::Transfer {
+                from: from.to_account_info(),
+                to: treasury.to_account_info(),
+                authority: user_pda.to_account_info(),
+            },
+        ).with_signer(signer_seeds),
+        amount,
+    )?;
+
+    emit!(ProtocolFeeTaken {
+        user: user_pda.user,
+        user_pda: user_pda.key(),
+        position,
+        mint: from.mint,
+        destination: treasury.key(),
+        amount,
+        slot: Clock::get()?.slot,
+    });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fee_amount_rounds_down() {
+        assert_eq!(fee_amount(10_000, 1_500).unwrap(), 1_500);
+        assert_eq!(fee_amount(99, 100).unwrap(), 0);
+        assert_eq!(fee_amount(1_000, 0).unwrap(), 0);
+        assert_eq!(fee_amount(u64::MAX, FEE_BPS_DENOMINATOR as u16).unwrap(), u64::MAX);
+    }
+
+    #[test]
+    fn management_fee_amount_is_pro_rata() {
+        assert_eq!(management_fee_amount(1_000_000, 200, SECONDS_PER_YEAR).unwrap(), 20_000);
+        assert_eq!(management_fee_amount(1_000_000, 200, SECONDS_PER_YEAR / 2).unwrap(), 10_000);
+        assert_eq!(management_fee_amount(1_000_000, 0, SECONDS_PER_YEAR).unwrap(), 0);
+        assert_eq!(management_fee_amount(1_000_000, 200, 0).unwrap(), 0);
+        assert_eq!(management_fee_amount(1_000_000, 200, -SECONDS_PER_YEAR).unwrap(), 0);
+    }
+
+    #[test]
+    fn management_fee_amount_is_capped_at_the_balance() {
+        assert_eq!(management_fee_amount(1_000_000, 200, 100 * SECONDS_PER_YEAR).unwrap(), 1_000_000);
+        assert_eq!(management_fee_amount(u64::MAX, u16::MAX, i64::MAX).unwrap_err(), AqueductError::MathOverflow.into());
+    }
+
+    #[test]
+    fn split_fee_value_pro_rata() {
+        assert_eq!(split_fee_value(1_000, 2_000, 30, 300).unwrap(), (100, 200));
+        assert_eq!(split_fee_value(1_000, 0, 50, 100).unwrap(), (500, 0));
+        assert_eq!(split_fee_value(1_000, 2_000, 0, 300).unwrap(), (0, 0));
+        assert_eq!(split_fee_value(1_000, 2_000, 30, 0).unwrap(), (0, 0));
+    }
+
+    #[test]
+    fn split_fee_value_never_exceeds_the_amounts() {
+        assert_eq!(split_fee_value(1_000, 2_000, 600, 300).unwrap(), (1_000, 2_000));
+    }
+
+    #[test]
+    fn discounted_bps_takes_a_share_of_the_rate() {
+        assert_eq!(discounted_bps(1_500, 0), 1_500);
+        assert_eq!(discounted_bps(1_500, 2_000), 1_200);
+        assert_eq!(discounted_bps(1_500, 10_000), 0);
+        assert_eq!(discounted_bps(1_500, u16::MAX), 0);
+        assert_eq!(discounted_bps(0, 5_000), 0);
+    }
+}
@@ -30,6 +30,7 @@ pub struct AddPoolConfig<'info> {
     pub system_program: Program<'info, System>,
 }
 
+// pool configs start unapproved, set_pool_approval approves them separately
 pub fn handler(_ctx: Context<AddPoolConfig>,
 ) -> Result<()> {
 
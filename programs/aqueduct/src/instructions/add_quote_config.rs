@@ -0,0 +1,37 @@
+use std::mem::size_of;
+
+use anchor_lang::prelude::*;
+use anchor_spl::token::Mint;
+
+use crate::state::*;
+
+#[derive(Accounts)]
+pub struct AddQuoteConfig<'info> {
+    #[account(mut)]
+    pub auth: Signer<'info>,
+
+    #[account(
+        init,
+        seeds = [
+            QUOTE_CONFIG_PDA_PREFIX,
+            mint.key().as_ref()
+        ],
+        bump,
+        payer = auth,
+        space = 8 + size_of::<QuoteConfig>()
+    )]
+    pub quote_config: Account<'info, QuoteConfig>,
+
+    pub mint: Account<'info, Mint>,
+
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handler(ctx: Context<AddQuoteConfig>,
+) -> Result<()> {
+
+    ctx.accounts.quote_config.mint = ctx.accounts.mint.key();
+    ctx.accounts.quote_config.approved = true;
+
+    Ok(())
+}
@@ -0,0 +1,35 @@
+use std::mem::size_of;
+
+use anchor_lang::prelude::*;
+
+use crate::state::*;
+
+#[derive(Accounts)]
+pub struct AllowlistAdd<'info> {
+    #[account(mut)]
+    pub auth: Signer<'info>,
+
+    /// CHECK: the wallet being allowed to create a user
+    pub user: UncheckedAccount<'info>,
+    #[account(
+        init,
+        seeds = [
+            ALLOWLIST_PDA_PREFIX,
+            user.key().as_ref()
+        ],
+        bump,
+        payer = auth,
+        space = 8 + size_of::<Allowlist>()
+    )]
+    pub allowlist: Account<'info, Allowlist>,
+
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handler(ctx: Context<AllowlistAdd>,
+) -> Result<()> {
+
+    ctx.accounts.allowlist.user = ctx.accounts.user.key();
+
+    Ok(())
+}
@@ -0,0 +1,27 @@
+use anchor_lang::prelude::*;
+
+use crate::state::*;
+
+#[derive(Accounts)]
+pub struct AllowlistRemove<'info> {
+    #[account(mut)]
+    pub auth: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [
+            ALLOWLIST_PDA_PREFIX,
+            allowlist.user.as_ref()
+        ],
+        bump,
+        close = auth
+    )]
+    pub allowlist: Account<'info, Allowlist>,
+}
+
+// only stops new users from being created, existing users are revoked with user_set_exit_only
+pub fn handler(_ctx: Context<AllowlistRemove>,
+) -> Result<()> {
+
+    Ok(())
+}
@@ -0,0 +1,441 @@
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token::{Mint, Token, TokenAccount};
+use anchor_spl::{associated_token, token};
+use whirlpool::state::{Position, TickArray, Whirlpool, NUM_REWARDS};
+use whirlpool::cpi::accounts::{ClosePosition, CloseBundledPosition};
+
+use crate::errors::AqueductError;
+use crate::events::*;
+use crate::fees::{discounted_bps, load_fee_schedule, load_fee_tiers, split_fee_value, take_service_fee, user_fee_discount_bps};
+use crate::math::value_in_user_quote;
+use crate::positions::PositionAccounts;
+use crate::rewards::{reward_slot_group, EMERGENCY_REWARD_ACCOUNTS_LEN};
+use crate::state::*;
+use crate::WhirlpoolProgram;
+
+#[derive(Accounts)]
+#[instruction(
+    bundle_index: Option<u16>,
+)]
+pub struct EmergencyExit<'info> {
+    #[account(
+        mut,
+        constraint = user.key() == user_pda.user
+    )]
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [
+            USER_PDA_PREFIX,
+            user_pda.vault_id.as_ref()
+        ],
+        bump,
+    )]
+    pub user_pda: Box<Account<'info, User>>,
+
+    pub whirlpool_program: Program<'info, WhirlpoolProgram>,
+    #[account(mut)]
+    pub whirlpool: Box<Account<'info, Whirlpool>>,
+    #[account(
+        mut,
+        seeds = [
+            POOL_CONFIG_PDA_PREFIX,
+            whirlpool.key().as_ref()
+        ],
+        bump,
+    )]
+    pub pool_config: Box<Account<'info, PoolConfig>>,
+    /// CHECK: the mint's config PDA, may not have been created in which case nothing is capped
+    #[account(
+        mut,
+        seeds = [
+            MINT_CONFIG_PDA_PREFIX,
+            whirlpool.token_mint_a.as_ref()
+        ],
+        bump,
+    )]
+    pub mint_config_a: UncheckedAccount<'info>,
+    /// CHECK: the mint's config PDA, may not have been created in which case nothing is capped
+    #[account(
+        mut,
+        seeds = [
+            MINT_CONFIG_PDA_PREFIX,
+            whirlpool.token_mint_b.as_ref()
+        ],
+        bump,
+    )]
+    pub mint_config_b: UncheckedAccount<'info>,
+    /// CHECK: the fee schedule PDA, read through load_fee_schedule which falls back to the defaults
+    #[account(
+        seeds = [
+            FEE_SCHEDULE_PDA_PREFIX
+        ],
+        bump,
+    )]
+    pub fee_schedule: UncheckedAccount<'info>,
+    /// CHECK: the fee tiers PDA, read through load_fee_tiers which falls back to no discount
+    #[account(
+        seeds = [
+            FEE_TIERS_PDA_PREFIX
+        ],
+        bump,
+    )]
+    pub fee_tiers: UncheckedAccount<'info>,
+    /// CHECK: the user's stake PDA, matched and only read once it exists in user_fee_discount_bps
+    pub stake: UncheckedAccount<'info>,
+
+    #[account(mut, has_one = whirlpool)]
+    pub position: Box<Account<'info, Position>>,
+    #[account(
+        mut,
+        constraint = position_token_account.mint == position.position_mint,
+        constraint = position_token_account.owner == user_pda.key(),
+        constraint = position_token_account.amount == 1
+    )]
+    pub position_token_account: Box<Account<'info, TokenAccount>>,
+    #[account(mut, address = position.position_mint)]
+    pub position_mint: Account<'info, Mint>,
+    /// CHECK: the user's position bundle, only used when closing a bundled position and checked by the whirlpool
+    #[account(mut)]
+    pub position_bundle: UncheckedAccount<'info>,
+
+    #[account(address = whirlpool.token_mint_a)]
+    pub mint_a: Box<Account<'info, Mint>>,
+    #[account(address = whirlpool.token_mint_b)]
+    pub mint_b: Box<Account<'info, Mint>>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_a,
+        associated_token::authority = user_pda,
+    )]
+    pub token_owner_account_a: Box<Account<'info, TokenAccount>>,
+    #[account(mut, address = whirlpool.token_vault_a)]
+    pub token_vault_a: Box<Account<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        associated_token::mint = mint_a,
+        associated_token::authority = user,
+        payer = user
+    )]
+    pub destination_ata_a: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = token_aqueduct_a.mint == whirlpool.token_mint_a,
+        constraint = token_aqueduct_a.owner == B_SERVICE @ AqueductError::InvalidTreasuryAccount,
+    )]
+    pub token_aqueduct_a: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_b,
+        associated_token::authority = user_pda,
+    )]
+    pub token_owner_account_b: Box<Account<'info, TokenAccount>>,
+    #[account(mut, address = whirlpool.token_vault_b)]
+    pub token_vault_b: Box<Account<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        associated_token::mint = mint_b,
+        associated_token::authority = user,
+        payer = user
+    )]
+    pub destination_ata_b: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = token_aqueduct_b.mint == whirlpool.token_mint_b,
+        constraint = token_aqueduct_b.owner == B_SERVICE @ AqueductError::InvalidTreasuryAccount,
+    )]
+    pub token_aqueduct_b: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut, has_one = whirlpool)]
+    pub tick_array_lower: AccountLoader<'info, TickArray>,
+    #[account(mut, has_one = whirlpool)]
+    pub tick_array_upper: AccountLoader<'info, TickArray>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    #[account(address = token::ID)]
+    pub token_program: Program<'info, Token>,
+    pub rent: Sysvar<'info, Rent>,
+    pub system_program: Program<'info, System>,
+
+    // remaining_accounts are (reward_mint, reward_owner_account, reward_vault, user_reward_ata) groups,
+    // one per whirlpool reward slot in slot order
+}
+
+// Lets the user unwind a position without the service once it has been inactive long enough.
+// Only the management fee already accrued on the position is taken, nothing is skimmed off fees or
+// rewards, and the vault is left paused afterwards.
+pub fn handler<'info>(ctx: Context<'_, '_, '_, 'info, EmergencyExit<'info>>,
+    bundle_index: Option<u16>,
+) -> Result<()> {
+
+    let vault_id = ctx.accounts.user_pda.vault_id;
+    let (_user_pda, user_pda_bump) = Pubkey::find_program_address(&[USER_PDA_PREFIX, vault_id.as_ref()], ctx.program_id);
+    let seeds = &[USER_PDA_PREFIX, vault_id.as_ref(), &[user_pda_bump]];
+    let signer_seeds = &[&seeds[..]];
+
+    require!(
+        Clock::get()?.slot >= ctx.accounts.user_pda.emergency_exit_available_slot(),
+        AqueductError::ServiceStillActive
+    );
+
+    let slot = Clock::get()?.slot;
+    let user = ctx.accounts.user_pda.user;
+    let user_pda = ctx.accounts.user_pda.key();
+    let fee_schedule = load_fee_schedule(&ctx.accounts.fee_schedule)?;
+    let fee_tiers = load_fee_tiers(&ctx.accounts.fee_tiers)?;
+    let discount_bps = user_fee_discount_bps(&fee_tiers, &ctx.accounts.user_pda, &ctx.accounts.stake, ctx.program_id)?;
+
+    let position = ctx.accounts.position_accounts();
+    position.update_fees_and_rewards(ctx.accounts.position.liquidity)?;
+    let (fees_a, fees_b) = position.collect_fees(signer_seeds)?;
+
+    emit!(FeesCollected {
+        user,
+        user_pda,
+        whirlpool: ctx.accounts.whirlpool.key(),
+        position: ctx.accounts.position.key(),
+        amount_a: fees_a,
+        amount_b: fees_b,
+        slot,
+    });
+
+    ctx.accounts.position.reload()?;
+
+    for i in 0..NUM_REWARDS {
+        // uninitialized slots and slots with nothing owed need no accounts
+        if !ctx.accounts.whirlpool.reward_infos[i].initialized() || ctx.accounts.position.reward_infos[i].amount_owed == 0 {
+            continue;
+        }
+
+        let reward_accounts = reward_slot_group(&ctx.accounts.whirlpool, i, &user_pda, ctx.remaining_accounts, EMERGENCY_REWARD_ACCOUNTS_LEN)?;
+        let reward_mint = reward_accounts[0].key();
+
+        let user_reward_ata: Account<'info, TokenAccount> = Account::try_from(&reward_accounts[3])?;
+        require!(user_reward_ata.owner == user, AqueductError::InvalidRewardAccounts);
+        require!(user_reward_ata.mint == reward_mint, AqueductError::InvalidRewardAccounts);
+
+        if reward_accounts[1].data_is_empty() {
+            associated_token::create(
+                CpiContext::new(
+                    ctx.accounts.associated_token_program.to_account_info(),
+                    associated_token::Create {
+                        payer: ctx.accounts.user.to_account_info(),
+                        associated_token: reward_accounts[1].clone(),
+                        authority: ctx.accounts.user_pda.to_account_info(),
+                        mint: reward_accounts[0].clone(),
+                        system_program: ctx.accounts.system_program.to_account_info(),
+                        token_program: ctx.accounts.token_program.to_account_info(),
+                        rent: ctx.accounts.rent.to_account_info(),
+                    },
+                ),
+            )?;
+        }
+
+        let reward_amount = position.collect_reward(signer_seeds, i, &reward_accounts[1], &reward_accounts[2])?;
+
+        let reward_owner_account_after: Account<'info, TokenAccount> = Account::try_from(&reward_accounts[1])?;
+
+        emit!(RewardCollected {
+            user,
+            user_pda,
+            whirlpool: ctx.accounts.whirlpool.key(),
+            position: ctx.accounts.position.key(),
+            reward_index: i as u8,
+            mint: reward_mint,
+            amount: reward_amount,
+            slot,
+        });
+
+        if reward_owner_account_after.amount > 0 {
+            token::transfer(
+                CpiContext::new(
+                    ctx.accounts.token_program.to_account_info(),
+                    token::Transfer {
+                        from: reward_owner_account_after.to_account_info(),
+                        to: user_reward_ata.to_account_info(),
+                        authority: ctx.accounts.user_pda.to_account_info()
+                    },
+                ).with_signer(signer_seeds),
+                reward_owner_account_after.amount
+            )?;
+
+            emit!(WithdrawEvent {
+                user,
+                user_pda,
+                mint: reward_mint,
+                amount: reward_owner_account_after.amount,
+                balance: 0,
+                slot,
+            });
+        }
+    }
+
+    let liquidity = ctx.accounts.position.liquidity;
+
+    let (amount_a, amount_b) = position.decrease_liquidity(signer_seeds, liquidity)?;
+    let tick_lower_index = ctx.accounts.position.tick_lower_index;
+    let tick_upper_index = ctx.accounts.position.tick_upper_index;
+
+    ctx.accounts.pool_config.remove_liquidity(liquidity);
+
+    let management_fee_bps = discounted_bps(fee_schedule.management_fee_bps, discount_bps);
+    ctx.accounts.user_pda.accrue_management_fee(management_fee_bps, Clock::get()?.unix_timestamp)?;
+    let management_fee_value = ctx.accounts.user_pda.release_management_fee(liquidity);
+    ctx.accounts.user_pda.release_cost_basis(liquidity);
+
+    // valued at the pool reference price; a pair that cannot be valued in the user's quote asset, or a pool
+    // without a reference, leaves the fee uncharged rather than blocking the exit
+    let quote_mint = ctx.accounts.user_pda.effective_quote_mint();
+    let reference_sqrt_price = ctx.accounts.pool_config.valuation_sqrt_price().ok();
+    let (management_fee_a, management_fee_b) = if management_fee_value > 0 {
+        let value_out = reference_sqrt_price
+            .and_then(|sqrt_price| value_in_user_quote(amount_a, amount_b, sqrt_price, &ctx.accounts.whirlpool, &quote_mint).ok())
+            .unwrap_or(0);
+        split_fee_value(amount_a, amount_b, management_fee_value, value_out)?
+    } else {
+        (0, 0)
+    };
+
+    match bundle_index {
+        // a bundled position hands its slot back to the user's bundle, there is no mint to burn
+        Some(bundle_index) => whirlpool::cpi::close_bundled_position(
+            ctx.accounts.into_close_bundled_position_context().with_signer(signer_seeds),
+            bundle_index
+        )?,
+        None => whirlpool::cpi::close_position(
+            ctx.accounts.into_close_position_context().with_signer(signer_seeds)
+        )?,
+    }
+
+    ctx.accounts.user_pda.open_positions = ctx.accounts.user_pda.open_positions.saturating_sub(1);
+
+    emit!(PositionClosed {
+        user,
+        user_pda,
+        whirlpool: ctx.accounts.whirlpool.key(),
+        position: ctx.accounts.position.key(),
+        tick_lower_index,
+        tick_upper_index,
+        liquidity,
+        amount_a,
+        amount_b,
+        sqrt_price: ctx.accounts.whirlpool.sqrt_price,
+        slot,
+    });
+
+    // the unattended path takes no referral accounts, the treasury keeps the whole fee
+    for (from, to, amount) in [
+        (&ctx.accounts.token_owner_account_a, &ctx.accounts.token_aqueduct_a, management_fee_a),
+        (&ctx.accounts.token_owner_account_b, &ctx.accounts.token_aqueduct_b, management_fee_b),
+    ] {
+        take_service_fee(
+            &ctx.accounts.user_pda,
+            from,
+            to,
+            None,
+            0,
+            ctx.accounts.token_program.to_account_info(),
+            signer_seeds,
+            ctx.accounts.position.key(),
+            amount,
+        )?;
+    }
+
+    ctx.accounts.token_owner_account_a.reload()?;
+    ctx.accounts.token_owner_account_b.reload()?;
+
+    // everything below leaves the user_pda, an amount that cannot be valued clears the net deposit
+    let withdrawn_value = reference_sqrt_price.and_then(|sqrt_price| value_in_user_quote(
+        ctx.accounts.token_owner_account_a.amount,
+        ctx.accounts.token_owner_account_b.amount,
+        sqrt_price,
+        &ctx.accounts.whirlpool,
+        &quote_mint
+    ).ok());
+    ctx.accounts.user_pda.record_withdrawal(withdrawn_value);
+
+    for (from, to, mint_config) in [
+        (&ctx.accounts.token_owner_account_a, &ctx.accounts.destination_ata_a, &ctx.accounts.mint_config_a),
+        (&ctx.accounts.token_owner_account_b, &ctx.accounts.destination_ata_b, &ctx.accounts.mint_config_b),
+    ] {
+        if from.amount == 0 {
+            continue;
+        }
+
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token::Transfer {
+                    from: from.to_account_info(),
+                    to: to.to_account_info(),
+                    authority: ctx.accounts.user_pda.to_account_info(),
+                },
+            ).with_signer(signer_seeds),
+            from.amount,
+        )?;
+
+        record_mint_withdraw(mint_config, from.amount)?;
+
+        emit!(WithdrawEvent {
+            user,
+            user_pda,
+            mint: from.mint,
+            amount: from.amount,
+            balance: 0,
+            slot,
+        });
+    }
+
+    ctx.accounts.user_pda.paused = true;
+
+    Ok(())
+}
+
+impl<'info> EmergencyExit<'info> {
+
+    fn position_accounts(&self) -> PositionAccounts<'info> {
+        PositionAccounts {
+            whirlpool_program: self.whirlpool_program.to_account_info(),
+            whirlpool: self.whirlpool.to_account_info(),
+            position_authority: self.user_pda.to_account_info(),
+            position: self.position.to_account_info(),
+            position_token_account: self.position_token_account.to_account_info(),
+            token_owner_account_a: self.token_owner_account_a.to_account_info(),
+            token_owner_account_b: self.token_owner_account_b.to_account_info(),
+            token_vault_a: self.token_vault_a.to_account_info(),
+            token_vault_b: self.token_vault_b.to_account_info(),
+            tick_array_lower: self.tick_array_lower.to_account_info(),
+            tick_array_upper: self.tick_array_upper.to_account_info(),
+            token_program: self.token_program.to_account_info(),
+        }
+    }
+
+    fn into_close_bundled_position_context(&self) -> CpiContext<'_, '_, '_, 'info, CloseBundledPosition<'info>> {
+        let cpi_accounts = CloseBundledPosition {
+            bundled_position: self.position.to_account_info(),
+            position_bundle: self.position_bundle.to_account_info(),
+            position_bundle_token_account: self.position_token_account.to_account_info(),
+            position_bundle_authority: self.user_pda.to_account_info(),
+            receiver: self.user.to_account_info(),
+        };
+        let cpi_program = self.whirlpool_program.to_account_info();
+        CpiContext::new(cpi_program, cpi_accounts)
+    }
+
+    fn into_close_position_context(&self) -> CpiContext<'_, '_, '_, 'info, ClosePosition<'info>> {
+        let cpi_accounts = ClosePosition {
+            position_authority: self.user_pda.to_account_info(),
+            receiver: self.user.to_account_info(),
+            position: self.position.to_account_info(),
+            position_mint: self.position_mint.to_account_info(),
+            position_token_account: self.position_token_account.to_account_info(),
+            token_program: self.token_program.to_account_info()
+        };
+        let cpi_program = self.whirlpool_program.to_account_info();
+        CpiContext::new(cpi_program, cpi_accounts)
+    }
+
+}
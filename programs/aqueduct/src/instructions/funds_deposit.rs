@@ -2,7 +2,11 @@ use anchor_lang::prelude::*;
 use anchor_spl::associated_token::AssociatedToken;
 use anchor_spl::token;
 use anchor_spl::token::{Mint, Token, TokenAccount};
+use whirlpool::state::Whirlpool;
 
+use crate::errors::AqueductError;
+use crate::events::*;
+use crate::math::mint_value_in_quote;
 use crate::state::*;
 
 #[derive(Accounts)]
@@ -12,8 +16,26 @@ use crate::state::*;
 pub struct FundsDeposit<'info> {
     #[account(mut)]
     pub user: Signer<'info>,
-    #[account(mut)]
+    #[account(
+        mut,
+        seeds = [
+            USER_PDA_PREFIX,
+            user_pda.vault_id.as_ref()
+        ],
+        bump,
+        constraint = !user_pda.exit_only @ AqueductError::UserExitOnly,
+    )]
     pub user_pda: Account<'info, User>,
+    #[account(
+        seeds = [
+            POOL_CONFIG_PDA_PREFIX,
+            whirlpool.key().as_ref()
+        ],
+        bump,
+        constraint = pool_config.approved @ AqueductError::PoolNotApproved,
+    )]
+    pub pool_config: Box<Account<'info, PoolConfig>>,
+    pub whirlpool: Box<Account<'info, Whirlpool>>,
     #[account(mut)]
     pub source_ata: Account<'info, TokenAccount>,
     #[account(
@@ -24,9 +46,21 @@ pub struct FundsDeposit<'info> {
     )]
     pub destination_ata: Account<'info, TokenAccount>,
     #[account(
-        constraint = *mint.to_account_info().key == USDC,
+        constraint = mint.key() == user_pda.effective_quote_mint()
+            || mint.key() == whirlpool.token_mint_a
+            || mint.key() == whirlpool.token_mint_b @ AqueductError::MintNotApproved,
     )]
     pub mint: Account<'info, Mint>,
+    /// CHECK: the mint's config PDA, may not have been created in which case nothing is capped
+    #[account(
+        mut,
+        seeds = [
+            MINT_CONFIG_PDA_PREFIX,
+            mint.key().as_ref()
+        ],
+        bump,
+    )]
+    pub mint_config: UncheckedAccount<'info>,
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub token_program: Program<'info, Token>,
     pub rent: Sysvar<'info, Rent>,
@@ -49,5 +83,22 @@ pub fn handler(ctx: Context<FundsDeposit>,
         deposit_amount,
     )?;
 
+    ctx.accounts.destination_ata.reload()?;
+
+    let quote_mint = ctx.accounts.user_pda.effective_quote_mint();
+    let sqrt_price = ctx.accounts.pool_config.valuation_sqrt_price()?;
+    let value = mint_value_in_quote(deposit_amount, &ctx.accounts.mint.key(), sqrt_price, &ctx.accounts.whirlpool, &quote_mint);
+    let net_deposit = ctx.accounts.user_pda.record_deposit(value);
+    record_mint_deposit(&ctx.accounts.mint_config, deposit_amount, net_deposit)?;
+
+    emit!(DepositEvent {
+        user: ctx.accounts.user_pda.user,
+        user_pda: ctx.accounts.user_pda.key(),
+        mint: ctx.accounts.mint.key(),
+        amount: deposit_amount,
+        balance: ctx.accounts.destination_ata.amount,
+        slot: Clock::get()?.slot,
+    });
+
     Ok(())
 }
@@ -0,0 +1,95 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token;
+use anchor_spl::token::{Mint, Token, TokenAccount};
+
+use crate::errors::AqueductError;
+use crate::events::*;
+use crate::state::*;
+
+#[derive(Accounts)]
+#[instruction(
+    lamports: u64,
+)]
+pub struct FundsDepositSol<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [
+            USER_PDA_PREFIX,
+            user_pda.vault_id.as_ref()
+        ],
+        bump,
+        constraint = !user_pda.exit_only @ AqueductError::UserExitOnly,
+    )]
+    pub user_pda: Account<'info, User>,
+    #[account(
+        init_if_needed,
+        associated_token::mint = native_mint,
+        associated_token::authority = user_pda,
+        payer = user
+    )]
+    pub destination_ata: Account<'info, TokenAccount>,
+    #[account(address = spl_token::native_mint::ID)]
+    pub native_mint: Account<'info, Mint>,
+    /// CHECK: the mint's config PDA, may not have been created in which case nothing is capped
+    #[account(
+        mut,
+        seeds = [
+            MINT_CONFIG_PDA_PREFIX,
+            native_mint.key().as_ref()
+        ],
+        bump,
+    )]
+    pub mint_config: UncheckedAccount<'info>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Program<'info, Token>,
+    pub rent: Sysvar<'info, Rent>,
+    pub system_program: Program<'info, System>,
+}
+
+// wraps SOL straight into the user_pda wSOL account
+pub fn handler(ctx: Context<FundsDepositSol>,
+   lamports: u64,
+) -> Result<()> {
+
+    system_program::transfer(
+        CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            system_program::Transfer {
+                from: ctx.accounts.user.to_account_info(),
+                to: ctx.accounts.destination_ata.to_account_info(),
+            },
+        ),
+        lamports,
+    )?;
+
+    token::sync_native(
+        CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            token::SyncNative {
+                account: ctx.accounts.destination_ata.to_account_info(),
+            },
+        ),
+    )?;
+
+    ctx.accounts.destination_ata.reload()?;
+
+    // there is no pool to price SOL against, it only counts when it is the quote asset
+    let value = if ctx.accounts.user_pda.effective_quote_mint() == ctx.accounts.native_mint.key() { Some(lamports) } else { None };
+    let net_deposit = ctx.accounts.user_pda.record_deposit(value);
+    record_mint_deposit(&ctx.accounts.mint_config, lamports, net_deposit)?;
+
+    emit!(DepositEvent {
+        user: ctx.accounts.user_pda.user,
+        user_pda: ctx.accounts.user_pda.key(),
+        mint: ctx.accounts.native_mint.key(),
+        amount: lamports,
+        balance: ctx.accounts.destination_ata.amount,
+        slot: Clock::get()?.slot,
+    });
+
+    Ok(())
+}
@@ -0,0 +1,238 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token;
+use anchor_spl::token::{Mint, Token, TokenAccount};
+use whirlpool::state::{TickArray, Whirlpool};
+use whirlpool::cpi::accounts::Swap;
+
+use crate::errors::AqueductError;
+use crate::events::*;
+use crate::state::*;
+use crate::WhirlpoolProgram;
+
+#[derive(Accounts)]
+#[instruction(
+    amount: u64,
+    minimum_amount_out: u64,
+    wrap_sol: bool,
+)]
+pub struct FundsDepositSwap<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [
+            USER_PDA_PREFIX,
+            user_pda.vault_id.as_ref()
+        ],
+        bump,
+        constraint = !user_pda.exit_only @ AqueductError::UserExitOnly,
+    )]
+    pub user_pda: Box<Account<'info, User>>,
+    #[account(
+        seeds = [
+            POOL_CONFIG_PDA_PREFIX,
+            whirlpool.key().as_ref()
+        ],
+        bump,
+        constraint = pool_config.approved @ AqueductError::PoolNotApproved,
+    )]
+    pub pool_config: Box<Account<'info, PoolConfig>>,
+
+    pub whirlpool_program: Program<'info, WhirlpoolProgram>,
+    #[account(mut)]
+    pub whirlpool: Box<Account<'info, Whirlpool>>,
+
+    /// CHECK: the user's token account for the input mint, unused when wrapping SOL
+    #[account(mut)]
+    pub source_ata: UncheckedAccount<'info>,
+
+    #[account(address = whirlpool.token_mint_a)]
+    pub mint_a: Box<Account<'info, Mint>>,
+    #[account(address = whirlpool.token_mint_b)]
+    pub mint_b: Box<Account<'info, Mint>>,
+    // caps are applied to the quote mint the deposit is converted into
+    /// CHECK: the mint's config PDA, may not have been created in which case nothing is capped
+    #[account(
+        mut,
+        seeds = [
+            MINT_CONFIG_PDA_PREFIX,
+            user_pda.effective_quote_mint().as_ref()
+        ],
+        bump,
+    )]
+    pub quote_mint_config: UncheckedAccount<'info>,
+
+    #[account(
+        init_if_needed,
+        associated_token::mint = mint_a,
+        associated_token::authority = user_pda,
+        payer = user
+    )]
+    pub token_owner_account_a: Box<Account<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        associated_token::mint = mint_b,
+        associated_token::authority = user_pda,
+        payer = user
+    )]
+    pub token_owner_account_b: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut, address = whirlpool.token_vault_a)]
+    pub token_vault_a: Box<Account<'info, TokenAccount>>,
+    #[account(mut, address = whirlpool.token_vault_b)]
+    pub token_vault_b: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut, has_one = whirlpool)]
+    pub tick_array_0: AccountLoader<'info, TickArray>,
+    #[account(mut, has_one = whirlpool)]
+    pub tick_array_1: AccountLoader<'info, TickArray>,
+    #[account(mut, has_one = whirlpool)]
+    pub tick_array_2: AccountLoader<'info, TickArray>,
+
+    /// CHECK:
+    pub oracle: UncheckedAccount<'info>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Program<'info, Token>,
+    pub rent: Sysvar<'info, Rent>,
+    pub system_program: Program<'info, System>,
+}
+
+// Deposits one side of an approved pair (or SOL) and converts it into the quote mint on the other side.
+pub fn handler<'info>(ctx: Context<'_, '_, '_, 'info, FundsDepositSwap<'info>>,
+    amount: u64,
+    minimum_amount_out: u64,
+    wrap_sol: bool,
+) -> Result<()> {
+
+    let vault_id = ctx.accounts.user_pda.vault_id;
+    let (_user_pda, user_pda_bump) = Pubkey::find_program_address(&[USER_PDA_PREFIX, vault_id.as_ref()], ctx.program_id);
+    let seeds = &[USER_PDA_PREFIX, vault_id.as_ref(), &[user_pda_bump]];
+    let signer_seeds = &[&seeds[..]];
+
+    let input_mint = if wrap_sol {
+        spl_token::native_mint::ID
+    } else {
+        let source_ata: Account<TokenAccount> = Account::try_from(&ctx.accounts.source_ata)?;
+        require!(source_ata.owner == ctx.accounts.user.key(), AqueductError::InvalidTokenAccounts);
+        source_ata.mint
+    };
+
+    let whirlpool = &ctx.accounts.whirlpool;
+    let a_to_b = if input_mint == whirlpool.token_mint_a {
+        true
+    } else if input_mint == whirlpool.token_mint_b {
+        false
+    } else {
+        return Err(AqueductError::MintNotApproved.into());
+    };
+
+    let output_mint = if a_to_b { whirlpool.token_mint_b } else { whirlpool.token_mint_a };
+    require!(output_mint == ctx.accounts.user_pda.effective_quote_mint(), AqueductError::OutputNotQuote);
+
+    let input_account = if a_to_b {
+        ctx.accounts.token_owner_account_a.to_account_info()
+    } else {
+        ctx.accounts.token_owner_account_b.to_account_info()
+    };
+
+    if wrap_sol {
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.user.to_account_info(),
+                    to: input_account.clone(),
+                },
+            ),
+            amount,
+        )?;
+
+        token::sync_native(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token::SyncNative {
+                    account: input_account,
+                },
+            ),
+        )?;
+    } else {
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token::Transfer {
+                    from: ctx.accounts.source_ata.to_account_info(),
+                    to: input_account,
+                    authority: ctx.accounts.user.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+    }
+
+    let quote_balance_before = if a_to_b {
+        ctx.accounts.token_owner_account_b.amount
+    } else {
+        ctx.accounts.token_owner_account_a.amount
+    };
+
+    let sqrt_price_limit = if a_to_b { MIN_SQRT_PRICE } else { MAX_SQRT_PRICE };
+
+    whirlpool::cpi::swap(
+        ctx.accounts.into_swap_context().with_signer(signer_seeds),
+        amount,
+        minimum_amount_out,
+        sqrt_price_limit,
+        true,
+        a_to_b
+    )?;
+
+    ctx.accounts.token_owner_account_a.reload()?;
+    ctx.accounts.token_owner_account_b.reload()?;
+
+    let quote_balance = if a_to_b {
+        ctx.accounts.token_owner_account_b.amount
+    } else {
+        ctx.accounts.token_owner_account_a.amount
+    };
+
+    let quote_amount = quote_balance.checked_sub(quote_balance_before).ok_or(AqueductError::MathOverflow)?;
+    let net_deposit = ctx.accounts.user_pda.record_deposit(Some(quote_amount));
+    record_mint_deposit(&ctx.accounts.quote_mint_config, quote_amount, net_deposit)?;
+
+    // recorded in the quote mint the user ends up holding
+    emit!(DepositEvent {
+        user: ctx.accounts.user_pda.user,
+        user_pda: ctx.accounts.user_pda.key(),
+        mint: output_mint,
+        amount: quote_amount,
+        balance: quote_balance,
+        slot: Clock::get()?.slot,
+    });
+
+    Ok(())
+}
+
+impl<'info> FundsDepositSwap<'info> {
+
+    fn into_swap_context(&self) -> CpiContext<'_, '_, '_, 'info, Swap<'info>> {
+        let cpi_accounts = Swap {
+            token_program: self.token_program.to_account_info(),
+            token_authority: self.user_pda.to_account_info(),
+            whirlpool: self.whirlpool.to_account_info(),
+            token_owner_account_a: self.token_owner_account_a.to_account_info(),
+            token_vault_a: self.token_vault_a.to_account_info(),
+            token_owner_account_b: self.token_owner_account_b.to_account_info(),
+            token_vault_b: self.token_vault_b.to_account_info(),
+            tick_array_0: self.tick_array_0.to_account_info(),
+            tick_array_1: self.tick_array_1.to_account_info(),
+            tick_array_2: self.tick_array_2.to_account_info(),
+            oracle: self.oracle.to_account_info(),
+        };
+        let cpi_program = self.whirlpool_program.to_account_info();
+        CpiContext::new(cpi_program, cpi_accounts)
+    }
+
+}
@@ -2,7 +2,12 @@ use anchor_lang::prelude::*;
 use anchor_spl::associated_token::AssociatedToken;
 use anchor_spl::token;
 use anchor_spl::token::{Mint, Token, TokenAccount};
+use whirlpool::state::Whirlpool;
 
+use crate::errors::AqueductError;
+use crate::events::*;
+use crate::fees::{discounted_bps, fee_amount, load_fee_schedule, load_fee_tiers, referral_token_account, take_service_fee, user_fee_discount_bps};
+use crate::math::mint_value_in_quote;
 use crate::state::*;
 
 #[derive(Accounts)]
@@ -13,13 +18,17 @@ pub struct FundsWithdraw<'info> {
     #[account(
         mut,
         constraint = user.key() == user_pda.user
+            || user_pda.is_delegate_with(&user.key(), DELEGATE_PERMISSION_WITHDRAW) @ AqueductError::DelegateNotPermitted
     )]
     pub user: Signer<'info>,
+    /// CHECK: withdrawals always land in the owner's wallet, even when a delegate signs
+    #[account(constraint = owner.key() == user_pda.user)]
+    pub owner: UncheckedAccount<'info>,
     #[account(
         mut,
         seeds = [
             USER_PDA_PREFIX,
-            user.key().as_ref()
+            user_pda.vault_id.as_ref()
         ],
         bump,
     )]
@@ -32,7 +41,7 @@ pub struct FundsWithdraw<'info> {
     #[account(
         init_if_needed,
         associated_token::mint = mint,
-        associated_token::authority = user,
+        associated_token::authority = owner,
         payer = user
     )]
     pub destination_ata: Account<'info, TokenAccount>,
@@ -40,6 +49,55 @@ pub struct FundsWithdraw<'info> {
         constraint = mint.decimals != 0,
     )]
     pub mint: Account<'info, Mint>,
+    // prices the withdrawal against the user's net deposit at the pool reference price, the user's own pool
+    // or an approved one; a pool without the mint and the quote asset clears it
+    #[account(
+        seeds = [
+            POOL_CONFIG_PDA_PREFIX,
+            whirlpool.key().as_ref()
+        ],
+        bump,
+        constraint = pool_config.approved || whirlpool.key() == user_pda.whirlpool @ AqueductError::PoolNotApproved,
+    )]
+    pub pool_config: Box<Account<'info, PoolConfig>>,
+    pub whirlpool: Box<Account<'info, Whirlpool>>,
+    /// CHECK: the mint's config PDA, may not have been created in which case nothing is capped
+    #[account(
+        mut,
+        seeds = [
+            MINT_CONFIG_PDA_PREFIX,
+            mint.key().as_ref()
+        ],
+        bump,
+    )]
+    pub mint_config: UncheckedAccount<'info>,
+    /// CHECK: the fee schedule PDA, read through load_fee_schedule which falls back to the defaults
+    #[account(
+        seeds = [
+            FEE_SCHEDULE_PDA_PREFIX
+        ],
+        bump,
+    )]
+    pub fee_schedule: UncheckedAccount<'info>,
+    /// CHECK: the fee tiers PDA, read through load_fee_tiers which falls back to no discount
+    #[account(
+        seeds = [
+            FEE_TIERS_PDA_PREFIX
+        ],
+        bump,
+    )]
+    pub fee_tiers: UncheckedAccount<'info>,
+    /// CHECK: the user's stake PDA, matched and only read once it exists in user_fee_discount_bps
+    pub stake: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        constraint = token_aqueduct.mint == mint.key(),
+        constraint = token_aqueduct.owner == B_SERVICE @ AqueductError::InvalidTreasuryAccount,
+    )]
+    pub token_aqueduct: Account<'info, TokenAccount>,
+    /// CHECK: the referrer's token account for the mint, only read when the user has a referrer
+    #[account(mut)]
+    pub referral_token: UncheckedAccount<'info>,
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub token_program: Program<'info, Token>,
     pub rent: Sysvar<'info, Rent>,
@@ -49,35 +107,58 @@ pub struct FundsWithdraw<'info> {
 pub fn handler(ctx: Context<FundsWithdraw>,
    amount: u64,
 ) -> Result<()> {
-    let (_user_pda, user_pda_bump) = Pubkey::find_program_address(&[USER_PDA_PREFIX, ctx.accounts.user.key.as_ref()], ctx.program_id);
-    let seeds = &[USER_PDA_PREFIX, ctx.accounts.user.key.as_ref(), &[user_pda_bump]];
+    let vault_id = ctx.accounts.user_pda.vault_id;
+    let (_user_pda, user_pda_bump) = Pubkey::find_program_address(&[USER_PDA_PREFIX, vault_id.as_ref()], ctx.program_id);
+    let seeds = &[USER_PDA_PREFIX, vault_id.as_ref(), &[user_pda_bump]];
     let signer_seeds = &[&seeds[..]];
 
-    if amount == 0u64 {
-        token::transfer(
-            CpiContext::new(
-                ctx.accounts.token_program.to_account_info(),
-                token::Transfer {
-                    from: ctx.accounts.source_ata.to_account_info(),
-                    to: ctx.accounts.destination_ata.to_account_info(),
-                    authority: ctx.accounts.user_pda.to_account_info(),
-                },
-            ).with_signer(signer_seeds),
-            ctx.accounts.source_ata.amount,
-        )?;
-    } else {
-        token::transfer(
-            CpiContext::new(
-                ctx.accounts.token_program.to_account_info(),
-                token::Transfer {
-                    from: ctx.accounts.source_ata.to_account_info(),
-                    to: ctx.accounts.destination_ata.to_account_info(),
-                    authority: ctx.accounts.user_pda.to_account_info(),
-                },
-            ).with_signer(signer_seeds),
-            amount,
-        )?;
-    }
+    let withdraw_amount = if amount == 0u64 { ctx.accounts.source_ata.amount } else { amount };
+    let fee_schedule = load_fee_schedule(&ctx.accounts.fee_schedule)?;
+    let fee_tiers = load_fee_tiers(&ctx.accounts.fee_tiers)?;
+    let discount_bps = user_fee_discount_bps(&fee_tiers, &ctx.accounts.user_pda, &ctx.accounts.stake, ctx.program_id)?;
+    let withdrawal_fee = fee_amount(withdraw_amount, discounted_bps(fee_schedule.withdrawal_fee_bps_for(&ctx.accounts.mint.key()), discount_bps))?;
+
+    let referral = referral_token_account(&ctx.accounts.user_pda, &ctx.accounts.referral_token, &ctx.accounts.mint.key(), ctx.program_id)?;
+    take_service_fee(
+        &ctx.accounts.user_pda,
+        &ctx.accounts.source_ata,
+        &ctx.accounts.token_aqueduct,
+        referral.as_ref(),
+        fee_schedule.referral_share_bps,
+        ctx.accounts.token_program.to_account_info(),
+        signer_seeds,
+        Pubkey::default(),
+        withdrawal_fee,
+    )?;
+
+    token::transfer(
+        CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            token::Transfer {
+                from: ctx.accounts.source_ata.to_account_info(),
+                to: ctx.accounts.destination_ata.to_account_info(),
+                authority: ctx.accounts.user_pda.to_account_info(),
+            },
+        ).with_signer(signer_seeds),
+        withdraw_amount - withdrawal_fee,
+    )?;
+
+    ctx.accounts.source_ata.reload()?;
+    record_mint_withdraw(&ctx.accounts.mint_config, withdraw_amount)?;
+
+    let quote_mint = ctx.accounts.user_pda.effective_quote_mint();
+    let value = ctx.accounts.pool_config.valuation_sqrt_price().ok()
+        .and_then(|sqrt_price| mint_value_in_quote(withdraw_amount, &ctx.accounts.mint.key(), sqrt_price, &ctx.accounts.whirlpool, &quote_mint));
+    ctx.accounts.user_pda.record_withdrawal(value);
+
+    emit!(WithdrawEvent {
+        user: ctx.accounts.user_pda.user,
+        user_pda: ctx.accounts.user_pda.key(),
+        mint: ctx.accounts.source_ata.mint,
+        amount: withdraw_amount - withdrawal_fee,
+        balance: ctx.accounts.source_ata.amount,
+        slot: Clock::get()?.slot,
+    });
 
     Ok(())
 }
\ No newline at end of file
@@ -0,0 +1,254 @@
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token;
+use anchor_spl::token::{Mint, Token, TokenAccount};
+use whirlpool::state::{TickArray, Whirlpool};
+use whirlpool::cpi::accounts::Swap;
+
+use crate::errors::AqueductError;
+use crate::events::*;
+use crate::fees::{discounted_bps, fee_amount, load_fee_schedule, load_fee_tiers, referral_token_account, take_service_fee, user_fee_discount_bps};
+use crate::state::*;
+use crate::WhirlpoolProgram;
+
+#[derive(Accounts)]
+#[instruction(
+    amount: u64,
+    minimum_amount_out: u64,
+)]
+pub struct FundsWithdrawSwap<'info> {
+    #[account(
+        mut,
+        constraint = user.key() == user_pda.user
+            || user_pda.is_delegate_with(&user.key(), DELEGATE_PERMISSION_WITHDRAW) @ AqueductError::DelegateNotPermitted
+    )]
+    pub user: Signer<'info>,
+    /// CHECK: withdrawals always land in the owner's wallet, even when a delegate signs
+    #[account(constraint = owner.key() == user_pda.user)]
+    pub owner: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [
+            USER_PDA_PREFIX,
+            user_pda.vault_id.as_ref()
+        ],
+        bump,
+    )]
+    pub user_pda: Box<Account<'info, User>>,
+    #[account(
+        seeds = [
+            POOL_CONFIG_PDA_PREFIX,
+            whirlpool.key().as_ref()
+        ],
+        bump,
+        constraint = pool_config.approved @ AqueductError::PoolNotApproved,
+    )]
+    pub pool_config: Box<Account<'info, PoolConfig>>,
+
+    pub whirlpool_program: Program<'info, WhirlpoolProgram>,
+    #[account(mut)]
+    pub whirlpool: Box<Account<'info, Whirlpool>>,
+
+    #[account(
+        address = user_pda.effective_quote_mint(),
+        constraint = quote_mint.key() == whirlpool.token_mint_a
+            || quote_mint.key() == whirlpool.token_mint_b @ AqueductError::QuoteMintNotInPool,
+    )]
+    pub quote_mint: Box<Account<'info, Mint>>,
+    /// CHECK: the mint's config PDA, may not have been created in which case nothing is capped
+    #[account(
+        mut,
+        seeds = [
+            MINT_CONFIG_PDA_PREFIX,
+            quote_mint.key().as_ref()
+        ],
+        bump,
+    )]
+    pub quote_mint_config: UncheckedAccount<'info>,
+    /// CHECK: the fee schedule PDA, read through load_fee_schedule which falls back to the defaults
+    #[account(
+        seeds = [
+            FEE_SCHEDULE_PDA_PREFIX
+        ],
+        bump,
+    )]
+    pub fee_schedule: UncheckedAccount<'info>,
+    /// CHECK: the fee tiers PDA, read through load_fee_tiers which falls back to no discount
+    #[account(
+        seeds = [
+            FEE_TIERS_PDA_PREFIX
+        ],
+        bump,
+    )]
+    pub fee_tiers: UncheckedAccount<'info>,
+    /// CHECK: the user's stake PDA, matched and only read once it exists in user_fee_discount_bps
+    pub stake: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        constraint = token_aqueduct.mint == quote_mint.key(),
+        constraint = token_aqueduct.owner == B_SERVICE @ AqueductError::InvalidTreasuryAccount,
+    )]
+    pub token_aqueduct: Box<Account<'info, TokenAccount>>,
+    /// CHECK: the referrer's token account for the quote mint, only read when the user has a referrer
+    #[account(mut)]
+    pub referral_token: UncheckedAccount<'info>,
+    #[account(
+        init_if_needed,
+        associated_token::mint = quote_mint,
+        associated_token::authority = owner,
+        payer = user
+    )]
+    pub destination_ata: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        associated_token::mint = whirlpool.token_mint_a,
+        associated_token::authority = user_pda,
+    )]
+    pub token_owner_account_a: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint = whirlpool.token_mint_b,
+        associated_token::authority = user_pda,
+    )]
+    pub token_owner_account_b: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut, address = whirlpool.token_vault_a)]
+    pub token_vault_a: Box<Account<'info, TokenAccount>>,
+    #[account(mut, address = whirlpool.token_vault_b)]
+    pub token_vault_b: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut, has_one = whirlpool)]
+    pub tick_array_0: AccountLoader<'info, TickArray>,
+    #[account(mut, has_one = whirlpool)]
+    pub tick_array_1: AccountLoader<'info, TickArray>,
+    #[account(mut, has_one = whirlpool)]
+    pub tick_array_2: AccountLoader<'info, TickArray>,
+
+    /// CHECK:
+    pub oracle: UncheckedAccount<'info>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Program<'info, Token>,
+    pub rent: Sysvar<'info, Rent>,
+    pub system_program: Program<'info, System>,
+}
+
+// Swaps the non-quote side of the pair into the user's quote mint and sends it to the owner.
+// With amount == 0 the whole non-quote balance is swapped and the whole quote balance withdrawn,
+// otherwise only the proceeds of swapping `amount` are withdrawn.
+pub fn handler<'info>(ctx: Context<'_, '_, '_, 'info, FundsWithdrawSwap<'info>>,
+    amount: u64,
+    minimum_amount_out: u64,
+) -> Result<()> {
+
+    let vault_id = ctx.accounts.user_pda.vault_id;
+    let (_user_pda, user_pda_bump) = Pubkey::find_program_address(&[USER_PDA_PREFIX, vault_id.as_ref()], ctx.program_id);
+    let seeds = &[USER_PDA_PREFIX, vault_id.as_ref(), &[user_pda_bump]];
+    let signer_seeds = &[&seeds[..]];
+
+    // selling A for B when the quote mint is B
+    let a_to_b = ctx.accounts.quote_mint.key() == ctx.accounts.whirlpool.token_mint_b;
+
+    let (input_balance, quote_balance_before) = if a_to_b {
+        (ctx.accounts.token_owner_account_a.amount, ctx.accounts.token_owner_account_b.amount)
+    } else {
+        (ctx.accounts.token_owner_account_b.amount, ctx.accounts.token_owner_account_a.amount)
+    };
+
+    let swap_amount = if amount == 0u64 { input_balance } else { amount };
+
+    if swap_amount > 0 {
+        let sqrt_price_limit = if a_to_b { MIN_SQRT_PRICE } else { MAX_SQRT_PRICE };
+
+        whirlpool::cpi::swap(
+            ctx.accounts.into_swap_context().with_signer(signer_seeds),
+            swap_amount,
+            minimum_amount_out,
+            sqrt_price_limit,
+            true,
+            a_to_b
+        )?;
+    }
+
+    ctx.accounts.token_owner_account_a.reload()?;
+    ctx.accounts.token_owner_account_b.reload()?;
+
+    let quote_account = if a_to_b {
+        &ctx.accounts.token_owner_account_b
+    } else {
+        &ctx.accounts.token_owner_account_a
+    };
+
+    let withdraw_amount = if amount == 0u64 {
+        quote_account.amount
+    } else {
+        quote_account.amount.checked_sub(quote_balance_before).ok_or(AqueductError::MathOverflow)?
+    };
+    let fee_schedule = load_fee_schedule(&ctx.accounts.fee_schedule)?;
+    let fee_tiers = load_fee_tiers(&ctx.accounts.fee_tiers)?;
+    let discount_bps = user_fee_discount_bps(&fee_tiers, &ctx.accounts.user_pda, &ctx.accounts.stake, ctx.program_id)?;
+    let withdrawal_fee = fee_amount(withdraw_amount, discounted_bps(fee_schedule.withdrawal_fee_bps_for(&ctx.accounts.quote_mint.key()), discount_bps))?;
+
+    let referral = referral_token_account(&ctx.accounts.user_pda, &ctx.accounts.referral_token, &ctx.accounts.quote_mint.key(), ctx.program_id)?;
+    take_service_fee(
+        &ctx.accounts.user_pda,
+        quote_account,
+        &ctx.accounts.token_aqueduct,
+        referral.as_ref(),
+        fee_schedule.referral_share_bps,
+        ctx.accounts.token_program.to_account_info(),
+        signer_seeds,
+        Pubkey::default(),
+        withdrawal_fee,
+    )?;
+
+    token::transfer(
+        CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            token::Transfer {
+                from: quote_account.to_account_info(),
+                to: ctx.accounts.destination_ata.to_account_info(),
+                authority: ctx.accounts.user_pda.to_account_info(),
+            },
+        ).with_signer(signer_seeds),
+        withdraw_amount - withdrawal_fee,
+    )?;
+
+    ctx.accounts.user_pda.record_withdrawal(Some(withdraw_amount));
+
+    record_mint_withdraw(&ctx.accounts.quote_mint_config, withdraw_amount)?;
+
+    emit!(WithdrawEvent {
+        user: ctx.accounts.user_pda.user,
+        user_pda: ctx.accounts.user_pda.key(),
+        mint: ctx.accounts.quote_mint.key(),
+        amount: withdraw_amount - withdrawal_fee,
+        balance: quote_account.amount - withdraw_amount,
+        slot: Clock::get()?.slot,
+    });
+
+    Ok(())
+}
+
+impl<'info> FundsWithdrawSwap<'info> {
+
+    fn into_swap_context(&self) -> CpiContext<'_, '_, '_, 'info, Swap<'info>> {
+        let cpi_accounts = Swap {
+            token_program: self.token_program.to_account_info(),
+            token_authority: self.user_pda.to_account_info(),
+            whirlpool: self.whirlpool.to_account_info(),
+            token_owner_account_a: self.token_owner_account_a.to_account_info(),
+            token_vault_a: self.token_vault_a.to_account_info(),
+            token_owner_account_b: self.token_owner_account_b.to_account_info(),
+            token_vault_b: self.token_vault_b.to_account_info(),
+            tick_array_0: self.tick_array_0.to_account_info(),
+            tick_array_1: self.tick_array_1.to_account_info(),
+            tick_array_2: self.tick_array_2.to_account_info(),
+            oracle: self.oracle.to_account_info(),
+        };
+        let cpi_program = self.whirlpool_program.to_account_info();
+        CpiContext::new(cpi_program, cpi_accounts)
+    }
+
+}
@@ -1,23 +1,115 @@
 pub mod add_pool_config;
+pub mod add_quote_config;
 pub mod position_close;
 pub mod position_open;
+pub mod position_bundle_init;
+pub mod position_open_bundled;
 pub mod funds_deposit;
+pub mod funds_deposit_sol;
+pub mod funds_deposit_swap;
 pub mod funds_withdraw;
+pub mod funds_withdraw_swap;
 pub mod user_create;
 pub mod user_settings;
 pub mod user_delete;
 pub mod position_fund;
 pub mod position_swap;
 pub mod init_ata;
+pub mod vault_create;
+pub mod vault_deposit;
+pub mod vault_withdraw;
+pub mod vault_position_open;
+pub mod vault_position_fund;
+pub mod vault_position_swap;
+pub mod vault_position_close;
+pub mod vault_refresh_price;
+pub mod vault_reward_swap;
+pub mod pool_refresh_price;
+pub mod reward_route_refresh_price;
+pub mod withdraw_in_kind;
+pub mod emergency_exit;
+pub mod user_migrate;
+pub mod user_count_positions;
+pub mod user_transfer_ownership;
+pub mod user_accept_ownership;
+pub mod user_set_delegate;
+pub mod user_set_quote_mint;
+pub mod set_mint_config;
+pub mod set_pool_config_caps;
+pub mod set_pool_approval;
+pub mod set_config;
+pub mod set_rate_limits;
+pub mod set_fee_schedule;
+pub mod set_fee_override;
+pub mod set_referral_share;
+pub mod set_fee_tiers;
+pub mod set_reward_route;
+pub mod allowlist_add;
+pub mod allowlist_remove;
+pub mod user_set_exit_only;
+pub mod user_set_intents_required;
+pub mod user_set_loss_guard;
+pub mod user_set_range;
+pub mod referral_claim;
+pub mod stake_deposit;
+pub mod stake_withdraw;
+pub mod user_set_reward_conversion;
+pub mod reward_swap;
 
 pub use add_pool_config::*;
+pub use add_quote_config::*;
 pub use position_close::*;
 pub use position_open::*;
+pub use position_bundle_init::*;
+pub use position_open_bundled::*;
 pub use funds_deposit::*;
+pub use funds_deposit_sol::*;
+pub use funds_deposit_swap::*;
 pub use funds_withdraw::*;
+pub use funds_withdraw_swap::*;
 pub use user_create::*;
 pub use user_settings::*;
 pub use user_delete::*;
 pub use position_fund::*;
 pub use position_swap::*;
-pub use init_ata::*;
\ No newline at end of file
+pub use init_ata::*;
+pub use vault_create::*;
+pub use vault_deposit::*;
+pub use vault_withdraw::*;
+pub use vault_position_open::*;
+pub use vault_position_fund::*;
+pub use vault_position_swap::*;
+pub use vault_position_close::*;
+pub use vault_refresh_price::*;
+pub use vault_reward_swap::*;
+pub use pool_refresh_price::*;
+pub use reward_route_refresh_price::*;
+pub use withdraw_in_kind::*;
+pub use emergency_exit::*;
+pub use user_migrate::*;
+pub use user_count_positions::*;
+pub use user_transfer_ownership::*;
+pub use user_accept_ownership::*;
+pub use user_set_delegate::*;
+pub use user_set_quote_mint::*;
+pub use set_mint_config::*;
+pub use set_pool_config_caps::*;
+pub use set_pool_approval::*;
+pub use set_config::*;
+pub use set_rate_limits::*;
+pub use set_fee_schedule::*;
+pub use set_fee_override::*;
+pub use set_referral_share::*;
+pub use set_fee_tiers::*;
+pub use set_reward_route::*;
+pub use allowlist_add::*;
+pub use allowlist_remove::*;
+pub use user_set_exit_only::*;
+pub use user_set_intents_required::*;
+pub use user_set_loss_guard::*;
+pub use user_set_range::*;
+pub use referral_claim::*;
+pub use stake_deposit::*;
+pub use stake_withdraw::*;
+pub use user_set_reward_conversion::*;
+pub use reward_swap::*;
\ No newline at end of file
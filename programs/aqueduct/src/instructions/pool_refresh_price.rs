@@ -0,0 +1,29 @@
+use anchor_lang::prelude::*;
+use whirlpool::state::Whirlpool;
+
+use crate::state::*;
+
+#[derive(Accounts)]
+pub struct PoolRefreshPrice<'info> {
+    pub auth: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [
+            POOL_CONFIG_PDA_PREFIX,
+            whirlpool.key().as_ref()
+        ],
+        bump,
+    )]
+    pub pool_config: Box<Account<'info, PoolConfig>>,
+    pub whirlpool: Box<Account<'info, Whirlpool>>,
+}
+
+// anyone may move the pool reference price, it only ever steps towards the pool price
+pub fn handler(ctx: Context<PoolRefreshPrice>,
+) -> Result<()> {
+
+    ctx.accounts.pool_config.refresh_reference_price(ctx.accounts.whirlpool.sqrt_price, Clock::get()?.slot)?;
+
+    Ok(())
+}
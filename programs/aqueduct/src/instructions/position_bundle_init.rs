@@ -0,0 +1,75 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{associated_token::AssociatedToken, token::Token};
+use whirlpool::cpi::accounts::InitializePositionBundle;
+
+use crate::errors::AqueductError;
+use crate::state::*;
+use crate::WhirlpoolProgram;
+
+#[derive(Accounts)]
+pub struct PositionBundleInit<'info> {
+    #[account(mut)]
+    pub auth: Signer<'info>,
+
+    /// CHECK:
+    #[account(constraint = user.key() == user_pda.user)]
+    pub user: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [
+            USER_PDA_PREFIX,
+            user_pda.vault_id.as_ref()
+        ],
+        bump,
+        constraint = user_pda.position_bundle_mint == Pubkey::default() @ AqueductError::PositionBundleExists,
+    )]
+    pub user_pda: Box<Account<'info, User>>,
+
+    pub whirlpool_program: Program<'info, WhirlpoolProgram>,
+
+    /// CHECK: Safe, initialized by the whirlpool program
+    #[account(mut)]
+    pub position_bundle: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub position_bundle_mint: Signer<'info>,
+    /// CHECK: Safe, initialized by the whirlpool program
+    #[account(mut)]
+    pub position_bundle_token_account: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+pub fn handler<'info>(ctx: Context<'_, '_, '_, 'info, PositionBundleInit<'info>>,
+) -> Result<()> {
+
+    whirlpool::cpi::initialize_position_bundle(
+        ctx.accounts.into_initialize_position_bundle_context()
+    )?;
+
+    ctx.accounts.user_pda.position_bundle_mint = ctx.accounts.position_bundle_mint.key();
+
+    Ok(())
+}
+
+impl<'info> PositionBundleInit<'info> {
+
+    fn into_initialize_position_bundle_context(&self) -> CpiContext<'_, '_, '_, 'info, InitializePositionBundle<'info>> {
+        let cpi_accounts = InitializePositionBundle {
+            position_bundle: self.position_bundle.to_account_info(),
+            position_bundle_mint: self.position_bundle_mint.to_account_info(),
+            position_bundle_token_account: self.position_bundle_token_account.to_account_info(),
+            position_bundle_owner: self.user_pda.to_account_info(),
+            funder: self.auth.to_account_info(),
+            token_program: self.token_program.to_account_info(),
+            system_program: self.system_program.to_account_info(),
+            rent: self.rent.to_account_info(),
+            associated_token_program: self.associated_token_program.to_account_info()
+        };
+        let cpi_program = self.whirlpool_program.to_account_info();
+        CpiContext::new(cpi_program, cpi_accounts)
+    }
+
+}
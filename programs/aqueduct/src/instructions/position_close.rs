@@ -1,27 +1,60 @@
 use anchor_lang::prelude::*;
 use anchor_spl::associated_token::AssociatedToken;
 use anchor_spl::token::{Mint, Token, TokenAccount};
-use anchor_spl::token;
-use whirlpool::state::{Position, TickArray, Whirlpool};
-use whirlpool::cpi::accounts::{CollectFees, CollectReward, ModifyLiquidity, ClosePosition, UpdateFeesAndRewards};
-
+use anchor_spl::{associated_token, token};
+use whirlpool::state::{Position, TickArray, Whirlpool, NUM_REWARDS};
+use whirlpool::cpi::accounts::{ClosePosition, CloseBundledPosition};
+
+use crate::events::*;
+use crate::fees::{discounted_bps, fee_amount, load_fee_schedule, load_fee_tiers, pay_referral_share, referral_token_account, split_fee_value, take_service_fee, user_fee_discount_bps};
+use crate::errors::AqueductError;
+use crate::math::{realized_pnl, value_in_user_quote};
+use crate::positions::PositionAccounts;
+use crate::rewards::reward_slot_accounts;
 use crate::state::*;
 use crate::WhirlpoolProgram;
 
 #[derive(Accounts)]
+#[instruction(
+    bundle_index: Option<u16>,
+)]
 pub struct PositionClose<'info> {
     #[account(mut)]
     pub auth: Signer<'info>,
 
     /// CHECK:
-    #[account(mut)]
+    #[account(mut, constraint = user.key() == user_pda.user)]
     pub user: UncheckedAccount<'info>,
-    #[account(mut)]
+    #[account(
+        mut,
+        seeds = [
+            USER_PDA_PREFIX,
+            user_pda.vault_id.as_ref()
+        ],
+        bump,
+    )]
     pub user_pda: Box<Account<'info, User>>,
 
+    #[account(
+        seeds = [
+            RATE_LIMITS_PDA_PREFIX
+        ],
+        bump,
+    )]
+    pub rate_limits: Box<Account<'info, RateLimits>>,
+
     pub whirlpool_program: Program<'info, WhirlpoolProgram>,
     #[account(mut)]
     pub whirlpool: Box<Account<'info, Whirlpool>>,
+    #[account(
+        mut,
+        seeds = [
+            POOL_CONFIG_PDA_PREFIX,
+            whirlpool.key().as_ref()
+        ],
+        bump,
+    )]
+    pub pool_config: Box<Account<'info, PoolConfig>>,
 
     #[account(mut, has_one = whirlpool)]
     pub position: Box<Account<'info, Position>>,
@@ -35,21 +68,58 @@ pub struct PositionClose<'info> {
 
     #[account(mut, address = position.position_mint)]
     pub position_mint: Account<'info, Mint>,
+    /// CHECK: the user's position bundle, only used when closing a bundled position and checked by the whirlpool
+    #[account(mut)]
+    pub position_bundle: UncheckedAccount<'info>,
+
+    /// CHECK: the fee schedule PDA, read through load_fee_schedule which falls back to the defaults
+    #[account(
+        seeds = [
+            FEE_SCHEDULE_PDA_PREFIX
+        ],
+        bump,
+    )]
+    pub fee_schedule: UncheckedAccount<'info>,
+    /// CHECK: the fee tiers PDA, read through load_fee_tiers which falls back to no discount
+    #[account(
+        seeds = [
+            FEE_TIERS_PDA_PREFIX
+        ],
+        bump,
+    )]
+    pub fee_tiers: UncheckedAccount<'info>,
+    /// CHECK: the user's stake PDA, matched and only read once it exists in user_fee_discount_bps
+    pub stake: UncheckedAccount<'info>,
 
     #[account(mut, constraint = token_owner_account_a.mint == whirlpool.token_mint_a)]
     pub token_owner_account_a: Box<Account<'info, TokenAccount>>,
     #[account(mut, address = whirlpool.token_vault_a)]
     pub token_vault_a: Box<Account<'info, TokenAccount>>,
-    #[account(mut)]
+    #[account(
+        mut,
+        constraint = token_aqueduct_a.mint == whirlpool.token_mint_a,
+        constraint = token_aqueduct_a.owner == B_SERVICE @ AqueductError::InvalidTreasuryAccount,
+    )]
     pub token_aqueduct_a: Box<Account<'info, TokenAccount>>,
 
     #[account(mut, constraint = token_owner_account_b.mint == whirlpool.token_mint_b)]
     pub token_owner_account_b: Box<Account<'info, TokenAccount>>,
     #[account(mut, address = whirlpool.token_vault_b)]
     pub token_vault_b: Box<Account<'info, TokenAccount>>,
-    #[account(mut)]
+    #[account(
+        mut,
+        constraint = token_aqueduct_b.mint == whirlpool.token_mint_b,
+        constraint = token_aqueduct_b.owner == B_SERVICE @ AqueductError::InvalidTreasuryAccount,
+    )]
     pub token_aqueduct_b: Box<Account<'info, TokenAccount>>,
 
+    /// CHECK: the referrer's token account for mint a, only read when the user has a referrer
+    #[account(mut)]
+    pub referral_token_a: UncheckedAccount<'info>,
+    /// CHECK: the referrer's token account for mint b, only read when the user has a referrer
+    #[account(mut)]
+    pub referral_token_b: UncheckedAccount<'info>,
+
     #[account(mut, has_one = whirlpool)]
     pub tick_array_lower: AccountLoader<'info, TickArray>,
     #[account(mut, has_one = whirlpool)]
@@ -59,45 +129,80 @@ pub struct PositionClose<'info> {
     #[account(address = token::ID)]
     pub token_program: Program<'info, Token>,
     pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
 
-    // remaining_accounts are the (reward_owner_account, reward_vault) pairs for the whirlpool
+    // remaining_accounts are (reward_mint, reward_owner_account, reward_vault, service_ata, referral_ata) groups,
+    // one per whirlpool reward slot in slot order, referral_ata is only read when the user has a referrer
 }
 
 pub fn handler<'info>(ctx: Context<'_, '_, '_, 'info, PositionClose<'info>>,
+    bundle_index: Option<u16>,
 ) -> Result<()> {
 
-    let (_user_pda, user_pda_bump) = Pubkey::find_program_address(&[USER_PDA_PREFIX, ctx.accounts.user.key.as_ref()], ctx.program_id);
-    let seeds = &[USER_PDA_PREFIX, ctx.accounts.user.key.as_ref(), &[user_pda_bump]];
+    let vault_id = ctx.accounts.user_pda.vault_id;
+    let (_user_pda, user_pda_bump) = Pubkey::find_program_address(&[USER_PDA_PREFIX, vault_id.as_ref()], ctx.program_id);
+    let seeds = &[USER_PDA_PREFIX, vault_id.as_ref(), &[user_pda_bump]];
     let signer_seeds = &[&seeds[..]];
 
-    ctx.accounts.position.reload()?;
-
-    if ctx.accounts.position.liquidity > 0 {
-        msg!("starting update_fees_and_rewards");
-        whirlpool::cpi::update_fees_and_rewards(
-            ctx.accounts.into_update_fees_and_rewards_context().with_signer(signer_seeds)
-        )?;
-        msg!("finished update_fees_and_rewards");
-    }
+    // the open that follows a close counts the rebalance, the close only has to be allowed
+    let now = Clock::get()?.unix_timestamp;
+    let rate_limits = &ctx.accounts.rate_limits;
+    ctx.accounts.user_pda.rate_limit_counters.check_rebalance(rate_limits, now)?;
 
-    msg!("starting collect_fees");
-    let token_a_start = ctx.accounts.token_owner_account_a.amount;
-    let token_b_start = ctx.accounts.token_owner_account_b.amount;
-
-    whirlpool::cpi::collect_fees(
-        ctx.accounts.into_collect_fees_context().with_signer(signer_seeds)
-    )?;
-
-    ctx.accounts.token_owner_account_a.reload()?;
-    ctx.accounts.token_owner_account_b.reload()?;
+    let slot = Clock::get()?.slot;
+    let user = ctx.accounts.user_pda.user;
+    let user_pda = ctx.accounts.user_pda.key();
+    let whirlpool_key = ctx.accounts.whirlpool.key();
+    let position_key = ctx.accounts.position.key();
+    let fee_schedule = load_fee_schedule(&ctx.accounts.fee_schedule)?;
+    let fee_tiers = load_fee_tiers(&ctx.accounts.fee_tiers)?;
+    let discount_bps = user_fee_discount_bps(&fee_tiers, &ctx.accounts.user_pda, &ctx.accounts.stake, ctx.program_id)?;
 
-    let token_a_end = ctx.accounts.token_owner_account_a.amount;
-    let token_b_end = ctx.accounts.token_owner_account_b.amount;
+    ctx.accounts.position.reload()?;
 
-    let service_amount_token_a = ((token_a_end - token_a_start) * AQUEDUCT_FEE_PERCENT)/100;
-    let service_amount_token_b = ((token_b_end - token_b_start) * AQUEDUCT_FEE_PERCENT)/100;
+    let token_owner_mint_a = ctx.accounts.token_owner_account_a.mint;
+    let token_owner_mint_b = ctx.accounts.token_owner_account_b.mint;
+
+    let position = ctx.accounts.position_accounts();
+    position.update_fees_and_rewards(ctx.accounts.position.liquidity)?;
+    let (fees_a, fees_b) = position.collect_fees(signer_seeds)?;
+
+    emit!(FeesCollected {
+        user,
+        user_pda,
+        whirlpool: whirlpool_key,
+        position: position_key,
+        amount_a: fees_a,
+        amount_b: fees_b,
+        slot,
+    });
+
+    // under the high-water mark mode collected fees are charged as part of the net profit once the position is closed
+    let (service_amount_token_a, service_amount_token_b) = if fee_schedule.high_water_mark_mode() {
+        (0, 0)
+    } else {
+        (
+            fee_amount(fees_a, discounted_bps(fee_schedule.performance_fee_bps_for(&token_owner_mint_a), discount_bps))?,
+            fee_amount(fees_b, discounted_bps(fee_schedule.performance_fee_bps_for(&token_owner_mint_b), discount_bps))?,
+        )
+    };
+
+    let referral_share_bps = fee_schedule.referral_share_bps;
+    let referral_a = referral_token_account(&ctx.accounts.user_pda, &ctx.accounts.referral_token_a, &token_owner_mint_a, ctx.program_id)?;
+    let referral_b = referral_token_account(&ctx.accounts.user_pda, &ctx.accounts.referral_token_b, &token_owner_mint_b, ctx.program_id)?;
 
     if service_amount_token_a > 0 {
+        let service_amount_token_a = service_amount_token_a - pay_referral_share(
+            &ctx.accounts.user_pda,
+            ctx.accounts.user_pda.to_account_info(),
+            ctx.accounts.token_owner_account_a.to_account_info(),
+            referral_a.as_ref(),
+            ctx.accounts.token_program.to_account_info(),
+            signer_seeds,
+            service_amount_token_a,
+            referral_share_bps,
+        )?;
+
         token::transfer(
             CpiContext::new(
                 ctx.accounts.token_program.to_account_info(),
@@ -109,9 +214,30 @@ pub fn handler<'info>(ctx: Context<'_, '_, '_, 'info, PositionClose<'info>>,
             ).with_signer(signer_seeds),
             service_amount_token_a,
         )?;
+
+        emit!(ProtocolFeeTaken {
+            user,
+            user_pda,
+            position: position_key,
+            mint: ctx.accounts.token_owner_account_a.mint,
+            destination: ctx.accounts.token_aqueduct_a.key(),
+            amount: service_amount_token_a,
+            slot,
+        });
     }
 
     if service_amount_token_b > 0 {
+        let service_amount_token_b = service_amount_token_b - pay_referral_share(
+            &ctx.accounts.user_pda,
+            ctx.accounts.user_pda.to_account_info(),
+            ctx.accounts.token_owner_account_b.to_account_info(),
+            referral_b.as_ref(),
+            ctx.accounts.token_program.to_account_info(),
+            signer_seeds,
+            service_amount_token_b,
+            referral_share_bps,
+        )?;
+
         token::transfer(
             CpiContext::new(
                 ctx.accounts.token_program.to_account_info(),
@@ -123,127 +249,264 @@ pub fn handler<'info>(ctx: Context<'_, '_, '_, 'info, PositionClose<'info>>,
             ).with_signer(signer_seeds),
             service_amount_token_b
         )?;
-    }
 
-    msg!("finished collect_fees");
+        emit!(ProtocolFeeTaken {
+            user,
+            user_pda,
+            position: position_key,
+            mint: ctx.accounts.token_owner_account_b.mint,
+            destination: ctx.accounts.token_aqueduct_b.key(),
+            amount: service_amount_token_b,
+            slot,
+        });
+    }
 
     ctx.accounts.position.reload()?;
 
-    msg!("starting collect_reward");
-    for (i, reward_pair) in ctx.remaining_accounts.chunks(3).enumerate() {
+    let quote_mint = ctx.accounts.user_pda.effective_quote_mint();
+    let mut rewards_a: u64 = 0;
+    let mut rewards_b: u64 = 0;
+
+    for i in 0..NUM_REWARDS {
+        // uninitialized slots and slots with nothing owed need no accounts
+        if !ctx.accounts.whirlpool.reward_infos[i].initialized() || ctx.accounts.position.reward_infos[i].amount_owed == 0 {
+            continue;
+        }
+
+        let reward = reward_slot_accounts(&ctx.accounts.whirlpool, i, &user_pda, ctx.remaining_accounts)?;
+
+        if reward.owner_account.data_is_empty() {
+            associated_token::create(
+                CpiContext::new(
+                    ctx.accounts.associated_token_program.to_account_info(),
+                    associated_token::Create {
+                        payer: ctx.accounts.auth.to_account_info(),
+                        associated_token: reward.owner_account.clone(),
+                        authority: ctx.accounts.user_pda.to_account_info(),
+                        mint: reward.mint.clone(),
+                        system_program: ctx.accounts.system_program.to_account_info(),
+                        token_program: ctx.accounts.token_program.to_account_info(),
+                        rent: ctx.accounts.rent.to_account_info(),
+                    },
+                ),
+            )?;
+        }
 
-        msg!("i: {}", i);
-        msg!("amount_owed: {}", ctx.accounts.position.reward_infos[i].amount_owed);
+        let reward_amount = position.collect_reward(signer_seeds, i, reward.owner_account, reward.vault)?;
+        let reward_owner_account: Account<'info, TokenAccount> = Account::try_from(reward.owner_account)?;
+        let reward_mint = reward_owner_account.mint;
+
+        emit!(RewardCollected {
+            user,
+            user_pda,
+            whirlpool: whirlpool_key,
+            position: position_key,
+            reward_index: i as u8,
+            mint: reward_mint,
+            amount: reward_amount,
+            slot,
+        });
+
+        // under the high-water mark mode a reward paid in one of the pool's tokens counts towards the profit
+        // on the close, any other reward mint has no price here and is still skimmed as it is collected
+        let reward_pair_amounts = if reward_mint == token_owner_mint_a {
+            Some((reward_amount, 0))
+        } else if reward_mint == token_owner_mint_b {
+            Some((0, reward_amount))
+        } else {
+            None
+        };
 
-        if ctx.accounts.position.reward_infos[i].amount_owed > 0 {
-            let reward_owner_account: Account<'info, TokenAccount> = Account::try_from(&reward_pair[0])?;
-            let reward_vault: Account<TokenAccount> = Account::try_from(&reward_pair[1])?;
-            let service_ata: Account<'info, TokenAccount> = Account::try_from(&reward_pair[2])?;
+        let service_amount = match reward_pair_amounts {
+            Some((reward_a, reward_b)) if fee_schedule.high_water_mark_mode() => {
+                rewards_a = rewards_a.checked_add(reward_a).ok_or(AqueductError::MathOverflow)?;
+                rewards_b = rewards_b.checked_add(reward_b).ok_or(AqueductError::MathOverflow)?;
+                0
+            }
+            _ => fee_amount(reward_amount, discounted_bps(fee_schedule.performance_fee_bps_for(&reward_mint), discount_bps))?,
+        };
 
-            let tokens_start = reward_owner_account.amount;
+        if service_amount > 0 {
+            let referral = referral_token_account(&ctx.accounts.user_pda, reward.referral, &reward_mint, ctx.program_id)?;
+            let service_amount = service_amount - pay_referral_share(
+                &ctx.accounts.user_pda,
+                ctx.accounts.user_pda.to_account_info(),
+                reward_owner_account.to_account_info(),
+                referral.as_ref(),
+                ctx.accounts.token_program.to_account_info(),
+                signer_seeds,
+                service_amount,
+                referral_share_bps,
+            )?;
 
-            whirlpool::cpi::collect_reward(
-                ctx.accounts.into_collect_rewards_context(reward_owner_account, reward_vault).with_signer(signer_seeds),
-                i as u8
+            token::transfer(
+                CpiContext::new(
+                    ctx.accounts.token_program.to_account_info(),
+                    token::Transfer {
+                        from: reward_owner_account.to_account_info(),
+                        to: reward.treasury.to_account_info(),
+                        authority: ctx.accounts.user_pda.to_account_info()
+                    },
+                ).with_signer(signer_seeds),
+                service_amount
             )?;
 
-            let reward_owner_account_after: Account<'info, TokenAccount> = Account::try_from(&reward_pair[0])?;
-            let service_amount = ((reward_owner_account_after.amount - tokens_start) * AQUEDUCT_FEE_PERCENT)/100;
-
-            if service_amount > 0 {
-                token::transfer(
-                    CpiContext::new(
-                        ctx.accounts.token_program.to_account_info(),
-                        token::Transfer {
-                            from: reward_owner_account_after.to_account_info(),
-                            to: service_ata.to_account_info(),
-                            authority: ctx.accounts.user_pda.to_account_info()
-                        },
-                    ).with_signer(signer_seeds),
-                    service_amount
-                )?;
-            }
+            emit!(ProtocolFeeTaken {
+                user,
+                user_pda,
+                position: position_key,
+                mint: reward_mint,
+                destination: reward.treasury.key(),
+                amount: service_amount,
+                slot,
+            });
         }
     }
-    msg!("finished collect_reward");
-
-    if ctx.accounts.position.liquidity > 0 {
-        msg!("starting decrease_liquidity");
-        whirlpool::cpi::decrease_liquidity(
-            ctx.accounts.into_reduce_liquidity_context().with_signer(signer_seeds),
-            ctx.accounts.position.liquidity,
-            0u64,
-            0u64
-        )?;
-        msg!("finished decrease_liquidity");
+
+    let liquidity = ctx.accounts.position.liquidity;
+    let (amount_a, amount_b) = position.decrease_liquidity(signer_seeds, liquidity)?;
+
+    ctx.accounts.token_owner_account_a.reload()?;
+    ctx.accounts.token_owner_account_b.reload()?;
+
+    let tick_lower_index = ctx.accounts.position.tick_lower_index;
+    let tick_upper_index = ctx.accounts.position.tick_upper_index;
+
+    match bundle_index {
+        // a bundled position hands its slot back to the user's bundle, there is no mint to burn
+        Some(bundle_index) => whirlpool::cpi::close_bundled_position(
+            ctx.accounts.into_close_bundled_position_context().with_signer(signer_seeds),
+            bundle_index
+        )?,
+        None => whirlpool::cpi::close_position(
+            ctx.accounts.into_close_position_context().with_signer(signer_seeds)
+        )?,
     }
 
-    ctx.accounts.position.reload()?;
-    msg!("ctx.accounts.position.liquidity: {}", ctx.accounts.position.liquidity);
+    ctx.accounts.pool_config.remove_liquidity(liquidity);
 
-    for ri in ctx.accounts.position.reward_infos {
-        msg!("amount_owed: {}", ri.amount_owed);
+    // what came out of the position against what was put in, fees and rewards are left out;
+    // both sides are valued at the pool reference price
+    let reference_sqrt_price = ctx.accounts.pool_config.valuation_sqrt_price()?;
+    let value_out = value_in_user_quote(amount_a, amount_b, reference_sqrt_price, &ctx.accounts.whirlpool, &quote_mint)?;
+
+    // the management fee accrues on the cost basis across all of the user's positions, the share of it
+    // that belongs to the liquidity leaving is charged out of what came out
+    let management_fee_bps = discounted_bps(fee_schedule.management_fee_bps, discount_bps);
+    ctx.accounts.user_pda.accrue_management_fee(management_fee_bps, now)?;
+    let management_fee_value = ctx.accounts.user_pda.release_management_fee(liquidity);
+    let (management_fee_a, management_fee_b) = split_fee_value(amount_a, amount_b, management_fee_value, value_out)?;
+
+    let cost_basis = ctx.accounts.user_pda.release_cost_basis(liquidity);
+
+    if ctx.accounts.user_pda.record_loss(cost_basis.saturating_sub(value_out), Clock::get()?.unix_timestamp) {
+        emit_loss_guard_triggered(&ctx.accounts.user_pda)?;
     }
 
-    msg!("starting close_position");
-    whirlpool::cpi::close_position(
-        ctx.accounts.into_close_position_context().with_signer(signer_seeds)
-    )?;
-    msg!("finished close_position");
+    // positions opened before cost basis tracking have nothing to measure profit against
+    if fee_schedule.high_water_mark_mode() && cost_basis > 0 {
+        let earned_a = fees_a.checked_add(rewards_a).ok_or(AqueductError::MathOverflow)?;
+        let earned_b = fees_b.checked_add(rewards_b).ok_or(AqueductError::MathOverflow)?;
+        let earned_value = value_in_user_quote(earned_a, earned_b, reference_sqrt_price, &ctx.accounts.whirlpool, &quote_mint)?;
+        let value = value_out.checked_add(earned_value).ok_or(AqueductError::MathOverflow)?;
+        let pnl = realized_pnl(value, cost_basis)?;
+        let fee_bps = discounted_bps(fee_schedule.performance_fee_bps_for(&quote_mint), discount_bps);
+        let fee_value = ctx.accounts.user_pda.record_realized_pnl(pnl, fee_bps)?;
+
+        let (fee_a, fee_b) = split_fee_value(amount_a + earned_a, amount_b + earned_b, fee_value, value)?;
+
+        for (from, to, referral, amount) in [
+            (&ctx.accounts.token_owner_account_a, &ctx.accounts.token_aqueduct_a, referral_a.as_ref(), fee_a),
+            (&ctx.accounts.token_owner_account_b, &ctx.accounts.token_aqueduct_b, referral_b.as_ref(), fee_b),
+        ] {
+            if amount == 0 {
+                continue;
+            }
 
-    Ok(())
-}
+            let amount = amount - pay_referral_share(
+                &ctx.accounts.user_pda,
+                ctx.accounts.user_pda.to_account_info(),
+                from.to_account_info(),
+                referral,
+                ctx.accounts.token_program.to_account_info(),
+                signer_seeds,
+                amount,
+                referral_share_bps,
+            )?;
 
-impl<'info> PositionClose<'info> {
+            token::transfer(
+                ctx.accounts.into_transfer_context(from.to_account_info(), to.to_account_info()).with_signer(signer_seeds),
+                amount,
+            )?;
 
-    fn into_update_fees_and_rewards_context(&self) -> CpiContext<'_, '_, '_, 'info, UpdateFeesAndRewards<'info>> {
-        let cpi_accounts = UpdateFeesAndRewards {
-            whirlpool: self.whirlpool.to_account_info(),
-            position: self.position.to_account_info(),
-            tick_array_lower: self.tick_array_lower.to_account_info(),
-            tick_array_upper: self.tick_array_upper.to_account_info()
-        };
-        let cpi_program = self.whirlpool_program.to_account_info();
-        CpiContext::new(cpi_program, cpi_accounts)
+            emit!(ProtocolFeeTaken {
+                user,
+                user_pda,
+                position: position_key,
+                mint: from.mint,
+                destination: to.key(),
+                amount,
+                slot,
+            });
+        }
+
+        ctx.accounts.token_owner_account_a.reload()?;
+        ctx.accounts.token_owner_account_b.reload()?;
     }
 
-    fn into_collect_fees_context(&self) -> CpiContext<'_, '_, '_, 'info, CollectFees<'info>> {
-        let cpi_accounts = CollectFees {
-            whirlpool: self.whirlpool.to_account_info(),
-            position_authority: self.user_pda.to_account_info(),
-            position: self.position.to_account_info(),
-            position_token_account: self.position_token_account.to_account_info(),
-            token_owner_account_a: self.token_owner_account_a.to_account_info(),
-            token_vault_a: self.token_vault_a.to_account_info(),
-            token_owner_account_b: self.token_owner_account_b.to_account_info(),
-            token_vault_b: self.token_vault_b.to_account_info(),
-            token_program: self.token_program.to_account_info()
-        };
-        let cpi_program = self.whirlpool_program.to_account_info();
-        CpiContext::new(cpi_program, cpi_accounts)
+    for (from, to, referral, amount) in [
+        (&ctx.accounts.token_owner_account_a, &ctx.accounts.token_aqueduct_a, referral_a.as_ref(), management_fee_a),
+        (&ctx.accounts.token_owner_account_b, &ctx.accounts.token_aqueduct_b, referral_b.as_ref(), management_fee_b),
+    ] {
+        take_service_fee(
+            &ctx.accounts.user_pda,
+            from,
+            to,
+            referral,
+            referral_share_bps,
+            ctx.accounts.token_program.to_account_info(),
+            signer_seeds,
+            position_key,
+            amount,
+        )?;
     }
 
-    fn into_collect_rewards_context(&self,
-        reward_owner_account: Account<'info, TokenAccount>,
-        reward_vault: Account<'info, TokenAccount>
-    ) -> CpiContext<'_, '_, '_, 'info, CollectReward<'info>> {
-        let cpi_accounts = CollectReward {
-            whirlpool: self.whirlpool.to_account_info(),
-            position_authority: self.user_pda.to_account_info(),
-            position: self.position.to_account_info(),
-            position_token_account: self.position_token_account.to_account_info(),
-            reward_owner_account: reward_owner_account.to_account_info(),
-            reward_vault: reward_vault.to_account_info(),
-            token_program: self.token_program.to_account_info()
+    ctx.accounts.user_pda.open_positions = ctx.accounts.user_pda.open_positions.saturating_sub(1);
+    ctx.accounts.user_pda.last_service_action_slot = slot;
+
+    emit!(PositionClosed {
+        user,
+        user_pda,
+        whirlpool: whirlpool_key,
+        position: position_key,
+        tick_lower_index,
+        tick_upper_index,
+        liquidity,
+        amount_a,
+        amount_b,
+        sqrt_price: ctx.accounts.whirlpool.sqrt_price,
+        slot,
+    });
+
+    Ok(())
+}
+
+impl<'info> PositionClose<'info> {
+
+    fn into_transfer_context(&self, from: AccountInfo<'info>, to: AccountInfo<'info>) -> CpiContext<'_, '_, '_, 'info, token::Transfer<'info>> {
+        let cpi_accounts = token::Transfer {
+            from,
+            to,
+            authority: self.user_pda.to_account_info(),
         };
-        let cpi_program = self.whirlpool_program.to_account_info();
+        let cpi_program = self.token_program.to_account_info();
         CpiContext::new(cpi_program, cpi_accounts)
     }
 
-    fn into_reduce_liquidity_context(&self) -> CpiContext<'_, '_, '_, 'info, ModifyLiquidity<'info>> {
-        let cpi_accounts = ModifyLiquidity {
+    fn position_accounts(&self) -> PositionAccounts<'info> {
+        PositionAccounts {
+            whirlpool_program: self.whirlpool_program.to_account_info(),
             whirlpool: self.whirlpool.to_account_info(),
-            token_program: self.token_program.to_account_info(),
             position_authority: self.user_pda.to_account_info(),
             position: self.position.to_account_info(),
             position_token_account: self.position_token_account.to_account_info(),
@@ -252,7 +515,18 @@ impl<'info> PositionClose<'info> {
             token_vault_a: self.token_vault_a.to_account_info(),
             token_vault_b: self.token_vault_b.to_account_info(),
             tick_array_lower: self.tick_array_lower.to_account_info(),
-            tick_array_upper: self.tick_array_upper.to_account_info()
+            tick_array_upper: self.tick_array_upper.to_account_info(),
+            token_program: self.token_program.to_account_info(),
+        }
+    }
+
+    fn into_close_bundled_position_context(&self) -> CpiContext<'_, '_, '_, 'info, CloseBundledPosition<'info>> {
+        let cpi_accounts = CloseBundledPosition {
+            bundled_position: self.position.to_account_info(),
+            position_bundle: self.position_bundle.to_account_info(),
+            position_bundle_token_account: self.position_token_account.to_account_info(),
+            position_bundle_authority: self.user_pda.to_account_info(),
+            receiver: self.auth.to_account_info(),
         };
         let cpi_program = self.whirlpool_program.to_account_info();
         CpiContext::new(cpi_program, cpi_accounts)
@@ -3,6 +3,9 @@ use anchor_spl::{associated_token::AssociatedToken, token::{Token, TokenAccount}
 use whirlpool::{manager::liquidity_manager::calculate_liquidity_token_deltas, math::{mul_u256, sqrt_price_from_tick_index, U256Muldiv}, state::{Position, TickArray, Whirlpool}};
 use whirlpool::cpi::accounts::{ModifyLiquidity};
 
+use crate::errors::AqueductError;
+use crate::events::*;
+use crate::math::value_in_user_quote;
 use crate::state::*;
 
 #[derive(Clone)]
@@ -23,13 +26,49 @@ pub struct PositionFund<'info> {
     pub auth: Signer<'info>,
 
     /// CHECK:
+    #[account(constraint = user.key() == user_pda.user)]
     pub user: UncheckedAccount<'info>,
-    #[account(mut)]
+    #[account(
+        mut,
+        seeds = [
+            USER_PDA_PREFIX,
+            user_pda.vault_id.as_ref()
+        ],
+        bump,
+        constraint = !user_pda.exit_only @ AqueductError::UserExitOnly,
+    )]
     pub user_pda: Box<Account<'info, User>>,
 
     pub whirlpool_program: Program<'info, WhirlpoolProgram>,
     #[account(mut)]
     pub whirlpool: Box<Account<'info, Whirlpool>>,
+    #[account(
+        mut,
+        seeds = [
+            POOL_CONFIG_PDA_PREFIX,
+            whirlpool.key().as_ref()
+        ],
+        bump,
+    )]
+    pub pool_config: Box<Account<'info, PoolConfig>>,
+    /// CHECK: the mint's config PDA, may not have been created in which case nothing is capped
+    #[account(
+        seeds = [
+            MINT_CONFIG_PDA_PREFIX,
+            whirlpool.token_mint_a.as_ref()
+        ],
+        bump,
+    )]
+    pub mint_config_a: UncheckedAccount<'info>,
+    /// CHECK: the mint's config PDA, may not have been created in which case nothing is capped
+    #[account(
+        seeds = [
+            MINT_CONFIG_PDA_PREFIX,
+            whirlpool.token_mint_b.as_ref()
+        ],
+        bump,
+    )]
+    pub mint_config_b: UncheckedAccount<'info>,
 
     #[account(mut, has_one = whirlpool)]
     pub position: Box<Account<'info, Position>>,
@@ -70,8 +109,9 @@ pub fn handler<'info>(ctx: Context<'_, '_, '_, 'info, PositionFund<'info>>,
     minimum_b_amount: u64
 ) -> Result<()> {
 
-    let (_user_pda, user_pda_bump) = Pubkey::find_program_address(&[USER_PDA_PREFIX, ctx.accounts.user.key.as_ref()], ctx.program_id);
-    let seeds = &[USER_PDA_PREFIX, ctx.accounts.user.key.as_ref(), &[user_pda_bump]];
+    let vault_id = ctx.accounts.user_pda.vault_id;
+    let (_user_pda, user_pda_bump) = Pubkey::find_program_address(&[USER_PDA_PREFIX, vault_id.as_ref()], ctx.program_id);
+    let seeds = &[USER_PDA_PREFIX, vault_id.as_ref(), &[user_pda_bump]];
     let signer_seeds = &[&seeds[..]];
 
     ctx.accounts.token_owner_account_a.reload()?;
@@ -89,14 +129,9 @@ pub fn handler<'info>(ctx: Context<'_, '_, '_, 'info, PositionFund<'info>>,
     let sqrt_price_current_x64 = ctx.accounts.whirlpool.sqrt_price;
     let sqrt_price_upper_x64 = sqrt_price_from_tick_index(tick_upper_index);
 
-    // msg!("sqrt_price_current_x64: {}", sqrt_price_current_x64);
-    // msg!("sqrt_price_upper_x64: {}", sqrt_price_upper_x64);
-
     // get_liquidity_from_token_a is imported from whirlpools-sdk (getLiquidityFromTokenA)
     let liquidity = get_liquidity_from_token_a(token_a_amount as u128, sqrt_price_current_x64, sqrt_price_upper_x64)?;
 
-    msg!("liquidity: {}", liquidity);
-
     let (token_max_a, token_max_b) = calculate_liquidity_token_deltas(
         tick_index_current,
         sqrt_price_current_x64,
@@ -104,19 +139,12 @@ pub fn handler<'info>(ctx: Context<'_, '_, '_, 'info, PositionFund<'info>>,
         liquidity as i128
     )?;
 
-    // msg!("token_owner_account_a.amount: {}", ctx.accounts.token_owner_account_a.amount);
-    // msg!("token_owner_account_b.amount: {}", ctx.accounts.token_owner_account_b.amount);
-
-    // msg!("token_a_amount: {}", token_a_amount);
-    // msg!("token_b_amount: {}", token_b_amount);
-
-    // msg!("token_max_a: {}", token_max_a);
-    // msg!("token_max_b: {}", token_max_b);
-
     assert!(token_max_a > minimum_a_amount);
     assert!(token_max_b > minimum_b_amount);
 
-    msg!("starting increase liquiditiy..");
+    ctx.accounts.pool_config.add_liquidity(liquidity)?;
+    check_mint_allocation(&ctx.accounts.mint_config_a, token_max_a, ctx.accounts.token_vault_a.amount)?;
+    check_mint_allocation(&ctx.accounts.mint_config_b, token_max_b, ctx.accounts.token_vault_b.amount)?;
 
     whirlpool::cpi::increase_liquidity(
         ctx.accounts.into_increase_liquidity_context().with_signer(signer_seeds),
@@ -125,7 +153,31 @@ pub fn handler<'info>(ctx: Context<'_, '_, '_, 'info, PositionFund<'info>>,
         token_max_b
     )?;
 
-    msg!("finished increase liquiditiy");
+    ctx.accounts.token_owner_account_a.reload()?;
+    ctx.accounts.token_owner_account_b.reload()?;
+
+    let amount_a = token_a_amount - ctx.accounts.token_owner_account_a.amount;
+    let amount_b = token_b_amount - ctx.accounts.token_owner_account_b.amount;
+
+    let quote_mint = ctx.accounts.user_pda.effective_quote_mint();
+    // at the pool reference price, like the value that comes out of the position on close
+    let cost_basis = value_in_user_quote(amount_a, amount_b, ctx.accounts.pool_config.valuation_sqrt_price()?, &ctx.accounts.whirlpool, &quote_mint)?;
+    ctx.accounts.user_pda.add_cost_basis(cost_basis, liquidity, Clock::get()?.unix_timestamp);
+
+    let slot = Clock::get()?.slot;
+    ctx.accounts.user_pda.last_service_action_slot = slot;
+
+    emit!(LiquidityAdded {
+        user: ctx.accounts.user_pda.user,
+        user_pda: ctx.accounts.user_pda.key(),
+        whirlpool: ctx.accounts.whirlpool.key(),
+        position: ctx.accounts.position.key(),
+        liquidity,
+        amount_a,
+        amount_b,
+        sqrt_price: sqrt_price_current_x64,
+        slot,
+    });
 
     Ok(())
 }
@@ -152,7 +204,7 @@ impl<'info> PositionFund<'info> {
 }
 
 // https://github.com/everlastingsong/solsandbox/blob/main/orca_whirlpools_sdk/rust_cpi/cpi_whirlpool_increase_liquidity/programs/cpi_whirlpool_increase_liquidity/src/lib.rs
-fn get_liquidity_from_token_a(amount: u128, sqrt_price_lower_x64: u128, sqrt_price_upper_x64: u128 ) -> Result<u128> {
+pub fn get_liquidity_from_token_a(amount: u128, sqrt_price_lower_x64: u128, sqrt_price_upper_x64: u128 ) -> Result<u128> {
     // Δa = liquidity/sqrt_price_lower - liquidity/sqrt_price_upper
     // liquidity = Δa * ((sqrt_price_lower * sqrt_price_upper) / (sqrt_price_upper - sqrt_price_lower))
     assert!(sqrt_price_lower_x64 < sqrt_price_upper_x64);
@@ -1,9 +1,13 @@
 use anchor_lang::prelude::*;
+use solana_program::sysvar;
 use anchor_spl::{associated_token::AssociatedToken, token::{Token, Mint, TokenAccount}};
-use whirlpool::{state::{OpenPositionBumps, Whirlpool}};
-use whirlpool::cpi::accounts::{OpenPosition};
+use whirlpool::{state::{OpenPositionBumps, OpenPositionWithMetadataBumps, Whirlpool}};
+use whirlpool::cpi::accounts::{InitializeTickArray, OpenPosition, OpenPositionWithMetadata};
 
+use crate::errors::AqueductError;
+use crate::events::*;
 use crate::state::*;
+use crate::ticks::{check_tick_array, resolve_tick_range, tick_array_start_index, validate_tick_range};
 
 #[derive(Clone)]
 pub struct WhirlpoolProgram;
@@ -16,23 +20,39 @@ impl Id for WhirlpoolProgram {
     position_bump: u8,
     tick_lower_index: i32,
     tick_upper_index: i32,
+    intent: Option<ServiceIntent>,
+    metadata_bump: Option<u8>,
+    range_spec: Option<RangeSpec>,
 )]
 pub struct PositionOpen<'info> {
     #[account(mut)]
     pub auth: Signer<'info>,
 
     /// CHECK:
+    #[account(constraint = user.key() == user_pda.user)]
     pub user: UncheckedAccount<'info>,
     #[account(
         mut,
         seeds = [
             USER_PDA_PREFIX,
-            user.key().as_ref()
+            user_pda.vault_id.as_ref()
         ],
         bump,
+        constraint = !user_pda.exit_only @ AqueductError::UserExitOnly,
+        // cost basis is tracked for the user as a whole, so it only ever covers a single position
+        constraint = user_pda.open_positions_counted @ AqueductError::OpenPositionsNotCounted,
+        constraint = user_pda.open_positions == 0 @ AqueductError::UserHasOpenPositions,
     )]
     pub user_pda: Box<Account<'info, User>>,
 
+    #[account(
+        seeds = [
+            RATE_LIMITS_PDA_PREFIX
+        ],
+        bump,
+    )]
+    pub rate_limits: Box<Account<'info, RateLimits>>,
+
     pub whirlpool_program: Program<'info, WhirlpoolProgram>,
 
     /// CHECK: Safe
@@ -43,9 +63,32 @@ pub struct PositionOpen<'info> {
     /// CHECK: Safe
     #[account(mut)]
     pub position_token_account: AccountInfo<'info>,
-    #[account(mut)]
+    // the user's books are kept in their quote mint, a pool without it could never be valued or closed
+    #[account(
+        mut,
+        constraint = whirlpool.token_mint_a == user_pda.effective_quote_mint()
+            || whirlpool.token_mint_b == user_pda.effective_quote_mint() @ AqueductError::QuoteMintNotInPool,
+    )]
     pub whirlpool: Box<Account<'info, Whirlpool>>,
 
+    // created by the service when the position range needs tick arrays that do not exist yet
+    /// CHECK: Safe, checked against the whirlpool tick array PDA in the handler
+    #[account(mut)]
+    pub tick_array_lower: UncheckedAccount<'info>,
+    /// CHECK: Safe, checked against the whirlpool tick array PDA in the handler
+    #[account(mut)]
+    pub tick_array_upper: UncheckedAccount<'info>,
+
+    // only used when the position NFT is minted with metadata, checked by the whirlpool
+    /// CHECK: Safe, initialized by the metadata program
+    #[account(mut)]
+    pub position_metadata_account: UncheckedAccount<'info>,
+    /// CHECK: Safe
+    pub metadata_program: UncheckedAccount<'info>,
+    /// CHECK: Safe
+    #[account(address = WHIRLPOOL_METADATA_UPDATE_AUTH)]
+    pub metadata_update_auth: UncheckedAccount<'info>,
+
     pub mint_a: Box<Account<'info, Mint>>,
     pub mint_b: Box<Account<'info, Mint>>,
 
@@ -64,6 +107,10 @@ pub struct PositionOpen<'info> {
     )]
     pub token_owner_account_b: Box<Account<'info, TokenAccount>>,
 
+    /// CHECK: instructions sysvar, read for user-signed intents
+    #[account(address = sysvar::instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+
     pub token_program: Program<'info, Token>,
     pub system_program: Program<'info, System>,
     pub rent: Sysvar<'info, Rent>,
@@ -74,29 +121,115 @@ pub fn handler<'info>(ctx: Context<'_, '_, '_, 'info, PositionOpen<'info>>,
     position_bump: u8,
     tick_lower_index: i32,
     tick_upper_index: i32,
+    intent: Option<ServiceIntent>,
+    metadata_bump: Option<u8>,
+    range_spec: Option<RangeSpec>,
 ) -> Result<()> {
 
-    let (_user_pda, user_pda_bump) = Pubkey::find_program_address(&[USER_PDA_PREFIX, ctx.accounts.user.key.as_ref()], ctx.program_id);
-    let seeds = &[USER_PDA_PREFIX, ctx.accounts.user.key.as_ref(), &[user_pda_bump]];
+    // with a range spec the passed ticks are ignored and derived from the current price
+    let (tick_lower_index, tick_upper_index) = resolve_tick_range(
+        &ctx.accounts.user_pda,
+        &ctx.accounts.whirlpool,
+        tick_lower_index,
+        tick_upper_index,
+        range_spec
+    )?;
+
+    if ctx.accounts.user_pda.intents_required {
+        let intent = intent.ok_or(AqueductError::IntentRequired)?;
+        require!(intent.tick_lower_index == tick_lower_index, AqueductError::IntentMismatch);
+        require!(intent.tick_upper_index == tick_upper_index, AqueductError::IntentMismatch);
+
+        let user_pda_key = ctx.accounts.user_pda.key();
+        let whirlpool_key = ctx.accounts.whirlpool.key();
+        intent.consume(&mut ctx.accounts.user_pda, &user_pda_key, &whirlpool_key, INTENT_ACTION_OPEN, &ctx.accounts.instructions)?;
+    }
+
+    let vault_id = ctx.accounts.user_pda.vault_id;
+    let (_user_pda, user_pda_bump) = Pubkey::find_program_address(&[USER_PDA_PREFIX, vault_id.as_ref()], ctx.program_id);
+    let seeds = &[USER_PDA_PREFIX, vault_id.as_ref(), &[user_pda_bump]];
     let signer_seeds = &[&seeds[..]];
 
+    let now = Clock::get()?.unix_timestamp;
+    let rate_limits = &ctx.accounts.rate_limits;
+    ctx.accounts.user_pda.record_rebalance(rate_limits, now)?;
+
     let tick_index_current = ctx.accounts.whirlpool.tick_current_index;
 
-    assert!(tick_index_current >= tick_lower_index);
-    assert!(tick_index_current <= tick_upper_index);
+    require!(
+        tick_index_current >= tick_lower_index && tick_index_current <= tick_upper_index,
+        AqueductError::CurrentTickOutOfRange
+    );
+    validate_tick_range(&ctx.accounts.whirlpool, tick_lower_index, tick_upper_index)?;
+
+    let whirlpool_key = ctx.accounts.whirlpool.key();
+    let tick_spacing = ctx.accounts.whirlpool.tick_spacing;
+    let start_lower = tick_array_start_index(tick_lower_index, tick_spacing);
+    let start_upper = tick_array_start_index(tick_upper_index, tick_spacing);
+
+    if check_tick_array(&whirlpool_key, &ctx.accounts.tick_array_lower, start_lower)? {
+        whirlpool::cpi::initialize_tick_array(
+            ctx.accounts.into_initialize_tick_array_context(ctx.accounts.tick_array_lower.to_account_info()),
+            start_lower
+        )?;
+    }
+    // both ends can fall in the same array, which is only created once
+    if start_upper != start_lower && check_tick_array(&whirlpool_key, &ctx.accounts.tick_array_upper, start_upper)? {
+        whirlpool::cpi::initialize_tick_array(
+            ctx.accounts.into_initialize_tick_array_context(ctx.accounts.tick_array_upper.to_account_info()),
+            start_upper
+        )?;
+    }
+
+    match metadata_bump {
+        // labelled position NFT so wallets show it as a whirlpool position
+        Some(metadata_bump) => whirlpool::cpi::open_position_with_metadata(
+            ctx.accounts.into_open_position_with_metadata_context().with_signer(signer_seeds),
+            OpenPositionWithMetadataBumps { position_bump, metadata_bump },
+            tick_lower_index,
+            tick_upper_index
+        )?,
+        None => whirlpool::cpi::open_position(
+            ctx.accounts.into_open_position_context().with_signer(signer_seeds),
+            OpenPositionBumps { position_bump },
+            tick_lower_index,
+            tick_upper_index
+        )?,
+    }
 
-    whirlpool::cpi::open_position(
-        ctx.accounts.into_open_position_context().with_signer(signer_seeds),
-        OpenPositionBumps { position_bump },
+    ctx.accounts.user_pda.open_positions += 1;
+    let slot = Clock::get()?.slot;
+    ctx.accounts.user_pda.last_service_action_slot = slot;
+
+    emit!(PositionOpened {
+        user: ctx.accounts.user_pda.user,
+        user_pda: ctx.accounts.user_pda.key(),
+        whirlpool: ctx.accounts.whirlpool.key(),
+        position: ctx.accounts.position.key(),
+        position_mint: ctx.accounts.position_mint.key(),
         tick_lower_index,
-        tick_upper_index
-    )?;
+        tick_upper_index,
+        tick_current_index: tick_index_current,
+        sqrt_price: ctx.accounts.whirlpool.sqrt_price,
+        slot,
+    });
 
     Ok(())
 }
 
 impl<'info> PositionOpen<'info> {
 
+    fn into_initialize_tick_array_context(&self, tick_array: AccountInfo<'info>) -> CpiContext<'_, '_, '_, 'info, InitializeTickArray<'info>> {
+        let cpi_accounts = InitializeTickArray {
+            whirlpool: self.whirlpool.to_account_info(),
+            funder: self.auth.to_account_info(),
+            tick_array,
+            system_program: self.system_program.to_account_info()
+        };
+        let cpi_program = self.whirlpool_program.to_account_info();
+        CpiContext::new(cpi_program, cpi_accounts)
+    }
+
     fn into_open_position_context(&self) -> CpiContext<'_, '_, '_, 'info, OpenPosition<'info>> {
         let cpi_accounts = OpenPosition {
             funder: self.auth.to_account_info(),
@@ -114,4 +247,24 @@ impl<'info> PositionOpen<'info> {
         CpiContext::new(cpi_program, cpi_accounts)
     }
 
+    fn into_open_position_with_metadata_context(&self) -> CpiContext<'_, '_, '_, 'info, OpenPositionWithMetadata<'info>> {
+        let cpi_accounts = OpenPositionWithMetadata {
+            funder: self.auth.to_account_info(),
+            owner: self.user_pda.to_account_info(),
+            position: self.position.to_account_info(),
+            position_mint: self.position_mint.to_account_info(),
+            position_metadata_account: self.position_metadata_account.to_account_info(),
+            position_token_account: self.position_token_account.to_account_info(),
+            whirlpool: self.whirlpool.to_account_info(),
+            token_program: self.token_program.to_account_info(),
+            system_program: self.system_program.to_account_info(),
+            rent: self.rent.to_account_info(),
+            associated_token_program: self.associated_token_program.to_account_info(),
+            metadata_program: self.metadata_program.to_account_info(),
+            metadata_update_auth: self.metadata_update_auth.to_account_info()
+        };
+        let cpi_program = self.whirlpool_program.to_account_info();
+        CpiContext::new(cpi_program, cpi_accounts)
+    }
+
 }
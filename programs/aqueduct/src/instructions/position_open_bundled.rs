@@ -0,0 +1,226 @@
+use anchor_lang::prelude::*;
+use solana_program::sysvar;
+use anchor_spl::{associated_token::AssociatedToken, token::{Token, Mint, TokenAccount}};
+use whirlpool::state::Whirlpool;
+use whirlpool::cpi::accounts::{InitializeTickArray, OpenBundledPosition};
+
+use crate::errors::AqueductError;
+use crate::events::*;
+use crate::state::*;
+use crate::ticks::{check_tick_array, resolve_tick_range, tick_array_start_index, validate_tick_range};
+use crate::WhirlpoolProgram;
+
+#[derive(Accounts)]
+#[instruction(
+    bundle_index: u16,
+    tick_lower_index: i32,
+    tick_upper_index: i32,
+    intent: Option<ServiceIntent>,
+    range_spec: Option<RangeSpec>,
+)]
+pub struct PositionOpenBundled<'info> {
+    #[account(mut)]
+    pub auth: Signer<'info>,
+
+    /// CHECK:
+    #[account(constraint = user.key() == user_pda.user)]
+    pub user: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [
+            USER_PDA_PREFIX,
+            user_pda.vault_id.as_ref()
+        ],
+        bump,
+        constraint = !user_pda.exit_only @ AqueductError::UserExitOnly,
+        // cost basis is tracked for the user as a whole, so it only ever covers a single position
+        constraint = user_pda.open_positions_counted @ AqueductError::OpenPositionsNotCounted,
+        constraint = user_pda.open_positions == 0 @ AqueductError::UserHasOpenPositions,
+    )]
+    pub user_pda: Box<Account<'info, User>>,
+
+    #[account(
+        seeds = [
+            RATE_LIMITS_PDA_PREFIX
+        ],
+        bump,
+    )]
+    pub rate_limits: Box<Account<'info, RateLimits>>,
+
+    pub whirlpool_program: Program<'info, WhirlpoolProgram>,
+
+    /// CHECK: Safe, initialized by the whirlpool program
+    #[account(mut)]
+    pub bundled_position: UncheckedAccount<'info>,
+    /// CHECK: Safe, the whirlpool checks it against the bundle token account
+    #[account(mut)]
+    pub position_bundle: UncheckedAccount<'info>,
+    #[account(
+        constraint = position_bundle_token_account.mint == user_pda.position_bundle_mint @ AqueductError::InvalidPositionBundle,
+        constraint = position_bundle_token_account.owner == user_pda.key() @ AqueductError::InvalidPositionBundle,
+        constraint = position_bundle_token_account.amount == 1
+    )]
+    pub position_bundle_token_account: Box<Account<'info, TokenAccount>>,
+    // the user's books are kept in their quote mint, a pool without it could never be valued or closed
+    #[account(
+        mut,
+        constraint = whirlpool.token_mint_a == user_pda.effective_quote_mint()
+            || whirlpool.token_mint_b == user_pda.effective_quote_mint() @ AqueductError::QuoteMintNotInPool,
+    )]
+    pub whirlpool: Box<Account<'info, Whirlpool>>,
+
+    // created by the service when the position range needs tick arrays that do not exist yet
+    /// CHECK: Safe, checked against the whirlpool tick array PDA in the handler
+    #[account(mut)]
+    pub tick_array_lower: UncheckedAccount<'info>,
+    /// CHECK: Safe, checked against the whirlpool tick array PDA in the handler
+    #[account(mut)]
+    pub tick_array_upper: UncheckedAccount<'info>,
+
+    pub mint_a: Box<Account<'info, Mint>>,
+    pub mint_b: Box<Account<'info, Mint>>,
+
+    #[account(
+        init_if_needed,
+        associated_token::mint = mint_a,
+        associated_token::authority = user_pda,
+        payer = auth
+    )]
+    pub token_owner_account_a: Box<Account<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        associated_token::mint = mint_b,
+        associated_token::authority = user_pda,
+        payer = auth
+    )]
+    pub token_owner_account_b: Box<Account<'info, TokenAccount>>,
+
+    /// CHECK: instructions sysvar, read for user-signed intents
+    #[account(address = sysvar::instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+pub fn handler<'info>(ctx: Context<'_, '_, '_, 'info, PositionOpenBundled<'info>>,
+    bundle_index: u16,
+    tick_lower_index: i32,
+    tick_upper_index: i32,
+    intent: Option<ServiceIntent>,
+    range_spec: Option<RangeSpec>,
+) -> Result<()> {
+
+    // with a range spec the passed ticks are ignored and derived from the current price
+    let (tick_lower_index, tick_upper_index) = resolve_tick_range(
+        &ctx.accounts.user_pda,
+        &ctx.accounts.whirlpool,
+        tick_lower_index,
+        tick_upper_index,
+        range_spec
+    )?;
+
+    if ctx.accounts.user_pda.intents_required {
+        let intent = intent.ok_or(AqueductError::IntentRequired)?;
+        require!(intent.tick_lower_index == tick_lower_index, AqueductError::IntentMismatch);
+        require!(intent.tick_upper_index == tick_upper_index, AqueductError::IntentMismatch);
+
+        let user_pda_key = ctx.accounts.user_pda.key();
+        let whirlpool_key = ctx.accounts.whirlpool.key();
+        intent.consume(&mut ctx.accounts.user_pda, &user_pda_key, &whirlpool_key, INTENT_ACTION_OPEN, &ctx.accounts.instructions)?;
+    }
+
+    let vault_id = ctx.accounts.user_pda.vault_id;
+    let (_user_pda, user_pda_bump) = Pubkey::find_program_address(&[USER_PDA_PREFIX, vault_id.as_ref()], ctx.program_id);
+    let seeds = &[USER_PDA_PREFIX, vault_id.as_ref(), &[user_pda_bump]];
+    let signer_seeds = &[&seeds[..]];
+
+    let now = Clock::get()?.unix_timestamp;
+    let rate_limits = &ctx.accounts.rate_limits;
+    ctx.accounts.user_pda.record_rebalance(rate_limits, now)?;
+
+    let tick_index_current = ctx.accounts.whirlpool.tick_current_index;
+
+    require!(
+        tick_index_current >= tick_lower_index && tick_index_current <= tick_upper_index,
+        AqueductError::CurrentTickOutOfRange
+    );
+    validate_tick_range(&ctx.accounts.whirlpool, tick_lower_index, tick_upper_index)?;
+
+    let whirlpool_key = ctx.accounts.whirlpool.key();
+    let tick_spacing = ctx.accounts.whirlpool.tick_spacing;
+    let start_lower = tick_array_start_index(tick_lower_index, tick_spacing);
+    let start_upper = tick_array_start_index(tick_upper_index, tick_spacing);
+
+    if check_tick_array(&whirlpool_key, &ctx.accounts.tick_array_lower, start_lower)? {
+        whirlpool::cpi::initialize_tick_array(
+            ctx.accounts.into_initialize_tick_array_context(ctx.accounts.tick_array_lower.to_account_info()),
+            start_lower
+        )?;
+    }
+    // both ends can fall in the same array, which is only created once
+    if start_upper != start_lower && check_tick_array(&whirlpool_key, &ctx.accounts.tick_array_upper, start_upper)? {
+        whirlpool::cpi::initialize_tick_array(
+            ctx.accounts.into_initialize_tick_array_context(ctx.accounts.tick_array_upper.to_account_info()),
+            start_upper
+        )?;
+    }
+
+    whirlpool::cpi::open_bundled_position(
+        ctx.accounts.into_open_bundled_position_context().with_signer(signer_seeds),
+        bundle_index,
+        tick_lower_index,
+        tick_upper_index
+    )?;
+
+    ctx.accounts.user_pda.open_positions += 1;
+    let slot = Clock::get()?.slot;
+    ctx.accounts.user_pda.last_service_action_slot = slot;
+
+    emit!(PositionOpened {
+        user: ctx.accounts.user_pda.user,
+        user_pda: ctx.accounts.user_pda.key(),
+        whirlpool: ctx.accounts.whirlpool.key(),
+        position: ctx.accounts.bundled_position.key(),
+        position_mint: ctx.accounts.user_pda.position_bundle_mint,
+        tick_lower_index,
+        tick_upper_index,
+        tick_current_index: tick_index_current,
+        sqrt_price: ctx.accounts.whirlpool.sqrt_price,
+        slot,
+    });
+
+    Ok(())
+}
+
+impl<'info> PositionOpenBundled<'info> {
+
+    fn into_initialize_tick_array_context(&self, tick_array: AccountInfo<'info>) -> CpiContext<'_, '_, '_, 'info, InitializeTickArray<'info>> {
+        let cpi_accounts = InitializeTickArray {
+            whirlpool: self.whirlpool.to_account_info(),
+            funder: self.auth.to_account_info(),
+            tick_array,
+            system_program: self.system_program.to_account_info()
+        };
+        let cpi_program = self.whirlpool_program.to_account_info();
+        CpiContext::new(cpi_program, cpi_accounts)
+    }
+
+    fn into_open_bundled_position_context(&self) -> CpiContext<'_, '_, '_, 'info, OpenBundledPosition<'info>> {
+        let cpi_accounts = OpenBundledPosition {
+            bundled_position: self.bundled_position.to_account_info(),
+            position_bundle: self.position_bundle.to_account_info(),
+            position_bundle_token_account: self.position_bundle_token_account.to_account_info(),
+            position_bundle_authority: self.user_pda.to_account_info(),
+            whirlpool: self.whirlpool.to_account_info(),
+            funder: self.auth.to_account_info(),
+            system_program: self.system_program.to_account_info(),
+            rent: self.rent.to_account_info()
+        };
+        let cpi_program = self.whirlpool_program.to_account_info();
+        CpiContext::new(cpi_program, cpi_accounts)
+    }
+
+}
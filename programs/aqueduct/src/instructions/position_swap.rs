@@ -1,8 +1,12 @@
 use anchor_lang::prelude::*;
+use solana_program::sysvar;
 use anchor_spl::{associated_token::AssociatedToken, token::{Token, TokenAccount}};
 use whirlpool::{state::{TickArray, Whirlpool}};
 use whirlpool::cpi::accounts::{Swap};
 
+use crate::errors::AqueductError;
+use crate::events::*;
+use crate::math::{realized_pnl, value_in_user_quote};
 use crate::state::*;
 
 #[derive(Clone)]
@@ -15,23 +19,41 @@ impl Id for WhirlpoolProgram {
 #[instruction(
     a_to_b: bool,
     amount: u64,
+    intent: Option<ServiceIntent>,
 )]
 pub struct PositionSwap<'info> {
     #[account(mut)]
     pub auth: Signer<'info>,
 
     /// CHECK:
+    #[account(constraint = user.key() == user_pda.user)]
     pub user: UncheckedAccount<'info>,
     #[account(
         mut,
         seeds = [
             USER_PDA_PREFIX,
-            user.key().as_ref()
+            user_pda.vault_id.as_ref()
         ],
         bump,
     )]
     pub user_pda: Box<Account<'info, User>>,
 
+    #[account(
+        seeds = [
+            RATE_LIMITS_PDA_PREFIX
+        ],
+        bump,
+    )]
+    pub rate_limits: Box<Account<'info, RateLimits>>,
+    #[account(
+        seeds = [
+            POOL_CONFIG_PDA_PREFIX,
+            whirlpool.key().as_ref()
+        ],
+        bump,
+    )]
+    pub pool_config: Box<Account<'info, PoolConfig>>,
+
     pub whirlpool_program: Program<'info, WhirlpoolProgram>,
     #[account(mut)]
     pub whirlpool: Box<Account<'info, Whirlpool>>,
@@ -56,6 +78,10 @@ pub struct PositionSwap<'info> {
     /// CHECK:
     pub oracle: UncheckedAccount<'info>,
 
+    /// CHECK: instructions sysvar, read for user-signed intents
+    #[account(address = sysvar::instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+
     pub token_program: Program<'info, Token>,
     pub system_program: Program<'info, System>,
     pub rent: Sysvar<'info, Rent>,
@@ -65,16 +91,32 @@ pub struct PositionSwap<'info> {
 pub fn handler<'info>(ctx: Context<'_, '_, '_, 'info, PositionSwap<'info>>,
     a_to_b: bool,
     amount: u64,
+    intent: Option<ServiceIntent>,
 ) -> Result<()> {
 
-    let (_user_pda, user_pda_bump) = Pubkey::find_program_address(&[USER_PDA_PREFIX, ctx.accounts.user.key.as_ref()], ctx.program_id);
-    let seeds = &[USER_PDA_PREFIX, ctx.accounts.user.key.as_ref(), &[user_pda_bump]];
-    let signer_seeds = &[&seeds[..]];
+    let mut other_amount_threshold = 0;
+    let mut sqrt_price_limit = if a_to_b { MIN_SQRT_PRICE } else { MAX_SQRT_PRICE };
+
+    if ctx.accounts.user_pda.intents_required {
+        let intent = intent.ok_or(AqueductError::IntentRequired)?;
+        require!(intent.a_to_b == a_to_b, AqueductError::IntentMismatch);
+        require!(intent.amount == amount, AqueductError::IntentMismatch);
+
+        let user_pda_key = ctx.accounts.user_pda.key();
+        let whirlpool_key = ctx.accounts.whirlpool.key();
+        intent.consume(&mut ctx.accounts.user_pda, &user_pda_key, &whirlpool_key, INTENT_ACTION_SWAP, &ctx.accounts.instructions)?;
 
-    msg!("a_to_b: {}", a_to_b);
-    msg!("amount: {}", amount);
+        // the owner's bounds go to the whirlpool unchanged, nothing is derived from the price at execution
+        other_amount_threshold = intent.min_amount_out;
+        if intent.sqrt_price_limit != 0 {
+            sqrt_price_limit = intent.sqrt_price_limit;
+        }
+    }
 
-    let sqrt_price_limit = if a_to_b { MIN_SQRT_PRICE } else { MAX_SQRT_PRICE };
+    let vault_id = ctx.accounts.user_pda.vault_id;
+    let (_user_pda, user_pda_bump) = Pubkey::find_program_address(&[USER_PDA_PREFIX, vault_id.as_ref()], ctx.program_id);
+    let seeds = &[USER_PDA_PREFIX, vault_id.as_ref(), &[user_pda_bump]];
+    let signer_seeds = &[&seeds[..]];
 
     let mut mod_amount = amount;
 
@@ -82,17 +124,71 @@ pub fn handler<'info>(ctx: Context<'_, '_, '_, 'info, PositionSwap<'info>>,
         mod_amount = if a_to_b { ctx.accounts.token_owner_account_a.amount } else { ctx.accounts.token_owner_account_b.amount }
     }
 
+    let token_a_start = ctx.accounts.token_owner_account_a.amount;
+    let token_b_start = ctx.accounts.token_owner_account_b.amount;
+    let sqrt_price_before = ctx.accounts.whirlpool.sqrt_price;
+
     if mod_amount > 0u64 {
         whirlpool::cpi::swap(
             ctx.accounts.into_swap_context().with_signer(signer_seeds),
             mod_amount,
-            0, // TODO: set reasonable safety defaults for the swap
+            other_amount_threshold, // TODO: set reasonable safety defaults for swaps without an intent
             sqrt_price_limit, // TODO: calculate this val to avoid errors with large orders
             true,
             a_to_b
         )?;
     }
 
+    ctx.accounts.whirlpool.reload()?;
+    ctx.accounts.token_owner_account_a.reload()?;
+    ctx.accounts.token_owner_account_b.reload()?;
+
+    let token_a_end = ctx.accounts.token_owner_account_a.amount;
+    let token_b_end = ctx.accounts.token_owner_account_b.amount;
+
+    let (amount_in, amount_out) = if a_to_b {
+        (token_a_start - token_a_end, token_b_end - token_b_start)
+    } else {
+        (token_b_start - token_b_end, token_a_end - token_a_start)
+    };
+
+    // both legs valued at the pool reference price, the difference is the fee and price impact paid
+    let quote_mint = ctx.accounts.user_pda.effective_quote_mint();
+    let reference_sqrt_price = ctx.accounts.pool_config.valuation_sqrt_price()?;
+    let whirlpool = &ctx.accounts.whirlpool;
+    let (input_a, input_b, output_a, output_b) = if a_to_b {
+        (amount_in, 0, 0, amount_out)
+    } else {
+        (0, amount_in, amount_out, 0)
+    };
+    let notional = value_in_user_quote(input_a, input_b, reference_sqrt_price, whirlpool, &quote_mint)?;
+    let loss = notional.saturating_sub(value_in_user_quote(output_a, output_b, reference_sqrt_price, whirlpool, &quote_mint)?);
+
+    let now = Clock::get()?.unix_timestamp;
+    let rate_limits = &ctx.accounts.rate_limits;
+    ctx.accounts.user_pda.record_swap(rate_limits, now, notional)?;
+
+    if ctx.accounts.user_pda.record_loss(loss, now) {
+        emit_loss_guard_triggered(&ctx.accounts.user_pda)?;
+    }
+    // swap costs count against the high-water mark, a loss never owes a fee
+    ctx.accounts.user_pda.record_realized_pnl(realized_pnl(0, loss)?, 0)?;
+
+    let slot = Clock::get()?.slot;
+    ctx.accounts.user_pda.last_service_action_slot = slot;
+
+    emit!(Swapped {
+        user: ctx.accounts.user_pda.user,
+        user_pda: ctx.accounts.user_pda.key(),
+        whirlpool: ctx.accounts.whirlpool.key(),
+        a_to_b,
+        amount_in,
+        amount_out,
+        sqrt_price_before,
+        sqrt_price_after: ctx.accounts.whirlpool.sqrt_price,
+        slot,
+    });
+
     Ok(())
 }
 
@@ -0,0 +1,74 @@
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token;
+use anchor_spl::token::{Mint, Token, TokenAccount};
+
+use crate::events::*;
+use crate::state::*;
+
+#[derive(Accounts)]
+pub struct ReferralClaim<'info> {
+    #[account(mut)]
+    pub referrer: Signer<'info>,
+    /// CHECK: holds no data, only signs for the referrer's fee balances
+    #[account(
+        seeds = [
+            REFERRAL_PDA_PREFIX,
+            referrer.key().as_ref()
+        ],
+        bump,
+    )]
+    pub referral: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        constraint = source_ata.owner == referral.key(),
+        constraint = source_ata.mint == mint.key(),
+    )]
+    pub source_ata: Account<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        associated_token::mint = mint,
+        associated_token::authority = referrer,
+        payer = referrer
+    )]
+    pub destination_ata: Account<'info, TokenAccount>,
+    pub mint: Account<'info, Mint>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Program<'info, Token>,
+    pub rent: Sysvar<'info, Rent>,
+    pub system_program: Program<'info, System>,
+}
+
+// sends the whole accrued referral balance for one mint to the referrer
+pub fn handler(ctx: Context<ReferralClaim>,
+) -> Result<()> {
+    let referrer = ctx.accounts.referrer.key();
+    let (_referral_pda, referral_bump) = Pubkey::find_program_address(&[REFERRAL_PDA_PREFIX, referrer.as_ref()], ctx.program_id);
+    let seeds = &[REFERRAL_PDA_PREFIX, referrer.as_ref(), &[referral_bump]];
+    let signer_seeds = &[&seeds[..]];
+
+    let amount = ctx.accounts.source_ata.amount;
+
+    if amount > 0 {
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token::Transfer {
+                    from: ctx.accounts.source_ata.to_account_info(),
+                    to: ctx.accounts.destination_ata.to_account_info(),
+                    authority: ctx.accounts.referral.to_account_info(),
+                },
+            ).with_signer(signer_seeds),
+            amount,
+        )?;
+    }
+
+    emit!(ReferralClaimed {
+        referrer,
+        mint: ctx.accounts.mint.key(),
+        amount,
+        slot: Clock::get()?.slot,
+    });
+
+    Ok(())
+}
@@ -0,0 +1,30 @@
+use anchor_lang::prelude::*;
+use whirlpool::state::Whirlpool;
+
+use crate::state::*;
+
+#[derive(Accounts)]
+pub struct RewardRouteRefreshPrice<'info> {
+    pub auth: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [
+            REWARD_ROUTE_PDA_PREFIX,
+            reward_route.reward_mint.as_ref()
+        ],
+        bump,
+    )]
+    pub reward_route: Box<Account<'info, RewardRoute>>,
+    #[account(address = reward_route.whirlpool)]
+    pub whirlpool: Box<Account<'info, Whirlpool>>,
+}
+
+// anyone may move the route reference price, it only ever steps towards the pool price
+pub fn handler(ctx: Context<RewardRouteRefreshPrice>,
+) -> Result<()> {
+
+    ctx.accounts.reward_route.refresh_reference_price(ctx.accounts.whirlpool.sqrt_price, Clock::get()?.slot)?;
+
+    Ok(())
+}
@@ -0,0 +1,188 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{Token, TokenAccount};
+use whirlpool::state::{TickArray, Whirlpool};
+use whirlpool::cpi::accounts::Swap;
+
+use crate::errors::AqueductError;
+use crate::events::*;
+use crate::math::minimum_swap_output;
+use crate::state::*;
+use crate::WhirlpoolProgram;
+
+#[derive(Accounts)]
+pub struct RewardSwap<'info> {
+    #[account(mut)]
+    pub auth: Signer<'info>,
+
+    /// CHECK:
+    #[account(constraint = user.key() == user_pda.user)]
+    pub user: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [
+            USER_PDA_PREFIX,
+            user_pda.vault_id.as_ref()
+        ],
+        bump,
+        constraint = user_pda.reward_conversion != REWARD_CONVERSION_NONE @ AqueductError::RewardConversionDisabled,
+    )]
+    pub user_pda: Box<Account<'info, User>>,
+    #[account(
+        seeds = [
+            RATE_LIMITS_PDA_PREFIX
+        ],
+        bump,
+    )]
+    pub rate_limits: Box<Account<'info, RateLimits>>,
+    // the pair the user provides liquidity in, the target for REWARD_CONVERSION_PAIR
+    #[account(address = user_pda.whirlpool)]
+    pub user_whirlpool: Box<Account<'info, Whirlpool>>,
+
+    #[account(
+        seeds = [
+            REWARD_ROUTE_PDA_PREFIX,
+            reward_route.reward_mint.as_ref()
+        ],
+        bump,
+        constraint = reward_route.approved @ AqueductError::RewardNotWhitelisted,
+    )]
+    pub reward_route: Box<Account<'info, RewardRoute>>,
+
+    pub whirlpool_program: Program<'info, WhirlpoolProgram>,
+    #[account(mut, address = reward_route.whirlpool)]
+    pub whirlpool: Box<Account<'info, Whirlpool>>,
+
+    #[account(
+        mut,
+        constraint = reward_account.owner == user_pda.key(),
+        constraint = reward_account.mint == reward_route.reward_mint,
+    )]
+    pub reward_account: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = output_account.owner == user_pda.key(),
+        constraint = output_account.mint == whirlpool.token_mint_a
+            || output_account.mint == whirlpool.token_mint_b,
+        constraint = output_account.mint != reward_route.reward_mint,
+    )]
+    pub output_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut, address = whirlpool.token_vault_a)]
+    pub token_vault_a: Box<Account<'info, TokenAccount>>,
+    #[account(mut, address = whirlpool.token_vault_b)]
+    pub token_vault_b: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut, has_one = whirlpool)]
+    pub tick_array_0: AccountLoader<'info, TickArray>,
+    #[account(mut, has_one = whirlpool)]
+    pub tick_array_1: AccountLoader<'info, TickArray>,
+    #[account(mut, has_one = whirlpool)]
+    pub tick_array_2: AccountLoader<'info, TickArray>,
+
+    /// CHECK:
+    pub oracle: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+// Sells the user's whole balance of a whitelisted reward through its route, into the user's quote
+// or one of their pair tokens depending on the conversion the user opted into.
+pub fn handler<'info>(ctx: Context<'_, '_, '_, 'info, RewardSwap<'info>>,
+) -> Result<()> {
+
+    let vault_id = ctx.accounts.user_pda.vault_id;
+    let (_user_pda, user_pda_bump) = Pubkey::find_program_address(&[USER_PDA_PREFIX, vault_id.as_ref()], ctx.program_id);
+    let seeds = &[USER_PDA_PREFIX, vault_id.as_ref(), &[user_pda_bump]];
+    let signer_seeds = &[&seeds[..]];
+
+    let output_mint = ctx.accounts.output_account.mint;
+    let user_whirlpool = &ctx.accounts.user_whirlpool;
+
+    if ctx.accounts.user_pda.reward_conversion == REWARD_CONVERSION_QUOTE {
+        require!(output_mint == ctx.accounts.user_pda.effective_quote_mint(), AqueductError::RewardRouteMismatch);
+    } else {
+        require!(
+            output_mint == user_whirlpool.token_mint_a || output_mint == user_whirlpool.token_mint_b,
+            AqueductError::RewardRouteMismatch
+        );
+    }
+
+    let amount_in = ctx.accounts.reward_account.amount;
+    if amount_in == 0 {
+        return Ok(());
+    }
+
+    let a_to_b = ctx.accounts.whirlpool.token_mint_a == ctx.accounts.reward_route.reward_mint;
+    let sqrt_price_limit = if a_to_b { MIN_SQRT_PRICE } else { MAX_SQRT_PRICE };
+    // bounded by the route reference price, so pushing the pool price ahead of the sale does not lower it
+    let minimum_amount_out = minimum_swap_output(
+        amount_in,
+        ctx.accounts.reward_route.valuation_sqrt_price()?,
+        a_to_b,
+        ctx.accounts.user_pda.effective_reward_max_slippage()
+    )?;
+
+    let output_before = ctx.accounts.output_account.amount;
+
+    whirlpool::cpi::swap(
+        ctx.accounts.into_swap_context(a_to_b).with_signer(signer_seeds),
+        amount_in,
+        minimum_amount_out,
+        sqrt_price_limit,
+        true,
+        a_to_b
+    )?;
+
+    ctx.accounts.output_account.reload()?;
+    let amount_out = ctx.accounts.output_account.amount - output_before;
+
+    // the reward is outside the user's pool, so the notional is the output when it lands in the quote mint
+    let notional = if output_mint == ctx.accounts.user_pda.effective_quote_mint() { amount_out } else { amount_in };
+    let now = Clock::get()?.unix_timestamp;
+    let rate_limits = &ctx.accounts.rate_limits;
+    ctx.accounts.user_pda.record_swap(rate_limits, now, notional)?;
+
+    let slot = Clock::get()?.slot;
+    ctx.accounts.user_pda.last_service_action_slot = slot;
+
+    emit!(RewardSwapped {
+        user: ctx.accounts.user_pda.user,
+        user_pda: ctx.accounts.user_pda.key(),
+        whirlpool: ctx.accounts.whirlpool.key(),
+        reward_mint: ctx.accounts.reward_route.reward_mint,
+        output_mint,
+        amount_in,
+        amount_out,
+        slot,
+    });
+
+    Ok(())
+}
+
+impl<'info> RewardSwap<'info> {
+
+    fn into_swap_context(&self, a_to_b: bool) -> CpiContext<'_, '_, '_, 'info, Swap<'info>> {
+        let (token_owner_account_a, token_owner_account_b) = if a_to_b {
+            (self.reward_account.to_account_info(), self.output_account.to_account_info())
+        } else {
+            (self.output_account.to_account_info(), self.reward_account.to_account_info())
+        };
+
+        let cpi_accounts = Swap {
+            token_program: self.token_program.to_account_info(),
+            token_authority: self.user_pda.to_account_info(),
+            whirlpool: self.whirlpool.to_account_info(),
+            token_owner_account_a,
+            token_vault_a: self.token_vault_a.to_account_info(),
+            token_owner_account_b,
+            token_vault_b: self.token_vault_b.to_account_info(),
+            tick_array_0: self.tick_array_0.to_account_info(),
+            tick_array_1: self.tick_array_1.to_account_info(),
+            tick_array_2: self.tick_array_2.to_account_info(),
+            oracle: self.oracle.to_account_info(),
+        };
+        let cpi_program = self.whirlpool_program.to_account_info();
+        CpiContext::new(cpi_program, cpi_accounts)
+    }
+
+}
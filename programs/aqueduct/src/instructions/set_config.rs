@@ -0,0 +1,36 @@
+use std::mem::size_of;
+
+use anchor_lang::prelude::*;
+
+use crate::state::*;
+
+#[derive(Accounts)]
+#[instruction(
+    attestation_required: bool,
+)]
+pub struct SetConfig<'info> {
+    #[account(mut)]
+    pub auth: Signer<'info>,
+
+    #[account(
+        init_if_needed,
+        seeds = [
+            CONFIG_PDA_PREFIX
+        ],
+        bump,
+        payer = auth,
+        space = 8 + size_of::<Config>()
+    )]
+    pub config: Account<'info, Config>,
+
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handler(ctx: Context<SetConfig>,
+    attestation_required: bool,
+) -> Result<()> {
+
+    ctx.accounts.config.attestation_required = attestation_required;
+
+    Ok(())
+}
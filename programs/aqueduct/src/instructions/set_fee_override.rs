@@ -0,0 +1,57 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::AqueductError;
+use crate::state::*;
+
+#[derive(Accounts)]
+#[instruction(
+    mint: Pubkey,
+    performance_fee_bps: u16,
+    withdrawal_fee_bps: u16,
+    enabled: bool,
+)]
+pub struct SetFeeOverride<'info> {
+    pub auth: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [
+            FEE_SCHEDULE_PDA_PREFIX
+        ],
+        bump,
+    )]
+    pub fee_schedule: Box<Account<'info, FeeSchedule>>,
+}
+
+// Adds or updates the override for `mint`, or clears it when `enabled` is false.
+pub fn handler(ctx: Context<SetFeeOverride>,
+    mint: Pubkey,
+    performance_fee_bps: u16,
+    withdrawal_fee_bps: u16,
+    enabled: bool,
+) -> Result<()> {
+
+    let max_bps = FEE_BPS_DENOMINATOR as u16;
+    require!(performance_fee_bps <= max_bps, AqueductError::InvalidFeeRate);
+    require!(withdrawal_fee_bps <= max_bps, AqueductError::InvalidFeeRate);
+
+    let overrides = &mut ctx.accounts.fee_schedule.overrides;
+
+    if let Some(existing) = overrides.iter_mut().find(|o| o.mint == mint) {
+        *existing = if enabled {
+            MintFeeOverride { mint, performance_fee_bps, withdrawal_fee_bps }
+        } else {
+            MintFeeOverride::default()
+        };
+        return Ok(());
+    }
+
+    if enabled {
+        let slot = overrides.iter_mut()
+            .find(|o| o.mint == Pubkey::default())
+            .ok_or(AqueductError::FeeOverridesFull)?;
+        *slot = MintFeeOverride { mint, performance_fee_bps, withdrawal_fee_bps };
+    }
+
+    Ok(())
+}
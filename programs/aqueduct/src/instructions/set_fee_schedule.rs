@@ -0,0 +1,57 @@
+use std::mem::size_of;
+
+use anchor_lang::prelude::*;
+
+use crate::errors::AqueductError;
+use crate::state::*;
+
+#[derive(Accounts)]
+#[instruction(
+    management_fee_bps: u16,
+    performance_fee_bps: u16,
+    withdrawal_fee_bps: u16,
+    performance_fee_mode: u8,
+)]
+pub struct SetFeeSchedule<'info> {
+    #[account(mut)]
+    pub auth: Signer<'info>,
+
+    #[account(
+        init_if_needed,
+        seeds = [
+            FEE_SCHEDULE_PDA_PREFIX
+        ],
+        bump,
+        payer = auth,
+        space = 8 + size_of::<FeeSchedule>()
+    )]
+    pub fee_schedule: Box<Account<'info, FeeSchedule>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handler(ctx: Context<SetFeeSchedule>,
+    management_fee_bps: u16,
+    performance_fee_bps: u16,
+    withdrawal_fee_bps: u16,
+    performance_fee_mode: u8,
+) -> Result<()> {
+
+    let max_bps = FEE_BPS_DENOMINATOR as u16;
+    require!(management_fee_bps <= max_bps, AqueductError::InvalidFeeRate);
+    require!(performance_fee_bps <= max_bps, AqueductError::InvalidFeeRate);
+    require!(withdrawal_fee_bps <= max_bps, AqueductError::InvalidFeeRate);
+    require!(
+        performance_fee_mode == PERFORMANCE_FEE_MODE_GROSS || performance_fee_mode == PERFORMANCE_FEE_MODE_HIGH_WATER_MARK,
+        AqueductError::InvalidFeeMode
+    );
+
+    let fee_schedule = &mut ctx.accounts.fee_schedule;
+
+    fee_schedule.management_fee_bps = management_fee_bps;
+    fee_schedule.performance_fee_bps = performance_fee_bps;
+    fee_schedule.withdrawal_fee_bps = withdrawal_fee_bps;
+    fee_schedule.performance_fee_mode = performance_fee_mode;
+
+    Ok(())
+}
@@ -0,0 +1,47 @@
+use std::mem::size_of;
+
+use anchor_lang::prelude::*;
+
+use crate::errors::AqueductError;
+use crate::state::*;
+
+#[derive(Accounts)]
+#[instruction(
+    stake_mint: Pubkey,
+    tiers: Vec<FeeTier>,
+)]
+pub struct SetFeeTiers<'info> {
+    #[account(mut)]
+    pub auth: Signer<'info>,
+
+    #[account(
+        init_if_needed,
+        seeds = [
+            FEE_TIERS_PDA_PREFIX
+        ],
+        bump,
+        payer = auth,
+        space = 8 + size_of::<FeeTiers>()
+    )]
+    pub fee_tiers: Box<Account<'info, FeeTiers>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+// replaces every tier, slots past the given ones are cleared
+pub fn handler(ctx: Context<SetFeeTiers>,
+    stake_mint: Pubkey,
+    tiers: Vec<FeeTier>,
+) -> Result<()> {
+
+    require!(tiers.len() <= MAX_FEE_TIERS, AqueductError::InvalidFeeTiers);
+    require!(tiers.iter().all(|t| t.discount_bps as u64 <= FEE_BPS_DENOMINATOR), AqueductError::InvalidFeeTiers);
+
+    let fee_tiers = &mut ctx.accounts.fee_tiers;
+
+    fee_tiers.stake_mint = stake_mint;
+    fee_tiers.tiers = [FeeTier::default(); MAX_FEE_TIERS];
+    fee_tiers.tiers[..tiers.len()].copy_from_slice(&tiers);
+
+    Ok(())
+}
@@ -0,0 +1,52 @@
+use std::mem::size_of;
+
+use anchor_lang::prelude::*;
+use anchor_spl::token::Mint;
+
+use crate::errors::AqueductError;
+use crate::state::*;
+
+#[derive(Accounts)]
+#[instruction(
+    user_deposit_cap: u64,
+    protocol_deposit_cap: u64,
+    max_allocation_percent: u8,
+)]
+pub struct SetMintConfig<'info> {
+    #[account(mut)]
+    pub auth: Signer<'info>,
+
+    #[account(
+        init_if_needed,
+        seeds = [
+            MINT_CONFIG_PDA_PREFIX,
+            mint.key().as_ref()
+        ],
+        bump,
+        payer = auth,
+        space = 8 + size_of::<MintConfig>()
+    )]
+    pub mint_config: Account<'info, MintConfig>,
+
+    pub mint: Account<'info, Mint>,
+
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handler(ctx: Context<SetMintConfig>,
+    user_deposit_cap: u64,
+    protocol_deposit_cap: u64,
+    max_allocation_percent: u8,
+) -> Result<()> {
+
+    require!(max_allocation_percent <= 100, AqueductError::InvalidAllocationPercent);
+
+    let mint_config = &mut ctx.accounts.mint_config;
+
+    mint_config.mint = ctx.accounts.mint.key();
+    mint_config.user_deposit_cap = user_deposit_cap;
+    mint_config.protocol_deposit_cap = protocol_deposit_cap;
+    mint_config.max_allocation_percent = max_allocation_percent;
+
+    Ok(())
+}
@@ -0,0 +1,39 @@
+use anchor_lang::prelude::*;
+use whirlpool::state::Whirlpool;
+
+use crate::state::*;
+
+#[derive(Accounts)]
+#[instruction(
+    approved: bool,
+)]
+pub struct SetPoolApproval<'info> {
+    pub auth: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [
+            POOL_CONFIG_PDA_PREFIX,
+            whirlpool.key().as_ref()
+        ],
+        bump,
+    )]
+    pub pool_config: Account<'info, PoolConfig>,
+    pub whirlpool: Box<Account<'info, Whirlpool>>,
+}
+
+// approves a whirlpool for deposits, approved = false revokes it again; positions already
+// open in the pool can still be closed and withdrawn. Approval seeds the reference price.
+pub fn handler(ctx: Context<SetPoolApproval>,
+    approved: bool,
+) -> Result<()> {
+
+    ctx.accounts.pool_config.approved = approved;
+    ctx.accounts.pool_config.rejected = !approved;
+
+    if approved {
+        ctx.accounts.pool_config.refresh_reference_price(ctx.accounts.whirlpool.sqrt_price, Clock::get()?.slot)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,80 @@
+use std::mem::size_of;
+
+use anchor_lang::prelude::*;
+use anchor_lang::system_program;
+use whirlpool::state::Whirlpool;
+
+use crate::errors::AqueductError;
+use crate::state::*;
+
+// Pool configs created before the caps existed are shorter than the current layout,
+// so they are grown in place the first time caps are set.
+#[derive(Accounts)]
+#[instruction(
+    liquidity_cap: u128,
+)]
+pub struct SetPoolConfigCaps<'info> {
+    #[account(mut)]
+    pub auth: Signer<'info>,
+
+    /// CHECK: possibly legacy layout, validated and deserialized in the handler
+    #[account(
+        mut,
+        seeds = [
+            POOL_CONFIG_PDA_PREFIX,
+            whirlpool.key().as_ref()
+        ],
+        bump,
+        owner = PROGRAM_ID,
+    )]
+    pub pool_config: UncheckedAccount<'info>,
+    pub whirlpool: Box<Account<'info, Whirlpool>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handler(ctx: Context<SetPoolConfigCaps>,
+    liquidity_cap: u128,
+) -> Result<()> {
+
+    let pool_config = ctx.accounts.pool_config.to_account_info();
+    let new_len = 8 + size_of::<PoolConfig>();
+
+    {
+        let data = pool_config.try_borrow_data()?;
+        require!(data.len() >= 8, AqueductError::InvalidLegacyAccount);
+        require!(data[..8] == PoolConfig::discriminator(), AqueductError::InvalidLegacyAccount);
+    }
+
+    if pool_config.data_len() < new_len {
+        let lamports_needed = Rent::get()?.minimum_balance(new_len).saturating_sub(pool_config.lamports());
+
+        if lamports_needed > 0 {
+            system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: ctx.accounts.auth.to_account_info(),
+                        to: pool_config.clone(),
+                    },
+                ),
+                lamports_needed,
+            )?;
+        }
+
+        // new fields are appended and all decode from zeroed bytes
+        pool_config.realloc(new_len, true)?;
+    }
+
+    let mut pool_config_state = {
+        let data = pool_config.try_borrow_data()?;
+        PoolConfig::try_deserialize(&mut &data[..])?
+    };
+
+    pool_config_state.liquidity_cap = liquidity_cap;
+
+    let mut data = pool_config.try_borrow_mut_data()?;
+    pool_config_state.try_serialize(&mut &mut data[..])?;
+
+    Ok(())
+}
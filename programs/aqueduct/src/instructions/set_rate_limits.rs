@@ -0,0 +1,47 @@
+use std::mem::size_of;
+
+use anchor_lang::prelude::*;
+
+use crate::state::*;
+
+#[derive(Accounts)]
+#[instruction(
+    rebalance_cooldown_seconds: i64,
+    max_rebalances_per_day: u16,
+    max_swaps_per_day: u16,
+    max_swap_notional_per_day: u64,
+)]
+pub struct SetRateLimits<'info> {
+    #[account(mut)]
+    pub auth: Signer<'info>,
+
+    #[account(
+        init_if_needed,
+        seeds = [
+            RATE_LIMITS_PDA_PREFIX
+        ],
+        bump,
+        payer = auth,
+        space = 8 + size_of::<RateLimits>()
+    )]
+    pub rate_limits: Account<'info, RateLimits>,
+
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handler(ctx: Context<SetRateLimits>,
+    rebalance_cooldown_seconds: i64,
+    max_rebalances_per_day: u16,
+    max_swaps_per_day: u16,
+    max_swap_notional_per_day: u64,
+) -> Result<()> {
+
+    let rate_limits = &mut ctx.accounts.rate_limits;
+
+    rate_limits.rebalance_cooldown_seconds = rebalance_cooldown_seconds.max(0);
+    rate_limits.max_rebalances_per_day = max_rebalances_per_day;
+    rate_limits.max_swaps_per_day = max_swaps_per_day;
+    rate_limits.max_swap_notional_per_day = max_swap_notional_per_day;
+
+    Ok(())
+}
@@ -0,0 +1,32 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::AqueductError;
+use crate::state::*;
+
+#[derive(Accounts)]
+#[instruction(
+    referral_share_bps: u16,
+)]
+pub struct SetReferralShare<'info> {
+    pub auth: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [
+            FEE_SCHEDULE_PDA_PREFIX
+        ],
+        bump,
+    )]
+    pub fee_schedule: Box<Account<'info, FeeSchedule>>,
+}
+
+pub fn handler(ctx: Context<SetReferralShare>,
+    referral_share_bps: u16,
+) -> Result<()> {
+
+    require!(referral_share_bps as u64 <= FEE_BPS_DENOMINATOR, AqueductError::InvalidFeeRate);
+
+    ctx.accounts.fee_schedule.referral_share_bps = referral_share_bps;
+
+    Ok(())
+}
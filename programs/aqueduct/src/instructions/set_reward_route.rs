@@ -0,0 +1,58 @@
+use std::mem::size_of;
+
+use anchor_lang::prelude::*;
+use whirlpool::state::Whirlpool;
+
+use crate::errors::AqueductError;
+use crate::state::*;
+
+#[derive(Accounts)]
+#[instruction(
+    approved: bool,
+)]
+pub struct SetRewardRoute<'info> {
+    #[account(mut)]
+    pub auth: Signer<'info>,
+
+    /// CHECK: only used as the route seed and compared against the whirlpool mints
+    pub reward_mint: UncheckedAccount<'info>,
+    #[account(
+        constraint = whirlpool.token_mint_a == reward_mint.key()
+            || whirlpool.token_mint_b == reward_mint.key() @ AqueductError::InvalidRewardRoute,
+    )]
+    pub whirlpool: Box<Account<'info, Whirlpool>>,
+
+    #[account(
+        init_if_needed,
+        seeds = [
+            REWARD_ROUTE_PDA_PREFIX,
+            reward_mint.key().as_ref()
+        ],
+        bump,
+        payer = auth,
+        space = 8 + size_of::<RewardRoute>()
+    )]
+    pub reward_route: Box<Account<'info, RewardRoute>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+// whitelists a reward mint and routes it through `whirlpool`, approved = false takes it off the whitelist
+pub fn handler(ctx: Context<SetRewardRoute>,
+    approved: bool,
+) -> Result<()> {
+
+    let reward_route = &mut ctx.accounts.reward_route;
+
+    // a reference for another pool means nothing here, the new pool's price is taken as is
+    if reward_route.whirlpool != ctx.accounts.whirlpool.key() {
+        reward_route.reference_sqrt_price = 0;
+    }
+
+    reward_route.reward_mint = ctx.accounts.reward_mint.key();
+    reward_route.whirlpool = ctx.accounts.whirlpool.key();
+    reward_route.approved = approved;
+    reward_route.refresh_reference_price(ctx.accounts.whirlpool.sqrt_price, Clock::get()?.slot)?;
+
+    Ok(())
+}
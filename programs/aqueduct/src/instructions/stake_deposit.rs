@@ -0,0 +1,96 @@
+use std::mem::size_of;
+
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token;
+use anchor_spl::token::{Mint, Token, TokenAccount};
+
+use crate::errors::AqueductError;
+use crate::state::*;
+
+#[derive(Accounts)]
+#[instruction(
+    amount: u64,
+)]
+pub struct StakeDeposit<'info> {
+    #[account(
+        mut,
+        constraint = user.key() == user_pda.user
+    )]
+    pub user: Signer<'info>,
+    #[account(
+        seeds = [
+            USER_PDA_PREFIX,
+            user_pda.vault_id.as_ref()
+        ],
+        bump,
+    )]
+    pub user_pda: Box<Account<'info, User>>,
+    #[account(
+        init_if_needed,
+        seeds = [
+            STAKE_PDA_PREFIX,
+            user_pda.key().as_ref()
+        ],
+        bump,
+        payer = user,
+        space = 8 + size_of::<Stake>()
+    )]
+    pub stake: Box<Account<'info, Stake>>,
+
+    #[account(
+        seeds = [
+            FEE_TIERS_PDA_PREFIX
+        ],
+        bump,
+    )]
+    pub fee_tiers: Box<Account<'info, FeeTiers>>,
+    #[account(address = fee_tiers.stake_mint)]
+    pub stake_mint: Box<Account<'info, Mint>>,
+
+    #[account(mut)]
+    pub source_ata: Box<Account<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        associated_token::mint = stake_mint,
+        associated_token::authority = stake,
+        payer = user
+    )]
+    pub stake_ata: Box<Account<'info, TokenAccount>>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Program<'info, Token>,
+    pub rent: Sysvar<'info, Rent>,
+    pub system_program: Program<'info, System>,
+}
+
+// every top up restarts the lock
+pub fn handler(ctx: Context<StakeDeposit>,
+    amount: u64,
+) -> Result<()> {
+
+    // a stake left in a retired stake mint has to be withdrawn before staking the current one
+    let stake = &ctx.accounts.stake;
+    require!(stake.amount == 0 || stake.mint == ctx.accounts.stake_mint.key(), AqueductError::InvalidStakeAccount);
+
+    token::transfer(
+        CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            token::Transfer {
+                from: ctx.accounts.source_ata.to_account_info(),
+                to: ctx.accounts.stake_ata.to_account_info(),
+                authority: ctx.accounts.user.to_account_info(),
+            },
+        ),
+        amount,
+    )?;
+
+    let stake = &mut ctx.accounts.stake;
+
+    stake.user_pda = ctx.accounts.user_pda.key();
+    stake.mint = ctx.accounts.stake_mint.key();
+    stake.amount = stake.amount.saturating_add(amount);
+    stake.locked_until = Clock::get()?.unix_timestamp + STAKE_LOCK_SECONDS;
+
+    Ok(())
+}
@@ -0,0 +1,87 @@
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token;
+use anchor_spl::token::{Mint, Token, TokenAccount};
+
+use crate::errors::AqueductError;
+use crate::state::*;
+
+#[derive(Accounts)]
+#[instruction(
+    amount: u64,
+)]
+pub struct StakeWithdraw<'info> {
+    #[account(
+        mut,
+        constraint = user.key() == user_pda.user
+    )]
+    pub user: Signer<'info>,
+    #[account(
+        seeds = [
+            USER_PDA_PREFIX,
+            user_pda.vault_id.as_ref()
+        ],
+        bump,
+    )]
+    pub user_pda: Box<Account<'info, User>>,
+    #[account(
+        mut,
+        seeds = [
+            STAKE_PDA_PREFIX,
+            user_pda.key().as_ref()
+        ],
+        bump,
+        has_one = user_pda,
+    )]
+    pub stake: Box<Account<'info, Stake>>,
+
+    #[account(
+        mut,
+        constraint = stake_ata.owner == stake.key(),
+        constraint = stake_ata.mint == stake.mint @ AqueductError::InvalidStakeAccount,
+    )]
+    pub stake_ata: Box<Account<'info, TokenAccount>>,
+    #[account(address = stake_ata.mint)]
+    pub stake_mint: Box<Account<'info, Mint>>,
+    #[account(
+        init_if_needed,
+        associated_token::mint = stake_mint,
+        associated_token::authority = user,
+        payer = user
+    )]
+    pub destination_ata: Box<Account<'info, TokenAccount>>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Program<'info, Token>,
+    pub rent: Sysvar<'info, Rent>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handler(ctx: Context<StakeWithdraw>,
+    amount: u64,
+) -> Result<()> {
+
+    require!(Clock::get()?.unix_timestamp >= ctx.accounts.stake.locked_until, AqueductError::StakeLocked);
+    require!(amount <= ctx.accounts.stake.amount, AqueductError::InsufficientStake);
+
+    let user_pda = ctx.accounts.user_pda.key();
+    let (_stake_pda, stake_bump) = Pubkey::find_program_address(&[STAKE_PDA_PREFIX, user_pda.as_ref()], ctx.program_id);
+    let seeds = &[STAKE_PDA_PREFIX, user_pda.as_ref(), &[stake_bump]];
+    let signer_seeds = &[&seeds[..]];
+
+    token::transfer(
+        CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            token::Transfer {
+                from: ctx.accounts.stake_ata.to_account_info(),
+                to: ctx.accounts.destination_ata.to_account_info(),
+                authority: ctx.accounts.stake.to_account_info(),
+            },
+        ).with_signer(signer_seeds),
+        amount,
+    )?;
+
+    ctx.accounts.stake.amount -= amount;
+
+    Ok(())
+}
@@ -0,0 +1,31 @@
+use anchor_lang::prelude::*;
+use crate::errors::AqueductError;
+use crate::state::*;
+
+#[derive(Accounts)]
+pub struct UserAcceptOwnership<'info> {
+    #[account(
+        constraint = new_owner.key() == user_pda.pending_owner @ AqueductError::NotPendingOwner
+    )]
+    pub new_owner: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [
+            USER_PDA_PREFIX,
+            user_pda.vault_id.as_ref()
+        ],
+        bump,
+    )]
+    pub user_pda: Box<Account<'info, User>>,
+}
+
+pub fn handler(ctx: Context<UserAcceptOwnership>,
+) -> Result<()> {
+
+    ctx.accounts.user_pda.user = ctx.accounts.new_owner.key();
+    ctx.accounts.user_pda.pending_owner = Pubkey::default();
+    ctx.accounts.user_pda.delegate = Pubkey::default();
+    ctx.accounts.user_pda.delegate_permissions = 0;
+
+    Ok(())
+}
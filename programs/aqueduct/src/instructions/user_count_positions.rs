@@ -0,0 +1,52 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::TokenAccount;
+use whirlpool::state::Position;
+
+use crate::errors::AqueductError;
+use crate::state::*;
+
+// Users created before open_positions was tracked start the counter at zero, the service sets it from
+// the positions the user_pda actually holds. Run user_migrate first for accounts without the field.
+#[derive(Accounts)]
+pub struct UserCountPositions<'info> {
+    pub auth: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [
+            USER_PDA_PREFIX,
+            user_pda.vault_id.as_ref()
+        ],
+        bump,
+    )]
+    pub user_pda: Box<Account<'info, User>>,
+
+    // remaining_accounts are (position, position_token_account) pairs for every open position of the user,
+    // the token account of a bundled position is the user's position bundle token account
+}
+
+pub fn handler<'info>(ctx: Context<'_, '_, '_, 'info, UserCountPositions<'info>>,
+) -> Result<()> {
+
+    require!(ctx.remaining_accounts.len() % 2 == 0, AqueductError::InvalidPositionAccounts);
+
+    let user_pda = ctx.accounts.user_pda.key();
+    let mut positions: Vec<Pubkey> = Vec::new();
+
+    for position_accounts in ctx.remaining_accounts.chunks(2) {
+        let position: Account<Position> = Account::try_from(&position_accounts[0])?;
+        let position_token_account: Account<TokenAccount> = Account::try_from(&position_accounts[1])?;
+
+        require!(position_token_account.owner == user_pda, AqueductError::InvalidPositionAccounts);
+        require!(position_token_account.mint == position.position_mint, AqueductError::InvalidPositionAccounts);
+        require!(position_token_account.amount == 1, AqueductError::InvalidPositionAccounts);
+        require!(!positions.contains(&position.key()), AqueductError::DuplicatePosition);
+
+        positions.push(position.key());
+    }
+
+    ctx.accounts.user_pda.open_positions = u16::try_from(positions.len()).map_err(|_| error!(AqueductError::MathOverflow))?;
+    ctx.accounts.user_pda.open_positions_counted = true;
+
+    Ok(())
+}
@@ -1,9 +1,18 @@
 use std::mem::size_of;
 
 use anchor_lang::prelude::*;
+use solana_program::sysvar;
+
+use crate::ed25519::verify_signed_message;
+use crate::errors::AqueductError;
 use crate::state::*;
 
 #[derive(Accounts)]
+#[instruction(
+    vault_id: Pubkey,
+    referrer: Option<Pubkey>,
+    attestation_expiry_slot: u64,
+)]
 pub struct UserCreate<'info> {
     #[account(mut)]
     pub user: Signer<'info>,
@@ -11,7 +20,7 @@ pub struct UserCreate<'info> {
         init,
         seeds = [
             USER_PDA_PREFIX,
-            user.key().as_ref()
+            vault_id.as_ref()
         ],
         bump,
         payer = user,
@@ -19,6 +28,18 @@ pub struct UserCreate<'info> {
     )]
     pub user_pda: Account<'info, User>,
 
+    /// CHECK: the config PDA, read through load_config which falls back to the defaults
+    #[account(
+        seeds = [
+            CONFIG_PDA_PREFIX
+        ],
+        bump,
+    )]
+    pub config: UncheckedAccount<'info>,
+    /// CHECK: instructions sysvar, read for admin attestations
+    #[account(address = sysvar::instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+
     /// CHECK: Mango CPI
     pub mango_group: AccountInfo<'info>,
     /// CHECK: Mango CPI
@@ -27,11 +48,23 @@ pub struct UserCreate<'info> {
     pub mango_v3: AccountInfo<'info>,
 
     pub system_program: Program<'info, System>,
+
+    // remaining_accounts[0] is the user's allowlist entry when onboarding through the allowlist
 }
 
+// `vault_id` is any key the user picks for the new account, it stays the PDA seed when ownership moves
+// so a wallet that handed a vault over can still create another one under a different id;
+// `attestation_expiry_slot` is the last slot an admin attestation is good for, unused with an allowlist entry
 pub fn handler(ctx: Context<UserCreate>,
+    vault_id: Pubkey,
+    referrer: Option<Pubkey>,
+    attestation_expiry_slot: u64,
 ) -> Result<()> {
 
+    if load_config(&ctx.accounts.config)?.attestation_required {
+        check_attestation(&ctx, attestation_expiry_slot)?;
+    }
+
     // mango_markets_v3 ::create_mango_account(
     //     ctx.accounts
     //         .into_mango_user_create_ctx(),
@@ -42,10 +75,45 @@ pub fn handler(ctx: Context<UserCreate>,
     // ctx.accounts.user_pda.mango_account = ctx.accounts.mango_account.to_account_info().key();
 
     ctx.accounts.user_pda.user = ctx.accounts.user.to_account_info().key();
+    ctx.accounts.user_pda.vault_id = vault_id;
+    // the emergency exit window runs from creation, not from slot zero
+    ctx.accounts.user_pda.last_service_action_slot = Clock::get()?.slot;
+    ctx.accounts.user_pda.open_positions_counted = true;
+
+    if let Some(referrer) = referrer {
+        require!(referrer != ctx.accounts.user.key(), AqueductError::SelfReferral);
+        ctx.accounts.user_pda.referrer = referrer;
+    }
 
     Ok(())
 }
 
+// either an allowlist entry created by the admin or an admin-signed ed25519 message in the transaction
+fn check_attestation(ctx: &Context<UserCreate>, expiry_slot: u64) -> Result<()> {
+    let user = ctx.accounts.user.key();
+
+    if let Some(allowlist_info) = ctx.remaining_accounts.get(0) {
+        let (allowlist_pda, _) = Pubkey::find_program_address(&[ALLOWLIST_PDA_PREFIX, user.as_ref()], ctx.program_id);
+        require!(allowlist_info.key() == allowlist_pda, AqueductError::AttestationRequired);
+
+        let allowlist: Account<Allowlist> = Account::try_from(allowlist_info)?;
+        require!(allowlist.user == user, AqueductError::AttestationRequired);
+
+        return Ok(());
+    }
+
+    require!(Clock::get()?.slot <= expiry_slot, AqueductError::AttestationExpired);
+
+    // bound to this program and a slot so it can not be replayed elsewhere or kept around indefinitely
+    let message = [
+        ONBOARDING_ATTESTATION_PREFIX,
+        ctx.program_id.as_ref(),
+        user.as_ref(),
+        &expiry_slot.to_le_bytes(),
+    ].concat();
+    verify_signed_message(&ctx.accounts.instructions, &ADMIN, &message)
+}
+
 // impl<'info> UserCreate<'info> {
 //     pub fn into_mango_user_create_ctx(
 //         &self,
@@ -1,4 +1,9 @@
 use anchor_lang::prelude::*;
+use anchor_spl::token;
+use anchor_spl::token::{Token, TokenAccount};
+
+use crate::errors::AqueductError;
+use crate::events::WithdrawEvent;
 use crate::state::*;
 
 #[derive(Accounts)]
@@ -10,13 +15,77 @@ pub struct UserDelete<'info> {
     pub user: Signer<'info>,
     #[account(
         mut,
-        close=user
+        seeds = [
+            USER_PDA_PREFIX,
+            user_pda.vault_id.as_ref()
+        ],
+        bump,
+        constraint = user_pda.open_positions_counted @ AqueductError::OpenPositionsNotCounted,
+        constraint = user_pda.open_positions == 0 @ AqueductError::UserHasOpenPositions,
+        close = user
     )]
     pub user_pda: Account<'info, User>,
+    pub token_program: Program<'info, Token>,
     pub system_program: Program<'info, System>,
+
+    // remaining_accounts are (user_pda_token_account, user_destination_token_account) pairs,
+    // every user_pda token account is emptied into the wallet and closed
 }
 
-pub fn handler(_ctx: Context<UserDelete>,
+pub fn handler<'info>(ctx: Context<'_, '_, '_, 'info, UserDelete<'info>>,
 ) -> Result<()> {
+
+    let vault_id = ctx.accounts.user_pda.vault_id;
+    let (_user_pda, user_pda_bump) = Pubkey::find_program_address(&[USER_PDA_PREFIX, vault_id.as_ref()], ctx.program_id);
+    let seeds = &[USER_PDA_PREFIX, vault_id.as_ref(), &[user_pda_bump]];
+    let signer_seeds = &[&seeds[..]];
+
+    require!(ctx.remaining_accounts.len() % 2 == 0, AqueductError::InvalidTokenAccounts);
+
+    let slot = Clock::get()?.slot;
+
+    for token_accounts in ctx.remaining_accounts.chunks(2) {
+        let source: Account<TokenAccount> = Account::try_from(&token_accounts[0])?;
+        let destination: Account<TokenAccount> = Account::try_from(&token_accounts[1])?;
+
+        require!(source.owner == ctx.accounts.user_pda.key(), AqueductError::InvalidTokenAccounts);
+        require!(destination.owner == ctx.accounts.user.key(), AqueductError::InvalidTokenAccounts);
+        require!(destination.mint == source.mint, AqueductError::InvalidTokenAccounts);
+
+        if source.amount > 0 {
+            token::transfer(
+                CpiContext::new(
+                    ctx.accounts.token_program.to_account_info(),
+                    token::Transfer {
+                        from: source.to_account_info(),
+                        to: destination.to_account_info(),
+                        authority: ctx.accounts.user_pda.to_account_info(),
+                    },
+                ).with_signer(signer_seeds),
+                source.amount,
+            )?;
+
+            emit!(WithdrawEvent {
+                user: ctx.accounts.user_pda.user,
+                user_pda: ctx.accounts.user_pda.key(),
+                mint: source.mint,
+                amount: source.amount,
+                balance: 0,
+                slot,
+            });
+        }
+
+        token::close_account(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token::CloseAccount {
+                    account: source.to_account_info(),
+                    destination: ctx.accounts.user.to_account_info(),
+                    authority: ctx.accounts.user_pda.to_account_info(),
+                },
+            ).with_signer(signer_seeds),
+        )?;
+    }
+
     Ok(())
 }
@@ -0,0 +1,79 @@
+use std::mem::size_of;
+
+use anchor_lang::prelude::*;
+use anchor_lang::system_program;
+use crate::errors::AqueductError;
+use crate::state::*;
+
+// Accounts created before the current layout are shorter and are grown in place, new fields decode
+// from zeroed bytes. Accounts from before `vault_id` existed are seeded by the owner key and are given
+// that key as their vault id. Anyone can pay for this, so the service can migrate every user.
+#[derive(Accounts)]
+pub struct UserMigrate<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: the key the user PDA is seeded by, the owner for legacy accounts
+    pub vault_id: UncheckedAccount<'info>,
+    /// CHECK: possibly legacy layout, validated and deserialized in the handler
+    #[account(
+        mut,
+        seeds = [
+            USER_PDA_PREFIX,
+            vault_id.key().as_ref()
+        ],
+        bump,
+        owner = PROGRAM_ID,
+    )]
+    pub user_pda: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handler(ctx: Context<UserMigrate>,
+) -> Result<()> {
+
+    let user_pda = ctx.accounts.user_pda.to_account_info();
+    let new_len = 8 + size_of::<User>();
+
+    {
+        let data = user_pda.try_borrow_data()?;
+        require!(data.len() >= 8 + 32, AqueductError::InvalidLegacyAccount);
+        require!(data[..8] == User::discriminator(), AqueductError::InvalidLegacyAccount);
+    }
+
+    if user_pda.data_len() < new_len {
+        let lamports_needed = Rent::get()?.minimum_balance(new_len).saturating_sub(user_pda.lamports());
+
+        if lamports_needed > 0 {
+            system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: ctx.accounts.payer.to_account_info(),
+                        to: user_pda.clone(),
+                    },
+                ),
+                lamports_needed,
+            )?;
+        }
+
+        // new fields are appended and all decode from zeroed bytes
+        user_pda.realloc(new_len, true)?;
+    }
+
+    let mut user_state = {
+        let data = user_pda.try_borrow_data()?;
+        User::try_deserialize(&mut &data[..])?
+    };
+
+    let vault_id = ctx.accounts.vault_id.key();
+    if user_state.vault_id == Pubkey::default() {
+        require!(user_state.user == vault_id, AqueductError::InvalidLegacyAccount);
+        user_state.vault_id = vault_id;
+    }
+    require!(user_state.vault_id == vault_id, AqueductError::InvalidLegacyAccount);
+
+    let mut data = user_pda.try_borrow_mut_data()?;
+    user_state.try_serialize(&mut &mut data[..])?;
+
+    Ok(())
+}
@@ -0,0 +1,40 @@
+use anchor_lang::prelude::*;
+use crate::state::*;
+
+#[derive(Accounts)]
+#[instruction(
+    delegate: Pubkey,
+    permissions: u8,
+)]
+pub struct UserSetDelegate<'info> {
+    #[account(
+        constraint = user.key() == user_pda.user
+    )]
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [
+            USER_PDA_PREFIX,
+            user_pda.vault_id.as_ref()
+        ],
+        bump,
+    )]
+    pub user_pda: Box<Account<'info, User>>,
+}
+
+// Setting the default key or no permissions revokes the delegate.
+pub fn handler(ctx: Context<UserSetDelegate>,
+    delegate: Pubkey,
+    permissions: u8,
+) -> Result<()> {
+
+    if delegate == Pubkey::default() || permissions == 0 {
+        ctx.accounts.user_pda.delegate = Pubkey::default();
+        ctx.accounts.user_pda.delegate_permissions = 0;
+    } else {
+        ctx.accounts.user_pda.delegate = delegate;
+        ctx.accounts.user_pda.delegate_permissions = permissions;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,31 @@
+use anchor_lang::prelude::*;
+
+use crate::state::*;
+
+#[derive(Accounts)]
+#[instruction(
+    exit_only: bool,
+)]
+pub struct UserSetExitOnly<'info> {
+    pub auth: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [
+            USER_PDA_PREFIX,
+            user_pda.vault_id.as_ref()
+        ],
+        bump,
+    )]
+    pub user_pda: Box<Account<'info, User>>,
+}
+
+// revoking a user stops deposits and new liquidity, withdrawals and exits keep working
+pub fn handler(ctx: Context<UserSetExitOnly>,
+    exit_only: bool,
+) -> Result<()> {
+
+    ctx.accounts.user_pda.exit_only = exit_only;
+
+    Ok(())
+}
@@ -0,0 +1,32 @@
+use anchor_lang::prelude::*;
+use crate::state::*;
+
+#[derive(Accounts)]
+#[instruction(
+    intents_required: bool,
+)]
+pub struct UserSetIntentsRequired<'info> {
+    #[account(
+        constraint = user.key() == user_pda.user
+    )]
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [
+            USER_PDA_PREFIX,
+            user_pda.vault_id.as_ref()
+        ],
+        bump,
+    )]
+    pub user_pda: Box<Account<'info, User>>,
+}
+
+// Owner only, a delegate can never lift the intent requirement.
+pub fn handler(ctx: Context<UserSetIntentsRequired>,
+    intents_required: bool,
+) -> Result<()> {
+
+    ctx.accounts.user_pda.intents_required = intents_required;
+
+    Ok(())
+}
@@ -0,0 +1,42 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::AqueductError;
+use crate::state::*;
+
+#[derive(Accounts)]
+#[instruction(
+    loss_guard_threshold: u64,
+    loss_guard_window_seconds: i64,
+)]
+pub struct UserSetLossGuard<'info> {
+    #[account(
+        constraint = user.key() == user_pda.user || user.key() == ADMIN @ AqueductError::DelegateNotPermitted
+    )]
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [
+            USER_PDA_PREFIX,
+            user_pda.vault_id.as_ref()
+        ],
+        bump,
+    )]
+    pub user_pda: Box<Account<'info, User>>,
+}
+
+// Set by the owner or the admin. A zero threshold disables the guard, a zero window keeps the default.
+// Only the admin may lower the threshold, disabling it included.
+pub fn handler(ctx: Context<UserSetLossGuard>,
+    loss_guard_threshold: u64,
+    loss_guard_window_seconds: i64,
+) -> Result<()> {
+
+    if loss_guard_threshold < ctx.accounts.user_pda.loss_guard_threshold {
+        require!(ctx.accounts.user.key() == ADMIN, AqueductError::DelegateNotPermitted);
+    }
+
+    ctx.accounts.user_pda.loss_guard_threshold = loss_guard_threshold;
+    ctx.accounts.user_pda.loss_guard_window_seconds = loss_guard_window_seconds.max(0);
+
+    Ok(())
+}
@@ -0,0 +1,45 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::Mint;
+
+use crate::errors::AqueductError;
+use crate::state::*;
+
+#[derive(Accounts)]
+pub struct UserSetQuoteMint<'info> {
+    #[account(
+        constraint = user.key() == user_pda.user
+    )]
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [
+            USER_PDA_PREFIX,
+            user_pda.vault_id.as_ref()
+        ],
+        bump,
+        // cost basis is kept in the quote asset, so it cannot change under open positions
+        constraint = user_pda.open_positions_counted @ AqueductError::OpenPositionsNotCounted,
+        constraint = user_pda.open_positions == 0 @ AqueductError::UserHasOpenPositions,
+    )]
+    pub user_pda: Box<Account<'info, User>>,
+    #[account(
+        seeds = [
+            QUOTE_CONFIG_PDA_PREFIX,
+            quote_mint.key().as_ref()
+        ],
+        bump,
+        constraint = quote_config.approved @ AqueductError::QuoteMintNotApproved,
+    )]
+    pub quote_config: Account<'info, QuoteConfig>,
+    pub quote_mint: Account<'info, Mint>,
+}
+
+pub fn handler(ctx: Context<UserSetQuoteMint>,
+) -> Result<()> {
+
+    ctx.accounts.user_pda.quote_mint = ctx.accounts.quote_mint.key();
+    // the net deposit was kept in the old quote asset and means nothing in the new one
+    ctx.accounts.user_pda.net_deposit = 0;
+
+    Ok(())
+}
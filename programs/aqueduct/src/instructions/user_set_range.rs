@@ -0,0 +1,42 @@
+use anchor_lang::prelude::*;
+
+use crate::state::*;
+
+#[derive(Accounts)]
+#[instruction(
+    range_spec: Option<RangeSpec>,
+)]
+pub struct UserSetRange<'info> {
+    #[account(
+        constraint = user.key() == user_pda.user
+    )]
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [
+            USER_PDA_PREFIX,
+            user_pda.vault_id.as_ref()
+        ],
+        bump,
+    )]
+    pub user_pda: Box<Account<'info, User>>,
+}
+
+// Once set, the service can only open positions with exactly this range. None hands tick choice back to it.
+pub fn handler(ctx: Context<UserSetRange>,
+    range_spec: Option<RangeSpec>,
+) -> Result<()> {
+
+    let range_spec = match range_spec {
+        Some(range_spec) => {
+            range_spec.validate()?;
+            range_spec
+        }
+        None => RangeSpec::default(),
+    };
+
+    ctx.accounts.user_pda.range_width_bps = range_spec.width_bps;
+    ctx.accounts.user_pda.range_skew_bps = range_spec.skew_bps;
+
+    Ok(())
+}
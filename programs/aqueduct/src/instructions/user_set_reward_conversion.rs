@@ -0,0 +1,39 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::AqueductError;
+use crate::state::*;
+
+#[derive(Accounts)]
+#[instruction(
+    reward_conversion: u8,
+    reward_max_slippage: u64,
+)]
+pub struct UserSetRewardConversion<'info> {
+    #[account(
+        constraint = user.key() == user_pda.user
+    )]
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [
+            USER_PDA_PREFIX,
+            user_pda.vault_id.as_ref()
+        ],
+        bump,
+    )]
+    pub user_pda: Box<Account<'info, User>>,
+}
+
+pub fn handler(ctx: Context<UserSetRewardConversion>,
+    reward_conversion: u8,
+    reward_max_slippage: u64,
+) -> Result<()> {
+
+    require!(reward_conversion <= REWARD_CONVERSION_PAIR, AqueductError::InvalidRewardConversion);
+    require!(reward_max_slippage <= RESOLUTION, AqueductError::InvalidSlippage);
+
+    ctx.accounts.user_pda.reward_conversion = reward_conversion;
+    ctx.accounts.user_pda.reward_max_slippage = reward_max_slippage;
+
+    Ok(())
+}
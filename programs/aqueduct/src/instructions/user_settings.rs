@@ -1,19 +1,25 @@
 use anchor_lang::prelude::*;
 use whirlpool::state::Whirlpool;
+use crate::errors::AqueductError;
 use crate::state::*;
 
 #[derive(Accounts)]
 #[instruction(
     is_paused: bool,
+    emergency_exit_slots: u64,
 )]
 pub struct UserSettings<'info> {
-    #[account(mut)]
+    #[account(
+        mut,
+        constraint = user.key() == user_pda.user
+            || user_pda.is_delegate_with(&user.key(), 0) @ AqueductError::DelegateNotPermitted
+    )]
     pub user: Signer<'info>,
     #[account(
         mut,
         seeds = [
             USER_PDA_PREFIX,
-            user.key().as_ref()
+            user_pda.vault_id.as_ref()
         ],
         bump,
     )]
@@ -24,10 +30,38 @@ pub struct UserSettings<'info> {
 
 pub fn handler(ctx: Context<UserSettings>,
     is_paused: bool,
+    emergency_exit_slots: u64,
 ) -> Result<()> {
 
+    // zero keeps the default delay
+    if emergency_exit_slots != 0 {
+        require!(emergency_exit_slots >= MIN_EMERGENCY_EXIT_SLOTS, AqueductError::EmergencyExitSlotsTooLow);
+    }
+
+    let user_pda = &ctx.accounts.user_pda;
+    let signer = ctx.accounts.user.key();
+
+    // a delegate may only touch the settings its permissions cover
+    if signer != user_pda.user {
+        if is_paused != user_pda.paused {
+            require!(user_pda.is_delegate_with(&signer, DELEGATE_PERMISSION_PAUSE), AqueductError::DelegateNotPermitted);
+        }
+        if ctx.accounts.whirlpool.key() != user_pda.whirlpool {
+            require!(user_pda.is_delegate_with(&signer, DELEGATE_PERMISSION_CHANGE_POOL), AqueductError::DelegateNotPermitted);
+        }
+        require!(emergency_exit_slots == user_pda.emergency_exit_slots, AqueductError::DelegateNotPermitted);
+    }
+
+    // positions are only opened in the user's pool, a new one has to trade their quote mint
+    if ctx.accounts.whirlpool.key() != user_pda.whirlpool {
+        let quote_mint = user_pda.effective_quote_mint();
+        let whirlpool = &ctx.accounts.whirlpool;
+        require!(whirlpool.token_mint_a == quote_mint || whirlpool.token_mint_b == quote_mint, AqueductError::QuoteMintNotInPool);
+    }
+
     ctx.accounts.user_pda.paused = is_paused;
     ctx.accounts.user_pda.whirlpool = ctx.accounts.whirlpool.key();
+    ctx.accounts.user_pda.emergency_exit_slots = emergency_exit_slots;
 
     Ok(())
 }
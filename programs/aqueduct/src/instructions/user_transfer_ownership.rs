@@ -0,0 +1,32 @@
+use anchor_lang::prelude::*;
+use crate::state::*;
+
+#[derive(Accounts)]
+#[instruction(
+    new_owner: Pubkey,
+)]
+pub struct UserTransferOwnership<'info> {
+    #[account(
+        constraint = user.key() == user_pda.user
+    )]
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [
+            USER_PDA_PREFIX,
+            user_pda.vault_id.as_ref()
+        ],
+        bump,
+    )]
+    pub user_pda: Box<Account<'info, User>>,
+}
+
+// Proposing the default key cancels a pending transfer.
+pub fn handler(ctx: Context<UserTransferOwnership>,
+    new_owner: Pubkey,
+) -> Result<()> {
+
+    ctx.accounts.user_pda.pending_owner = new_owner;
+
+    Ok(())
+}
@@ -0,0 +1,112 @@
+use std::mem::size_of;
+
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token::{Mint, Token, TokenAccount};
+use whirlpool::state::Whirlpool;
+
+use crate::errors::AqueductError;
+use crate::state::*;
+
+#[derive(Accounts)]
+pub struct VaultCreate<'info> {
+    #[account(mut)]
+    pub auth: Signer<'info>,
+
+    #[account(
+        seeds = [
+            POOL_CONFIG_PDA_PREFIX,
+            whirlpool.key().as_ref()
+        ],
+        bump,
+        constraint = pool_config.approved @ AqueductError::PoolNotApproved,
+    )]
+    pub pool_config: Box<Account<'info, PoolConfig>>,
+    pub whirlpool: Box<Account<'info, Whirlpool>>,
+
+    #[account(
+        init,
+        seeds = [
+            VAULT_PDA_PREFIX,
+            whirlpool.key().as_ref()
+        ],
+        bump,
+        payer = auth,
+        space = 8 + size_of::<Vault>()
+    )]
+    pub vault: Box<Account<'info, Vault>>,
+    #[account(
+        init,
+        seeds = [
+            VAULT_SHARE_MINT_PDA_PREFIX,
+            vault.key().as_ref()
+        ],
+        bump,
+        payer = auth,
+        mint::decimals = quote_mint.decimals,
+        mint::authority = vault,
+    )]
+    pub share_mint: Box<Account<'info, Mint>>,
+    #[account(
+        init,
+        associated_token::mint = share_mint,
+        associated_token::authority = vault,
+        payer = auth
+    )]
+    pub vault_share_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        seeds = [
+            QUOTE_CONFIG_PDA_PREFIX,
+            quote_mint.key().as_ref()
+        ],
+        bump,
+        constraint = quote_config.approved @ AqueductError::QuoteMintNotApproved,
+    )]
+    pub quote_config: Box<Account<'info, QuoteConfig>>,
+    #[account(
+        constraint = quote_mint.key() == whirlpool.token_mint_a
+            || quote_mint.key() == whirlpool.token_mint_b @ AqueductError::QuoteMintNotInPool,
+    )]
+    pub quote_mint: Box<Account<'info, Mint>>,
+    #[account(address = whirlpool.token_mint_a)]
+    pub mint_a: Box<Account<'info, Mint>>,
+    #[account(address = whirlpool.token_mint_b)]
+    pub mint_b: Box<Account<'info, Mint>>,
+
+    #[account(
+        init,
+        associated_token::mint = mint_a,
+        associated_token::authority = vault,
+        payer = auth
+    )]
+    pub token_owner_account_a: Box<Account<'info, TokenAccount>>,
+    #[account(
+        init,
+        associated_token::mint = mint_b,
+        associated_token::authority = vault,
+        payer = auth
+    )]
+    pub token_owner_account_b: Box<Account<'info, TokenAccount>>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Program<'info, Token>,
+    pub rent: Sysvar<'info, Rent>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handler(ctx: Context<VaultCreate>,
+) -> Result<()> {
+
+    let vault = &mut ctx.accounts.vault;
+
+    vault.whirlpool = ctx.accounts.whirlpool.key();
+    vault.share_mint = ctx.accounts.share_mint.key();
+    vault.quote_mint = ctx.accounts.quote_mint.key();
+    vault.position = Pubkey::default();
+    vault.position_mint = Pubkey::default();
+    vault.reference_sqrt_price = ctx.accounts.whirlpool.sqrt_price;
+    vault.reference_slot = Clock::get()?.slot;
+
+    Ok(())
+}
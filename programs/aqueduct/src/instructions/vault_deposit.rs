@@ -0,0 +1,255 @@
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token;
+use anchor_spl::token::{Mint, Token, TokenAccount};
+use whirlpool::state::{Position, Whirlpool};
+
+use crate::errors::AqueductError;
+use crate::events::VaultDeposited;
+use crate::fees::{discounted_bps, load_fee_schedule, load_fee_tiers};
+use crate::math::*;
+use crate::positions::update_fees_and_rewards;
+use crate::state::*;
+use crate::WhirlpoolProgram;
+
+#[derive(Accounts)]
+#[instruction(
+    amount: u64,
+    minimum_shares_out: u64,
+)]
+pub struct VaultDeposit<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [
+            VAULT_PDA_PREFIX,
+            whirlpool.key().as_ref()
+        ],
+        bump,
+        has_one = whirlpool,
+        has_one = share_mint,
+    )]
+    pub vault: Box<Account<'info, Vault>>,
+    /// CHECK: the fee schedule PDA, read through load_fee_schedule which falls back to the defaults
+    #[account(
+        seeds = [
+            FEE_SCHEDULE_PDA_PREFIX
+        ],
+        bump,
+    )]
+    pub fee_schedule: UncheckedAccount<'info>,
+    /// CHECK: the fee tiers PDA, read through load_fee_tiers which falls back to no discount
+    #[account(
+        seeds = [
+            FEE_TIERS_PDA_PREFIX
+        ],
+        bump,
+    )]
+    pub fee_tiers: UncheckedAccount<'info>,
+    /// CHECK: the quote mint's config PDA, may not have been created in which case nothing is capped
+    #[account(
+        mut,
+        seeds = [
+            MINT_CONFIG_PDA_PREFIX,
+            vault.quote_mint.as_ref()
+        ],
+        bump,
+    )]
+    pub mint_config: UncheckedAccount<'info>,
+
+    pub whirlpool_program: Program<'info, WhirlpoolProgram>,
+    #[account(mut)]
+    pub whirlpool: Box<Account<'info, Whirlpool>>,
+
+    #[account(mut)]
+    pub share_mint: Box<Account<'info, Mint>>,
+    #[account(
+        init_if_needed,
+        associated_token::mint = share_mint,
+        associated_token::authority = user,
+        payer = user
+    )]
+    pub user_share_account: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint = share_mint,
+        associated_token::authority = vault,
+    )]
+    pub vault_share_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = source_ata.owner == user.key(),
+        constraint = source_ata.mint == vault.quote_mint @ AqueductError::VaultMintMismatch,
+    )]
+    pub source_ata: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        associated_token::mint = whirlpool.token_mint_a,
+        associated_token::authority = vault,
+    )]
+    pub token_owner_account_a: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint = whirlpool.token_mint_b,
+        associated_token::authority = vault,
+    )]
+    pub token_owner_account_b: Box<Account<'info, TokenAccount>>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Program<'info, Token>,
+    pub rent: Sysvar<'info, Rent>,
+    pub system_program: Program<'info, System>,
+
+    // remaining_accounts are (position, tick_array_lower, tick_array_upper) when the vault has a position open
+}
+
+pub fn handler<'info>(ctx: Context<'_, '_, '_, 'info, VaultDeposit<'info>>,
+    amount: u64,
+    minimum_shares_out: u64,
+) -> Result<()> {
+
+    let whirlpool_key = ctx.accounts.whirlpool.key();
+    let (_vault_pda, vault_bump) = Pubkey::find_program_address(&[VAULT_PDA_PREFIX, whirlpool_key.as_ref()], ctx.program_id);
+    let seeds = &[VAULT_PDA_PREFIX, whirlpool_key.as_ref(), &[vault_bump]];
+    let signer_seeds = &[&seeds[..]];
+
+    // shares are priced at the vault reference price, which a price pushed within the transaction does not move
+    ctx.accounts.vault.check_sqrt_price(ctx.accounts.whirlpool.sqrt_price)?;
+
+    let supply = ctx.accounts.share_mint.supply;
+    let fee_schedule = load_fee_schedule(&ctx.accounts.fee_schedule)?;
+    let fee_tiers = load_fee_tiers(&ctx.accounts.fee_tiers)?;
+    let now = Clock::get()?.unix_timestamp;
+
+    let (shares_out, dead_shares, nav) = if supply == 0 {
+        require!(amount >= VAULT_MIN_INITIAL_DEPOSIT, AqueductError::DepositTooSmall);
+        // the management fee clock starts with the first deposit, an empty vault accrues nothing
+        ctx.accounts.vault.management_fee_accrued_at = now;
+        (amount - VAULT_DEAD_SHARES, VAULT_DEAD_SHARES, 0)
+    } else {
+        let (amount_a, amount_b, fees_owed_a, fees_owed_b) = vault_holdings(&ctx)?;
+        let whirlpool = &ctx.accounts.whirlpool;
+
+        let value = ctx.accounts.vault.holdings_value(whirlpool, amount_a, amount_b)?;
+        let management_fee_bps = discounted_bps(fee_schedule.management_fee_bps, ctx.accounts.vault.fee_discount_bps(&fee_tiers, value));
+        ctx.accounts.vault.accrue_management_fee(amount_a, amount_b, management_fee_bps, now)?;
+
+        let nav = ctx.accounts.vault.net_asset_value(whirlpool, amount_a, amount_b, fees_owed_a, fees_owed_b, &fee_schedule, &fee_tiers, supply)?;
+        require!(nav > 0, AqueductError::ZeroShares);
+        (mul_div(amount, supply, nav)?, 0u64, nav)
+    };
+
+    require!(shares_out > 0, AqueductError::ZeroShares);
+    require!(shares_out >= minimum_shares_out, AqueductError::SharesBelowMinimum);
+
+    // the user cap is measured on what the depositor's shares are worth once this deposit is in
+    let user_value = if supply == 0 {
+        amount
+    } else {
+        let user_shares = ctx.accounts.user_share_account.amount.checked_add(shares_out).ok_or(AqueductError::MathOverflow)?;
+        mul_div(user_shares, nav, supply)?
+    };
+    record_mint_deposit(&ctx.accounts.mint_config, amount, Some(user_value))?;
+
+    let destination = if ctx.accounts.vault.quote_mint == ctx.accounts.whirlpool.token_mint_a {
+        ctx.accounts.token_owner_account_a.to_account_info()
+    } else {
+        ctx.accounts.token_owner_account_b.to_account_info()
+    };
+
+    token::transfer(
+        CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            token::Transfer {
+                from: ctx.accounts.source_ata.to_account_info(),
+                to: destination,
+                authority: ctx.accounts.user.to_account_info(),
+            },
+        ),
+        amount,
+    )?;
+
+    if dead_shares > 0 {
+        token::mint_to(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token::MintTo {
+                    mint: ctx.accounts.share_mint.to_account_info(),
+                    to: ctx.accounts.vault_share_account.to_account_info(),
+                    authority: ctx.accounts.vault.to_account_info(),
+                },
+            ).with_signer(signer_seeds),
+            dead_shares,
+        )?;
+    }
+
+    token::mint_to(
+        CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            token::MintTo {
+                mint: ctx.accounts.share_mint.to_account_info(),
+                to: ctx.accounts.user_share_account.to_account_info(),
+                authority: ctx.accounts.vault.to_account_info(),
+            },
+        ).with_signer(signer_seeds),
+        shares_out,
+    )?;
+
+    if supply == 0 {
+        ctx.accounts.vault.set_high_water_mark(amount, dead_shares + shares_out)?;
+    }
+
+    emit!(VaultDeposited {
+        user: ctx.accounts.user.key(),
+        vault: ctx.accounts.vault.key(),
+        mint: ctx.accounts.vault.quote_mint,
+        amount,
+        shares: shares_out,
+        share_supply: supply + dead_shares + shares_out,
+        slot: Clock::get()?.slot,
+    });
+
+    Ok(())
+}
+
+// idle balances plus the liquidity and fees owed of the shared position at the vault reference price,
+// returned with the fees owed on their own as the performance fee is still to come off them
+fn vault_holdings<'info>(ctx: &Context<'_, '_, '_, 'info, VaultDeposit<'info>>) -> Result<(u64, u64, u64, u64)> {
+    let whirlpool = &ctx.accounts.whirlpool;
+    let reference_sqrt_price = ctx.accounts.vault.reference_sqrt_price;
+
+    let mut amount_a = ctx.accounts.token_owner_account_a.amount;
+    let mut amount_b = ctx.accounts.token_owner_account_b.amount;
+    let mut fees_a = 0;
+    let mut fees_b = 0;
+
+    if ctx.accounts.vault.has_position() {
+        let position_accounts = ctx.remaining_accounts;
+        require!(position_accounts.len() >= 3, AqueductError::VaultPositionMismatch);
+        require!(position_accounts[0].key() == ctx.accounts.vault.position, AqueductError::VaultPositionMismatch);
+
+        let liquidity = Account::<Position>::try_from(&position_accounts[0])?.liquidity;
+        update_fees_and_rewards(
+            &ctx.accounts.whirlpool_program.to_account_info(),
+            &whirlpool.to_account_info(),
+            &position_accounts[0],
+            &position_accounts[1],
+            &position_accounts[2],
+            liquidity
+        )?;
+
+        let position: Account<Position> = Account::try_from(&position_accounts[0])?;
+        let (position_a, position_b) = position_token_amounts(&position, reference_sqrt_price)?;
+        fees_a = position.fee_owed_a;
+        fees_b = position.fee_owed_b;
+
+        amount_a = amount_a.checked_add(position_a).and_then(|v| v.checked_add(fees_a)).ok_or(AqueductError::MathOverflow)?;
+        amount_b = amount_b.checked_add(position_b).and_then(|v| v.checked_add(fees_b)).ok_or(AqueductError::MathOverflow)?;
+    }
+
+    Ok((amount_a, amount_b, fees_a, fees_b))
+}
@@ -0,0 +1,398 @@
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token::{Mint, Token, TokenAccount};
+use anchor_spl::{associated_token, token};
+use whirlpool::state::{Position, TickArray, Whirlpool, NUM_REWARDS};
+use whirlpool::cpi::accounts::ClosePosition;
+
+use crate::errors::AqueductError;
+use crate::events::*;
+use crate::fees::{discounted_bps, fee_amount, load_fee_schedule, load_fee_tiers, split_fee_value};
+use crate::math::position_token_amounts;
+use crate::positions::PositionAccounts;
+use crate::rewards::reward_slot_accounts;
+use crate::state::*;
+use crate::WhirlpoolProgram;
+
+#[derive(Accounts)]
+pub struct VaultPositionClose<'info> {
+    #[account(mut)]
+    pub auth: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [
+            VAULT_PDA_PREFIX,
+            whirlpool.key().as_ref()
+        ],
+        bump,
+        has_one = whirlpool,
+        has_one = position,
+        has_one = position_mint,
+        has_one = share_mint,
+    )]
+    pub vault: Box<Account<'info, Vault>>,
+    #[account(
+        seeds = [
+            RATE_LIMITS_PDA_PREFIX
+        ],
+        bump,
+    )]
+    pub rate_limits: Box<Account<'info, RateLimits>>,
+    /// CHECK: the fee schedule PDA, read through load_fee_schedule which falls back to the defaults
+    #[account(
+        seeds = [
+            FEE_SCHEDULE_PDA_PREFIX
+        ],
+        bump,
+    )]
+    pub fee_schedule: UncheckedAccount<'info>,
+    /// CHECK: the fee tiers PDA, read through load_fee_tiers which falls back to no discount
+    #[account(
+        seeds = [
+            FEE_TIERS_PDA_PREFIX
+        ],
+        bump,
+    )]
+    pub fee_tiers: UncheckedAccount<'info>,
+    pub share_mint: Box<Account<'info, Mint>>,
+    #[account(
+        mut,
+        seeds = [
+            POOL_CONFIG_PDA_PREFIX,
+            whirlpool.key().as_ref()
+        ],
+        bump,
+    )]
+    pub pool_config: Box<Account<'info, PoolConfig>>,
+
+    pub whirlpool_program: Program<'info, WhirlpoolProgram>,
+    #[account(mut)]
+    pub whirlpool: Box<Account<'info, Whirlpool>>,
+
+    #[account(mut, has_one = whirlpool)]
+    pub position: Box<Account<'info, Position>>,
+    #[account(
+        mut,
+        constraint = position_token_account.mint == position.position_mint,
+        constraint = position_token_account.amount == 1
+    )]
+    pub position_token_account: Box<Account<'info, TokenAccount>>,
+    #[account(mut, address = position.position_mint)]
+    pub position_mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = whirlpool.token_mint_a,
+        associated_token::authority = vault,
+    )]
+    pub token_owner_account_a: Box<Account<'info, TokenAccount>>,
+    #[account(mut, address = whirlpool.token_vault_a)]
+    pub token_vault_a: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = token_aqueduct_a.mint == whirlpool.token_mint_a,
+        constraint = token_aqueduct_a.owner == B_SERVICE @ AqueductError::InvalidTreasuryAccount,
+    )]
+    pub token_aqueduct_a: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        associated_token::mint = whirlpool.token_mint_b,
+        associated_token::authority = vault,
+    )]
+    pub token_owner_account_b: Box<Account<'info, TokenAccount>>,
+    #[account(mut, address = whirlpool.token_vault_b)]
+    pub token_vault_b: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = token_aqueduct_b.mint == whirlpool.token_mint_b,
+        constraint = token_aqueduct_b.owner == B_SERVICE @ AqueductError::InvalidTreasuryAccount,
+    )]
+    pub token_aqueduct_b: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut, has_one = whirlpool)]
+    pub tick_array_lower: AccountLoader<'info, TickArray>,
+    #[account(mut, has_one = whirlpool)]
+    pub tick_array_upper: AccountLoader<'info, TickArray>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    #[account(address = token::ID)]
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+
+    // remaining_accounts are (reward_mint, reward_owner_account, reward_vault, service_ata, referral_ata) groups,
+    // one per whirlpool reward slot in slot order, referral_ata is never read as the vault has no referrer
+}
+
+pub fn handler<'info>(ctx: Context<'_, '_, '_, 'info, VaultPositionClose<'info>>,
+) -> Result<()> {
+
+    let whirlpool_key = ctx.accounts.whirlpool.key();
+    let (_vault_pda, vault_bump) = Pubkey::find_program_address(&[VAULT_PDA_PREFIX, whirlpool_key.as_ref()], ctx.program_id);
+    let seeds = &[VAULT_PDA_PREFIX, whirlpool_key.as_ref(), &[vault_bump]];
+    let signer_seeds = &[&seeds[..]];
+
+    // the open that follows a close counts the rebalance, the close only has to be allowed
+    let now = Clock::get()?.unix_timestamp;
+    let rate_limits = &ctx.accounts.rate_limits;
+    ctx.accounts.vault.rate_limit_counters.check_rebalance(rate_limits, now)?;
+
+    let fee_schedule = load_fee_schedule(&ctx.accounts.fee_schedule)?;
+    let fee_tiers = load_fee_tiers(&ctx.accounts.fee_tiers)?;
+    let supply = ctx.accounts.share_mint.supply;
+    let quote_mint = ctx.accounts.vault.quote_mint;
+
+    let position = ctx.accounts.position_accounts();
+    position.update_fees_and_rewards(ctx.accounts.position.liquidity)?;
+    ctx.accounts.position.reload()?;
+
+    // the management fee is brought up to date on everything the vault holds before it is unwound
+    let (position_a, position_b) = position_token_amounts(&ctx.accounts.position, ctx.accounts.vault.reference_sqrt_price)?;
+    let total_a = ctx.accounts.token_owner_account_a.amount
+        .checked_add(position_a)
+        .and_then(|v| v.checked_add(ctx.accounts.position.fee_owed_a))
+        .ok_or(AqueductError::MathOverflow)?;
+    let total_b = ctx.accounts.token_owner_account_b.amount
+        .checked_add(position_b)
+        .and_then(|v| v.checked_add(ctx.accounts.position.fee_owed_b))
+        .ok_or(AqueductError::MathOverflow)?;
+
+    let value = ctx.accounts.vault.holdings_value(&ctx.accounts.whirlpool, total_a, total_b)?;
+    let discount_bps = ctx.accounts.vault.fee_discount_bps(&fee_tiers, value);
+    let management_fee_bps = discounted_bps(fee_schedule.management_fee_bps, discount_bps);
+    ctx.accounts.vault.accrue_management_fee(total_a, total_b, management_fee_bps, now)?;
+
+    let (fees_a, fees_b) = position.collect_fees(signer_seeds)?;
+
+    let vault_key = ctx.accounts.vault.key();
+    let position_key = ctx.accounts.position.key();
+    let slot = Clock::get()?.slot;
+
+    emit!(FeesCollected {
+        user: vault_key,
+        user_pda: vault_key,
+        whirlpool: whirlpool_key,
+        position: position_key,
+        amount_a: fees_a,
+        amount_b: fees_b,
+        slot,
+    });
+
+    // under the high-water mark mode collected fees are part of the NAV gain charged once the position is unwound
+    let high_water_mark = fee_schedule.high_water_mark_mode();
+    let (service_amount_token_a, service_amount_token_b) = if high_water_mark {
+        (0, 0)
+    } else {
+        (
+            fee_amount(fees_a, discounted_bps(fee_schedule.performance_fee_bps_for(&ctx.accounts.whirlpool.token_mint_a), discount_bps))?,
+            fee_amount(fees_b, discounted_bps(fee_schedule.performance_fee_bps_for(&ctx.accounts.whirlpool.token_mint_b), discount_bps))?,
+        )
+    };
+
+    ctx.accounts.take_fees(signer_seeds, service_amount_token_a, service_amount_token_b, slot)?;
+
+    ctx.accounts.position.reload()?;
+
+    for i in 0..NUM_REWARDS {
+        // uninitialized slots and slots with nothing owed need no accounts
+        if !ctx.accounts.whirlpool.reward_infos[i].initialized() || ctx.accounts.position.reward_infos[i].amount_owed == 0 {
+            continue;
+        }
+
+        let reward = reward_slot_accounts(&ctx.accounts.whirlpool, i, &vault_key, ctx.remaining_accounts)?;
+
+        if reward.owner_account.data_is_empty() {
+            associated_token::create(
+                CpiContext::new(
+                    ctx.accounts.associated_token_program.to_account_info(),
+                    associated_token::Create {
+                        payer: ctx.accounts.auth.to_account_info(),
+                        associated_token: reward.owner_account.clone(),
+                        authority: ctx.accounts.vault.to_account_info(),
+                        mint: reward.mint.clone(),
+                        system_program: ctx.accounts.system_program.to_account_info(),
+                        token_program: ctx.accounts.token_program.to_account_info(),
+                        rent: ctx.accounts.rent.to_account_info(),
+                    },
+                ),
+            )?;
+        }
+
+        let reward_amount = position.collect_reward(signer_seeds, i, reward.owner_account, reward.vault)?;
+        let reward_owner_account: Account<'info, TokenAccount> = Account::try_from(reward.owner_account)?;
+        let reward_mint = reward_owner_account.mint;
+
+        emit!(RewardCollected {
+            user: vault_key,
+            user_pda: vault_key,
+            whirlpool: whirlpool_key,
+            position: position_key,
+            reward_index: i as u8,
+            mint: reward_mint,
+            amount: reward_amount,
+            slot,
+        });
+
+        // rewards only count towards the NAV once vault_reward_swap has sold them into the pair, so they are
+        // skimmed as collected under either mode
+        let service_amount = fee_amount(
+            reward_amount,
+            discounted_bps(fee_schedule.performance_fee_bps_for(&reward_mint), discount_bps)
+        )?;
+
+        if service_amount > 0 {
+            token::transfer(
+                ctx.accounts.into_transfer_context(reward_owner_account.to_account_info(), reward.treasury.to_account_info()).with_signer(signer_seeds),
+                service_amount
+            )?;
+
+            emit!(ProtocolFeeTaken {
+                user: vault_key,
+                user_pda: vault_key,
+                position: position_key,
+                mint: reward_mint,
+                destination: reward.treasury.key(),
+                amount: service_amount,
+                slot,
+            });
+        }
+    }
+
+    let liquidity = ctx.accounts.position.liquidity;
+    let tick_lower_index = ctx.accounts.position.tick_lower_index;
+    let tick_upper_index = ctx.accounts.position.tick_upper_index;
+    let (amount_a, amount_b) = position.decrease_liquidity(signer_seeds, liquidity)?;
+
+    ctx.accounts.pool_config.remove_liquidity(liquidity);
+
+    whirlpool::cpi::close_position(
+        ctx.accounts.into_close_position_context().with_signer(signer_seeds)
+    )?;
+
+    ctx.accounts.vault.position = Pubkey::default();
+    ctx.accounts.vault.position_mint = Pubkey::default();
+
+    ctx.accounts.token_owner_account_a.reload()?;
+    ctx.accounts.token_owner_account_b.reload()?;
+
+    // with everything back in the idle balances the management fee owed is paid out of them
+    let management_fee_a = ctx.accounts.vault.management_fee_owed_a.min(ctx.accounts.token_owner_account_a.amount);
+    let management_fee_b = ctx.accounts.vault.management_fee_owed_b.min(ctx.accounts.token_owner_account_b.amount);
+    ctx.accounts.vault.management_fee_owed_a -= management_fee_a;
+    ctx.accounts.vault.management_fee_owed_b -= management_fee_b;
+
+    let idle_a = ctx.accounts.token_owner_account_a.amount - management_fee_a;
+    let idle_b = ctx.accounts.token_owner_account_b.amount - management_fee_b;
+    let value = ctx.accounts.vault.holdings_value(&ctx.accounts.whirlpool, idle_a, idle_b)?;
+
+    let (performance_fee_a, performance_fee_b) = if high_water_mark {
+        let fee_bps = discounted_bps(fee_schedule.performance_fee_bps_for(&quote_mint), discount_bps);
+        let fee_value = ctx.accounts.vault.high_water_mark_fee(value, supply, fee_bps)?;
+        split_fee_value(
+            idle_a.saturating_sub(ctx.accounts.vault.management_fee_owed_a),
+            idle_b.saturating_sub(ctx.accounts.vault.management_fee_owed_b),
+            fee_value,
+            value
+        )?
+    } else {
+        (0, 0)
+    };
+
+    ctx.accounts.take_fees(signer_seeds, management_fee_a + performance_fee_a, management_fee_b + performance_fee_b, slot)?;
+
+    // the mark follows the NAV per share left after the fees under both modes, so a switch to the
+    // high-water mark mode only charges gains made from then on
+    let value = ctx.accounts.vault.holdings_value(&ctx.accounts.whirlpool, idle_a - performance_fee_a, idle_b - performance_fee_b)?;
+    ctx.accounts.vault.set_high_water_mark(value, supply)?;
+
+    emit!(PositionClosed {
+        user: vault_key,
+        user_pda: vault_key,
+        whirlpool: whirlpool_key,
+        position: position_key,
+        tick_lower_index,
+        tick_upper_index,
+        liquidity,
+        amount_a,
+        amount_b,
+        sqrt_price: ctx.accounts.whirlpool.sqrt_price,
+        slot,
+    });
+
+    Ok(())
+}
+
+impl<'info> VaultPositionClose<'info> {
+
+    // moves service fees from the vault's pair accounts to the treasury
+    fn take_fees(&self, signer_seeds: &[&[&[u8]]], amount_a: u64, amount_b: u64, slot: u64) -> Result<()> {
+        for (from, to, amount) in [
+            (&self.token_owner_account_a, &self.token_aqueduct_a, amount_a),
+            (&self.token_owner_account_b, &self.token_aqueduct_b, amount_b),
+        ] {
+            if amount == 0 {
+                continue;
+            }
+
+            token::transfer(
+                self.into_transfer_context(from.to_account_info(), to.to_account_info()).with_signer(signer_seeds),
+                amount,
+            )?;
+
+            emit!(ProtocolFeeTaken {
+                user: self.vault.key(),
+                user_pda: self.vault.key(),
+                position: self.position.key(),
+                mint: from.mint,
+                destination: to.key(),
+                amount,
+                slot,
+            });
+        }
+
+        Ok(())
+    }
+
+    fn into_transfer_context(&self, from: AccountInfo<'info>, to: AccountInfo<'info>) -> CpiContext<'_, '_, '_, 'info, token::Transfer<'info>> {
+        let cpi_accounts = token::Transfer {
+            from,
+            to,
+            authority: self.vault.to_account_info(),
+        };
+        let cpi_program = self.token_program.to_account_info();
+        CpiContext::new(cpi_program, cpi_accounts)
+    }
+
+    fn position_accounts(&self) -> PositionAccounts<'info> {
+        PositionAccounts {
+            whirlpool_program: self.whirlpool_program.to_account_info(),
+            whirlpool: self.whirlpool.to_account_info(),
+            position_authority: self.vault.to_account_info(),
+            position: self.position.to_account_info(),
+            position_token_account: self.position_token_account.to_account_info(),
+            token_owner_account_a: self.token_owner_account_a.to_account_info(),
+            token_owner_account_b: self.token_owner_account_b.to_account_info(),
+            token_vault_a: self.token_vault_a.to_account_info(),
+            token_vault_b: self.token_vault_b.to_account_info(),
+            tick_array_lower: self.tick_array_lower.to_account_info(),
+            tick_array_upper: self.tick_array_upper.to_account_info(),
+            token_program: self.token_program.to_account_info(),
+        }
+    }
+
+    fn into_close_position_context(&self) -> CpiContext<'_, '_, '_, 'info, ClosePosition<'info>> {
+        let cpi_accounts = ClosePosition {
+            position_authority: self.vault.to_account_info(),
+            receiver: self.auth.to_account_info(),
+            position: self.position.to_account_info(),
+            position_mint: self.position_mint.to_account_info(),
+            position_token_account: self.position_token_account.to_account_info(),
+            token_program: self.token_program.to_account_info()
+        };
+        let cpi_program = self.whirlpool_program.to_account_info();
+        CpiContext::new(cpi_program, cpi_accounts)
+    }
+
+}
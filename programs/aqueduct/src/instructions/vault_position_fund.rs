@@ -0,0 +1,179 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{Token, TokenAccount};
+use whirlpool::{manager::liquidity_manager::calculate_liquidity_token_deltas, math::sqrt_price_from_tick_index, state::{Position, TickArray, Whirlpool}};
+use whirlpool::cpi::accounts::{ModifyLiquidity};
+
+use crate::events::LiquidityAdded;
+use crate::instructions::position_fund::get_liquidity_from_token_a;
+use crate::state::*;
+use crate::WhirlpoolProgram;
+
+#[derive(Accounts)]
+#[instruction(
+    minimum_a_amount: u64,
+    minimum_b_amount: u64
+)]
+pub struct VaultPositionFund<'info> {
+    #[account(mut)]
+    pub auth: Signer<'info>,
+
+    #[account(
+        seeds = [
+            VAULT_PDA_PREFIX,
+            whirlpool.key().as_ref()
+        ],
+        bump,
+        has_one = whirlpool,
+        has_one = position,
+    )]
+    pub vault: Box<Account<'info, Vault>>,
+
+    pub whirlpool_program: Program<'info, WhirlpoolProgram>,
+    #[account(mut)]
+    pub whirlpool: Box<Account<'info, Whirlpool>>,
+    #[account(
+        mut,
+        seeds = [
+            POOL_CONFIG_PDA_PREFIX,
+            whirlpool.key().as_ref()
+        ],
+        bump,
+    )]
+    pub pool_config: Box<Account<'info, PoolConfig>>,
+    /// CHECK: the mint's config PDA, may not have been created in which case nothing is capped
+    #[account(
+        seeds = [
+            MINT_CONFIG_PDA_PREFIX,
+            whirlpool.token_mint_a.as_ref()
+        ],
+        bump,
+    )]
+    pub mint_config_a: UncheckedAccount<'info>,
+    /// CHECK: the mint's config PDA, may not have been created in which case nothing is capped
+    #[account(
+        seeds = [
+            MINT_CONFIG_PDA_PREFIX,
+            whirlpool.token_mint_b.as_ref()
+        ],
+        bump,
+    )]
+    pub mint_config_b: UncheckedAccount<'info>,
+
+    #[account(mut, has_one = whirlpool)]
+    pub position: Box<Account<'info, Position>>,
+    #[account(
+        constraint = position_token_account.mint == position.position_mint,
+        constraint = position_token_account.owner == vault.key(),
+        constraint = position_token_account.amount == 1
+    )]
+    pub position_token_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        associated_token::mint = whirlpool.token_mint_a,
+        associated_token::authority = vault,
+    )]
+    pub token_owner_account_a: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint = whirlpool.token_mint_b,
+        associated_token::authority = vault,
+    )]
+    pub token_owner_account_b: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut, constraint = token_vault_a.key() == whirlpool.token_vault_a)]
+    pub token_vault_a: Box<Account<'info, TokenAccount>>,
+    #[account(mut, constraint = token_vault_b.key() == whirlpool.token_vault_b)]
+    pub token_vault_b: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut, has_one = whirlpool)]
+    pub tick_array_lower: AccountLoader<'info, TickArray>,
+    #[account(mut, has_one = whirlpool)]
+    pub tick_array_upper: AccountLoader<'info, TickArray>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+pub fn handler<'info>(ctx: Context<'_, '_, '_, 'info, VaultPositionFund<'info>>,
+    minimum_a_amount: u64,
+    minimum_b_amount: u64
+) -> Result<()> {
+
+    let whirlpool_key = ctx.accounts.whirlpool.key();
+    let (_vault_pda, vault_bump) = Pubkey::find_program_address(&[VAULT_PDA_PREFIX, whirlpool_key.as_ref()], ctx.program_id);
+    let seeds = &[VAULT_PDA_PREFIX, whirlpool_key.as_ref(), &[vault_bump]];
+    let signer_seeds = &[&seeds[..]];
+
+    let token_a_amount: u64 = ctx.accounts.token_owner_account_a.amount;
+    let token_b_amount: u64 = ctx.accounts.token_owner_account_b.amount;
+
+    let tick_index_current = ctx.accounts.whirlpool.tick_current_index;
+
+    assert!(tick_index_current >= ctx.accounts.position.tick_lower_index);
+    assert!(tick_index_current <= ctx.accounts.position.tick_upper_index);
+
+    let sqrt_price_current_x64 = ctx.accounts.whirlpool.sqrt_price;
+    let sqrt_price_upper_x64 = sqrt_price_from_tick_index(ctx.accounts.position.tick_upper_index);
+
+    let liquidity = get_liquidity_from_token_a(token_a_amount as u128, sqrt_price_current_x64, sqrt_price_upper_x64)?;
+
+    let (token_max_a, token_max_b) = calculate_liquidity_token_deltas(
+        tick_index_current,
+        sqrt_price_current_x64,
+        &ctx.accounts.position,
+        liquidity as i128
+    )?;
+
+    assert!(token_max_a > minimum_a_amount);
+    assert!(token_max_b > minimum_b_amount);
+
+    ctx.accounts.pool_config.add_liquidity(liquidity)?;
+    check_mint_allocation(&ctx.accounts.mint_config_a, token_max_a, ctx.accounts.token_vault_a.amount)?;
+    check_mint_allocation(&ctx.accounts.mint_config_b, token_max_b, ctx.accounts.token_vault_b.amount)?;
+
+    whirlpool::cpi::increase_liquidity(
+        ctx.accounts.into_increase_liquidity_context().with_signer(signer_seeds),
+        liquidity,
+        token_max_a,
+        token_max_b
+    )?;
+
+    ctx.accounts.token_owner_account_a.reload()?;
+    ctx.accounts.token_owner_account_b.reload()?;
+
+    let vault_key = ctx.accounts.vault.key();
+    emit!(LiquidityAdded {
+        user: vault_key,
+        user_pda: vault_key,
+        whirlpool: whirlpool_key,
+        position: ctx.accounts.position.key(),
+        liquidity,
+        amount_a: token_a_amount - ctx.accounts.token_owner_account_a.amount,
+        amount_b: token_b_amount - ctx.accounts.token_owner_account_b.amount,
+        sqrt_price: sqrt_price_current_x64,
+        slot: Clock::get()?.slot,
+    });
+
+    Ok(())
+}
+
+impl<'info> VaultPositionFund<'info> {
+
+    fn into_increase_liquidity_context(&self) -> CpiContext<'_, '_, '_, 'info, ModifyLiquidity<'info>> {
+        let cpi_accounts = ModifyLiquidity {
+            whirlpool: self.whirlpool.to_account_info(),
+            token_program: self.token_program.to_account_info(),
+            position_authority: self.vault.to_account_info(),
+            position: self.position.to_account_info(),
+            position_token_account: self.position_token_account.to_account_info(),
+            token_owner_account_a: self.token_owner_account_a.to_account_info(),
+            token_owner_account_b: self.token_owner_account_b.to_account_info(),
+            token_vault_a: self.token_vault_a.to_account_info(),
+            token_vault_b: self.token_vault_b.to_account_info(),
+            tick_array_lower: self.tick_array_lower.to_account_info(),
+            tick_array_upper: self.tick_array_upper.to_account_info(),
+        };
+        let cpi_program = self.whirlpool_program.to_account_info();
+        CpiContext::new(cpi_program, cpi_accounts)
+    }
+}
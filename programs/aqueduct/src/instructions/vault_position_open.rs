@@ -0,0 +1,127 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{associated_token::AssociatedToken, token::Token};
+use whirlpool::{state::{OpenPositionBumps, Whirlpool}};
+use whirlpool::cpi::accounts::{OpenPosition};
+
+use crate::errors::AqueductError;
+use crate::events::PositionOpened;
+use crate::state::*;
+use crate::ticks::validate_tick_range;
+use crate::WhirlpoolProgram;
+
+#[derive(Accounts)]
+#[instruction(
+    position_bump: u8,
+    tick_lower_index: i32,
+    tick_upper_index: i32,
+)]
+pub struct VaultPositionOpen<'info> {
+    #[account(mut)]
+    pub auth: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [
+            VAULT_PDA_PREFIX,
+            whirlpool.key().as_ref()
+        ],
+        bump,
+        has_one = whirlpool,
+        constraint = !vault.has_position() @ AqueductError::VaultHasPosition,
+    )]
+    pub vault: Box<Account<'info, Vault>>,
+    #[account(
+        seeds = [
+            RATE_LIMITS_PDA_PREFIX
+        ],
+        bump,
+    )]
+    pub rate_limits: Box<Account<'info, RateLimits>>,
+
+    pub whirlpool_program: Program<'info, WhirlpoolProgram>,
+
+    /// CHECK: Safe
+    #[account(mut)]
+    pub position: AccountInfo<'info>,
+    #[account(mut)]
+    pub position_mint: Signer<'info>,
+    /// CHECK: Safe
+    #[account(mut)]
+    pub position_token_account: AccountInfo<'info>,
+    #[account(mut)]
+    pub whirlpool: Box<Account<'info, Whirlpool>>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+pub fn handler<'info>(ctx: Context<'_, '_, '_, 'info, VaultPositionOpen<'info>>,
+    position_bump: u8,
+    tick_lower_index: i32,
+    tick_upper_index: i32,
+) -> Result<()> {
+
+    let whirlpool_key = ctx.accounts.whirlpool.key();
+    let (_vault_pda, vault_bump) = Pubkey::find_program_address(&[VAULT_PDA_PREFIX, whirlpool_key.as_ref()], ctx.program_id);
+    let seeds = &[VAULT_PDA_PREFIX, whirlpool_key.as_ref(), &[vault_bump]];
+    let signer_seeds = &[&seeds[..]];
+
+    let now = Clock::get()?.unix_timestamp;
+    let rate_limits = &ctx.accounts.rate_limits;
+    ctx.accounts.vault.rate_limit_counters.record_rebalance(rate_limits, now)?;
+
+    let tick_index_current = ctx.accounts.whirlpool.tick_current_index;
+
+    assert!(tick_index_current >= tick_lower_index);
+    assert!(tick_index_current <= tick_upper_index);
+    validate_tick_range(&ctx.accounts.whirlpool, tick_lower_index, tick_upper_index)?;
+
+    whirlpool::cpi::open_position(
+        ctx.accounts.into_open_position_context().with_signer(signer_seeds),
+        OpenPositionBumps { position_bump },
+        tick_lower_index,
+        tick_upper_index
+    )?;
+
+    ctx.accounts.vault.position = ctx.accounts.position.key();
+    ctx.accounts.vault.position_mint = ctx.accounts.position_mint.key();
+
+    let vault_key = ctx.accounts.vault.key();
+    emit!(PositionOpened {
+        user: vault_key,
+        user_pda: vault_key,
+        whirlpool: whirlpool_key,
+        position: ctx.accounts.position.key(),
+        position_mint: ctx.accounts.position_mint.key(),
+        tick_lower_index,
+        tick_upper_index,
+        tick_current_index: tick_index_current,
+        sqrt_price: ctx.accounts.whirlpool.sqrt_price,
+        slot: Clock::get()?.slot,
+    });
+
+    Ok(())
+}
+
+impl<'info> VaultPositionOpen<'info> {
+
+    fn into_open_position_context(&self) -> CpiContext<'_, '_, '_, 'info, OpenPosition<'info>> {
+        let cpi_accounts = OpenPosition {
+            funder: self.auth.to_account_info(),
+            owner: self.vault.to_account_info(),
+            position: self.position.to_account_info(),
+            position_mint: self.position_mint.to_account_info(),
+            position_token_account: self.position_token_account.to_account_info(),
+            whirlpool: self.whirlpool.to_account_info(),
+            token_program: self.token_program.to_account_info(),
+            system_program: self.system_program.to_account_info(),
+            rent: self.rent.to_account_info(),
+            associated_token_program: self.associated_token_program.to_account_info()
+        };
+        let cpi_program = self.whirlpool_program.to_account_info();
+        CpiContext::new(cpi_program, cpi_accounts)
+    }
+
+}
@@ -0,0 +1,163 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{Token, TokenAccount};
+use whirlpool::{state::{TickArray, Whirlpool}};
+use whirlpool::cpi::accounts::{Swap};
+
+use crate::events::Swapped;
+use crate::math::value_in_mint;
+use crate::state::*;
+use crate::WhirlpoolProgram;
+
+#[derive(Accounts)]
+#[instruction(
+    a_to_b: bool,
+    amount: u64,
+)]
+pub struct VaultPositionSwap<'info> {
+    #[account(mut)]
+    pub auth: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [
+            VAULT_PDA_PREFIX,
+            whirlpool.key().as_ref()
+        ],
+        bump,
+        has_one = whirlpool,
+    )]
+    pub vault: Box<Account<'info, Vault>>,
+    #[account(
+        seeds = [
+            RATE_LIMITS_PDA_PREFIX
+        ],
+        bump,
+    )]
+    pub rate_limits: Box<Account<'info, RateLimits>>,
+
+    pub whirlpool_program: Program<'info, WhirlpoolProgram>,
+    #[account(mut)]
+    pub whirlpool: Box<Account<'info, Whirlpool>>,
+
+    #[account(
+        mut,
+        associated_token::mint = whirlpool.token_mint_a,
+        associated_token::authority = vault,
+    )]
+    pub token_owner_account_a: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint = whirlpool.token_mint_b,
+        associated_token::authority = vault,
+    )]
+    pub token_owner_account_b: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut, constraint = token_vault_a.key() == whirlpool.token_vault_a)]
+    pub token_vault_a: Box<Account<'info, TokenAccount>>,
+    #[account(mut, constraint = token_vault_b.key() == whirlpool.token_vault_b)]
+    pub token_vault_b: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut, has_one = whirlpool)]
+    pub tick_array_0: AccountLoader<'info, TickArray>,
+    #[account(mut, has_one = whirlpool)]
+    pub tick_array_1: AccountLoader<'info, TickArray>,
+    #[account(mut, has_one = whirlpool)]
+    pub tick_array_2: AccountLoader<'info, TickArray>,
+
+    /// CHECK:
+    pub oracle: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+pub fn handler<'info>(ctx: Context<'_, '_, '_, 'info, VaultPositionSwap<'info>>,
+    a_to_b: bool,
+    amount: u64,
+) -> Result<()> {
+
+    let whirlpool_key = ctx.accounts.whirlpool.key();
+    let (_vault_pda, vault_bump) = Pubkey::find_program_address(&[VAULT_PDA_PREFIX, whirlpool_key.as_ref()], ctx.program_id);
+    let seeds = &[VAULT_PDA_PREFIX, whirlpool_key.as_ref(), &[vault_bump]];
+    let signer_seeds = &[&seeds[..]];
+
+    // the vault only trades while the pool is near its reference price and never pushes it out of that band
+    ctx.accounts.vault.check_sqrt_price(ctx.accounts.whirlpool.sqrt_price)?;
+    let sqrt_price_limit = ctx.accounts.vault.swap_sqrt_price_limit(a_to_b)?;
+
+    let mut mod_amount = amount;
+
+    if amount == 0u64 {
+        mod_amount = if a_to_b { ctx.accounts.token_owner_account_a.amount } else { ctx.accounts.token_owner_account_b.amount }
+    }
+
+    let token_a_start = ctx.accounts.token_owner_account_a.amount;
+    let token_b_start = ctx.accounts.token_owner_account_b.amount;
+    let sqrt_price_before = ctx.accounts.whirlpool.sqrt_price;
+
+    if mod_amount > 0u64 {
+        whirlpool::cpi::swap(
+            ctx.accounts.into_swap_context().with_signer(signer_seeds),
+            mod_amount,
+            0,
+            sqrt_price_limit,
+            true,
+            a_to_b
+        )?;
+    }
+
+    ctx.accounts.whirlpool.reload()?;
+    ctx.accounts.token_owner_account_a.reload()?;
+    ctx.accounts.token_owner_account_b.reload()?;
+
+    let token_a_end = ctx.accounts.token_owner_account_a.amount;
+    let token_b_end = ctx.accounts.token_owner_account_b.amount;
+
+    let (amount_in, amount_out) = if a_to_b {
+        (token_a_start - token_a_end, token_b_end - token_b_start)
+    } else {
+        (token_b_start - token_b_end, token_a_end - token_a_start)
+    };
+
+    let (input_a, input_b) = if a_to_b { (amount_in, 0) } else { (0, amount_in) };
+    let notional = value_in_mint(input_a, input_b, ctx.accounts.vault.reference_sqrt_price, &ctx.accounts.whirlpool, &ctx.accounts.vault.quote_mint)?;
+    let now = Clock::get()?.unix_timestamp;
+    let rate_limits = &ctx.accounts.rate_limits;
+    ctx.accounts.vault.rate_limit_counters.record_swap(rate_limits, now, notional)?;
+
+    let vault_key = ctx.accounts.vault.key();
+    emit!(Swapped {
+        user: vault_key,
+        user_pda: vault_key,
+        whirlpool: whirlpool_key,
+        a_to_b,
+        amount_in,
+        amount_out,
+        sqrt_price_before,
+        sqrt_price_after: ctx.accounts.whirlpool.sqrt_price,
+        slot: Clock::get()?.slot,
+    });
+
+    Ok(())
+}
+
+impl<'info> VaultPositionSwap<'info> {
+
+    fn into_swap_context(&self) -> CpiContext<'_, '_, '_, 'info, Swap<'info>> {
+        let cpi_accounts = Swap {
+            token_program: self.token_program.to_account_info(),
+            token_authority: self.vault.to_account_info(),
+            whirlpool: self.whirlpool.to_account_info(),
+            token_owner_account_a: self.token_owner_account_a.to_account_info(),
+            token_vault_a: self.token_vault_a.to_account_info(),
+            token_owner_account_b: self.token_owner_account_b.to_account_info(),
+            token_vault_b: self.token_vault_b.to_account_info(),
+            tick_array_0: self.tick_array_0.to_account_info(),
+            tick_array_1: self.tick_array_1.to_account_info(),
+            tick_array_2: self.tick_array_2.to_account_info(),
+            oracle: self.oracle.to_account_info(),
+        };
+        let cpi_program = self.whirlpool_program.to_account_info();
+        CpiContext::new(cpi_program, cpi_accounts)
+    }
+
+}
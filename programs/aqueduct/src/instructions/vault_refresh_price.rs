@@ -0,0 +1,30 @@
+use anchor_lang::prelude::*;
+use whirlpool::state::Whirlpool;
+
+use crate::state::*;
+
+#[derive(Accounts)]
+pub struct VaultRefreshPrice<'info> {
+    pub auth: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [
+            VAULT_PDA_PREFIX,
+            whirlpool.key().as_ref()
+        ],
+        bump,
+        has_one = whirlpool,
+    )]
+    pub vault: Box<Account<'info, Vault>>,
+    pub whirlpool: Box<Account<'info, Whirlpool>>,
+}
+
+// anyone may move the vault reference price, it only ever steps towards the pool price
+pub fn handler(ctx: Context<VaultRefreshPrice>,
+) -> Result<()> {
+
+    ctx.accounts.vault.refresh_reference_price(ctx.accounts.whirlpool.sqrt_price, Clock::get()?.slot)?;
+
+    Ok(())
+}
@@ -0,0 +1,178 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{Mint, Token, TokenAccount};
+use whirlpool::state::{TickArray, Whirlpool};
+use whirlpool::cpi::accounts::Swap;
+
+use crate::errors::AqueductError;
+use crate::events::*;
+use crate::math::{minimum_swap_output, value_in_mint};
+use crate::state::*;
+use crate::WhirlpoolProgram;
+
+#[derive(Accounts)]
+pub struct VaultRewardSwap<'info> {
+    #[account(mut)]
+    pub auth: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [
+            VAULT_PDA_PREFIX,
+            vault.whirlpool.as_ref()
+        ],
+        bump,
+    )]
+    pub vault: Box<Account<'info, Vault>>,
+    #[account(address = vault.whirlpool)]
+    pub vault_whirlpool: Box<Account<'info, Whirlpool>>,
+    #[account(
+        seeds = [
+            RATE_LIMITS_PDA_PREFIX
+        ],
+        bump,
+    )]
+    pub rate_limits: Box<Account<'info, RateLimits>>,
+
+    #[account(
+        seeds = [
+            REWARD_ROUTE_PDA_PREFIX,
+            reward_route.reward_mint.as_ref()
+        ],
+        bump,
+        constraint = reward_route.approved @ AqueductError::RewardNotWhitelisted,
+    )]
+    pub reward_route: Box<Account<'info, RewardRoute>>,
+
+    pub whirlpool_program: Program<'info, WhirlpoolProgram>,
+    #[account(mut, address = reward_route.whirlpool)]
+    pub whirlpool: Box<Account<'info, Whirlpool>>,
+
+    #[account(
+        mut,
+        constraint = reward_account.owner == vault.key(),
+        constraint = reward_account.mint == reward_route.reward_mint,
+    )]
+    pub reward_account: Box<Account<'info, TokenAccount>>,
+    // one of the vault's pair tokens, the route has to trade it against the reward
+    #[account(
+        constraint = output_mint.key() == vault_whirlpool.token_mint_a
+            || output_mint.key() == vault_whirlpool.token_mint_b @ AqueductError::RewardRouteMismatch,
+        constraint = output_mint.key() == whirlpool.token_mint_a
+            || output_mint.key() == whirlpool.token_mint_b @ AqueductError::RewardRouteMismatch,
+        constraint = output_mint.key() != reward_route.reward_mint @ AqueductError::RewardRouteMismatch,
+    )]
+    pub output_mint: Box<Account<'info, Mint>>,
+    // the pair ATA the NAV is read from
+    #[account(
+        mut,
+        associated_token::mint = output_mint,
+        associated_token::authority = vault,
+    )]
+    pub output_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut, address = whirlpool.token_vault_a)]
+    pub token_vault_a: Box<Account<'info, TokenAccount>>,
+    #[account(mut, address = whirlpool.token_vault_b)]
+    pub token_vault_b: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut, has_one = whirlpool)]
+    pub tick_array_0: AccountLoader<'info, TickArray>,
+    #[account(mut, has_one = whirlpool)]
+    pub tick_array_1: AccountLoader<'info, TickArray>,
+    #[account(mut, has_one = whirlpool)]
+    pub tick_array_2: AccountLoader<'info, TickArray>,
+
+    /// CHECK:
+    pub oracle: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+// Sells the vault's whole balance of a whitelisted reward through its route into one of the vault's pair
+// tokens, where it becomes part of the NAV share holders are paid out of.
+pub fn handler<'info>(ctx: Context<'_, '_, '_, 'info, VaultRewardSwap<'info>>,
+) -> Result<()> {
+
+    let vault_whirlpool_key = ctx.accounts.vault.whirlpool;
+    let (_vault_pda, vault_bump) = Pubkey::find_program_address(&[VAULT_PDA_PREFIX, vault_whirlpool_key.as_ref()], ctx.program_id);
+    let seeds = &[VAULT_PDA_PREFIX, vault_whirlpool_key.as_ref(), &[vault_bump]];
+    let signer_seeds = &[&seeds[..]];
+
+    let amount_in = ctx.accounts.reward_account.amount;
+    if amount_in == 0 {
+        return Ok(());
+    }
+
+    let a_to_b = ctx.accounts.whirlpool.token_mint_a == ctx.accounts.reward_route.reward_mint;
+    let sqrt_price_limit = if a_to_b { MIN_SQRT_PRICE } else { MAX_SQRT_PRICE };
+    // bounded by the route reference price, so pushing the pool price ahead of the sale does not lower it
+    let minimum_amount_out = minimum_swap_output(
+        amount_in,
+        ctx.accounts.reward_route.valuation_sqrt_price()?,
+        a_to_b,
+        DEFAULT_REWARD_MAX_SLIPPAGE
+    )?;
+
+    let output_before = ctx.accounts.output_account.amount;
+
+    whirlpool::cpi::swap(
+        ctx.accounts.into_swap_context(a_to_b).with_signer(signer_seeds),
+        amount_in,
+        minimum_amount_out,
+        sqrt_price_limit,
+        true,
+        a_to_b
+    )?;
+
+    ctx.accounts.output_account.reload()?;
+    let amount_out = ctx.accounts.output_account.amount - output_before;
+
+    let output_mint = ctx.accounts.output_mint.key();
+    let (output_a, output_b) = if output_mint == ctx.accounts.vault_whirlpool.token_mint_a { (amount_out, 0) } else { (0, amount_out) };
+    let notional = value_in_mint(output_a, output_b, ctx.accounts.vault.reference_sqrt_price, &ctx.accounts.vault_whirlpool, &ctx.accounts.vault.quote_mint)?;
+    let now = Clock::get()?.unix_timestamp;
+    let rate_limits = &ctx.accounts.rate_limits;
+    ctx.accounts.vault.rate_limit_counters.record_swap(rate_limits, now, notional)?;
+
+    let vault_key = ctx.accounts.vault.key();
+    emit!(RewardSwapped {
+        user: vault_key,
+        user_pda: vault_key,
+        whirlpool: ctx.accounts.whirlpool.key(),
+        reward_mint: ctx.accounts.reward_route.reward_mint,
+        output_mint,
+        amount_in,
+        amount_out,
+        slot: Clock::get()?.slot,
+    });
+
+    Ok(())
+}
+
+impl<'info> VaultRewardSwap<'info> {
+
+    fn into_swap_context(&self, a_to_b: bool) -> CpiContext<'_, '_, '_, 'info, Swap<'info>> {
+        let (token_owner_account_a, token_owner_account_b) = if a_to_b {
+            (self.reward_account.to_account_info(), self.output_account.to_account_info())
+        } else {
+            (self.output_account.to_account_info(), self.reward_account.to_account_info())
+        };
+
+        let cpi_accounts = Swap {
+            token_program: self.token_program.to_account_info(),
+            token_authority: self.vault.to_account_info(),
+            whirlpool: self.whirlpool.to_account_info(),
+            token_owner_account_a,
+            token_vault_a: self.token_vault_a.to_account_info(),
+            token_owner_account_b,
+            token_vault_b: self.token_vault_b.to_account_info(),
+            tick_array_0: self.tick_array_0.to_account_info(),
+            tick_array_1: self.tick_array_1.to_account_info(),
+            tick_array_2: self.tick_array_2.to_account_info(),
+            oracle: self.oracle.to_account_info(),
+        };
+        let cpi_program = self.whirlpool_program.to_account_info();
+        CpiContext::new(cpi_program, cpi_accounts)
+    }
+
+}
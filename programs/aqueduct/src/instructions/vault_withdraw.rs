@@ -0,0 +1,442 @@
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token;
+use anchor_spl::token::{Mint, Token, TokenAccount};
+use whirlpool::state::{Position, Whirlpool};
+
+use crate::errors::AqueductError;
+use crate::events::*;
+use crate::fees::{discounted_bps, fee_amount, load_fee_schedule, load_fee_tiers, pay_referral_share, referral_token_account, split_fee_value};
+use crate::math::*;
+use crate::positions::PositionAccounts;
+use crate::state::*;
+use crate::WhirlpoolProgram;
+
+#[derive(Accounts)]
+#[instruction(
+    shares: u64,
+    minimum_a_amount: u64,
+    minimum_b_amount: u64,
+)]
+pub struct VaultWithdraw<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [
+            VAULT_PDA_PREFIX,
+            whirlpool.key().as_ref()
+        ],
+        bump,
+        has_one = whirlpool,
+        has_one = share_mint,
+    )]
+    pub vault: Box<Account<'info, Vault>>,
+    /// CHECK: the fee schedule PDA, read through load_fee_schedule which falls back to the defaults
+    #[account(
+        seeds = [
+            FEE_SCHEDULE_PDA_PREFIX
+        ],
+        bump,
+    )]
+    pub fee_schedule: UncheckedAccount<'info>,
+    /// CHECK: the fee tiers PDA, read through load_fee_tiers which falls back to no discount
+    #[account(
+        seeds = [
+            FEE_TIERS_PDA_PREFIX
+        ],
+        bump,
+    )]
+    pub fee_tiers: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [
+            POOL_CONFIG_PDA_PREFIX,
+            whirlpool.key().as_ref()
+        ],
+        bump,
+    )]
+    pub pool_config: Box<Account<'info, PoolConfig>>,
+    /// CHECK: the quote mint's config PDA, may not have been created in which case nothing is tracked
+    #[account(
+        mut,
+        seeds = [
+            MINT_CONFIG_PDA_PREFIX,
+            vault.quote_mint.as_ref()
+        ],
+        bump,
+    )]
+    pub mint_config: UncheckedAccount<'info>,
+
+    pub whirlpool_program: Program<'info, WhirlpoolProgram>,
+    #[account(mut)]
+    pub whirlpool: Box<Account<'info, Whirlpool>>,
+
+    #[account(mut)]
+    pub share_mint: Box<Account<'info, Mint>>,
+    #[account(
+        mut,
+        constraint = user_share_account.owner == user.key(),
+        constraint = user_share_account.mint == share_mint.key(),
+    )]
+    pub user_share_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(address = whirlpool.token_mint_a)]
+    pub mint_a: Box<Account<'info, Mint>>,
+    #[account(address = whirlpool.token_mint_b)]
+    pub mint_b: Box<Account<'info, Mint>>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_a,
+        associated_token::authority = vault,
+    )]
+    pub token_owner_account_a: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_b,
+        associated_token::authority = vault,
+    )]
+    pub token_owner_account_b: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut, address = whirlpool.token_vault_a)]
+    pub token_vault_a: Box<Account<'info, TokenAccount>>,
+    #[account(mut, address = whirlpool.token_vault_b)]
+    pub token_vault_b: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = token_aqueduct_a.mint == whirlpool.token_mint_a,
+        constraint = token_aqueduct_a.owner == B_SERVICE @ AqueductError::InvalidTreasuryAccount,
+    )]
+    pub token_aqueduct_a: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = token_aqueduct_b.mint == whirlpool.token_mint_b,
+        constraint = token_aqueduct_b.owner == B_SERVICE @ AqueductError::InvalidTreasuryAccount,
+    )]
+    pub token_aqueduct_b: Box<Account<'info, TokenAccount>>,
+
+    /// CHECK: the withdrawer's user PDA when they have one, only read for its referrer
+    pub user_pda: UncheckedAccount<'info>,
+    /// CHECK: the referrer's token account for mint a, only read when the withdrawer has a referrer
+    #[account(mut)]
+    pub referral_token_a: UncheckedAccount<'info>,
+    /// CHECK: the referrer's token account for mint b, only read when the withdrawer has a referrer
+    #[account(mut)]
+    pub referral_token_b: UncheckedAccount<'info>,
+
+    #[account(
+        init_if_needed,
+        associated_token::mint = mint_a,
+        associated_token::authority = user,
+        payer = user
+    )]
+    pub destination_ata_a: Box<Account<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        associated_token::mint = mint_b,
+        associated_token::authority = user,
+        payer = user
+    )]
+    pub destination_ata_b: Box<Account<'info, TokenAccount>>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Program<'info, Token>,
+    pub rent: Sysvar<'info, Rent>,
+    pub system_program: Program<'info, System>,
+
+    // remaining_accounts are (position, position_token_account, tick_array_lower, tick_array_upper)
+    // when the vault has a position open
+}
+
+pub fn handler<'info>(ctx: Context<'_, '_, '_, 'info, VaultWithdraw<'info>>,
+    shares: u64,
+    minimum_a_amount: u64,
+    minimum_b_amount: u64,
+) -> Result<()> {
+
+    let whirlpool_key = ctx.accounts.whirlpool.key();
+    let (_vault_pda, vault_bump) = Pubkey::find_program_address(&[VAULT_PDA_PREFIX, whirlpool_key.as_ref()], ctx.program_id);
+    let seeds = &[VAULT_PDA_PREFIX, whirlpool_key.as_ref(), &[vault_bump]];
+    let signer_seeds = &[&seeds[..]];
+
+    require!(shares > 0, AqueductError::ZeroShares);
+
+    let supply = ctx.accounts.share_mint.supply;
+    let fee_schedule = load_fee_schedule(&ctx.accounts.fee_schedule)?;
+    let fee_tiers = load_fee_tiers(&ctx.accounts.fee_tiers)?;
+    let vault_key = ctx.accounts.vault.key();
+    let quote_mint = ctx.accounts.vault.quote_mint;
+    let reference_sqrt_price = ctx.accounts.vault.reference_sqrt_price;
+    let now = Clock::get()?.unix_timestamp;
+    let slot = Clock::get()?.slot;
+
+    // share holders without a user PDA have no referrer and the treasury keeps the whole fee
+    let referred_user: Option<Account<User>> = if *ctx.accounts.user_pda.owner == PROGRAM_ID && !ctx.accounts.user_pda.data_is_empty() {
+        let user_pda: Account<User> = Account::try_from(&ctx.accounts.user_pda)?;
+        require!(user_pda.user == ctx.accounts.user.key(), AqueductError::InvalidReferralAccount);
+        Some(user_pda)
+    } else {
+        None
+    };
+    let referral_share_bps = fee_schedule.referral_share_bps;
+
+    let mut total_a = ctx.accounts.token_owner_account_a.amount;
+    let mut total_b = ctx.accounts.token_owner_account_b.amount;
+    let mut position_liquidity = 0u128;
+    if ctx.accounts.vault.has_position() {
+        let position_accounts = ctx.remaining_accounts;
+        require!(position_accounts.len() >= 4, AqueductError::VaultPositionMismatch);
+        require!(position_accounts[0].key() == ctx.accounts.vault.position, AqueductError::VaultPositionMismatch);
+
+        position_liquidity = Account::<Position>::try_from(&position_accounts[0])?.liquidity;
+        ctx.accounts.position_accounts(position_accounts).update_fees_and_rewards(position_liquidity)?;
+
+        let position: Account<Position> = Account::try_from(&position_accounts[0])?;
+        let (position_a, position_b) = position_token_amounts(&position, reference_sqrt_price)?;
+        total_a = total_a.checked_add(position_a).and_then(|v| v.checked_add(position.fee_owed_a)).ok_or(AqueductError::MathOverflow)?;
+        total_b = total_b.checked_add(position_b).and_then(|v| v.checked_add(position.fee_owed_b)).ok_or(AqueductError::MathOverflow)?;
+    }
+
+    // the management fee is brought up to date on the holdings before the withdrawer's share is taken out
+    let value = ctx.accounts.vault.holdings_value(&ctx.accounts.whirlpool, total_a, total_b)?;
+    let discount_bps = ctx.accounts.vault.fee_discount_bps(&fee_tiers, value);
+    let management_fee_bps = discounted_bps(fee_schedule.management_fee_bps, discount_bps);
+    ctx.accounts.vault.accrue_management_fee(total_a, total_b, management_fee_bps, now)?;
+
+    // under the high-water mark mode the withdrawer pays their share of the fee on the gain above the mark
+    let high_water_mark = fee_schedule.high_water_mark_mode();
+    let performance_fee_value = if high_water_mark {
+        let value = ctx.accounts.vault.holdings_value(&ctx.accounts.whirlpool, total_a, total_b)?;
+        let fee_bps = discounted_bps(fee_schedule.performance_fee_bps_for(&quote_mint), discount_bps);
+        mul_div(ctx.accounts.vault.high_water_mark_fee(value, supply, fee_bps)?, shares, supply)?
+    } else {
+        0
+    };
+
+    if ctx.accounts.vault.has_position() {
+        // fees owed are collected first so the withdrawer takes their share of them with the idle balances
+        let position = ctx.accounts.position_accounts(ctx.remaining_accounts);
+        let (fees_a, fees_b) = position.collect_fees(signer_seeds)?;
+
+        emit!(FeesCollected {
+            user: vault_key,
+            user_pda: vault_key,
+            whirlpool: whirlpool_key,
+            position: ctx.accounts.vault.position,
+            amount_a: fees_a,
+            amount_b: fees_b,
+            slot,
+        });
+
+        let (service_amount_token_a, service_amount_token_b) = if high_water_mark {
+            (0, 0)
+        } else {
+            (
+                fee_amount(fees_a, discounted_bps(fee_schedule.performance_fee_bps_for(&ctx.accounts.mint_a.key()), discount_bps))?,
+                fee_amount(fees_b, discounted_bps(fee_schedule.performance_fee_bps_for(&ctx.accounts.mint_b.key()), discount_bps))?,
+            )
+        };
+
+        ctx.accounts.take_fees(signer_seeds, referred_user.as_ref(), referral_share_bps, service_amount_token_a, service_amount_token_b, slot)?;
+
+        ctx.accounts.token_owner_account_a.reload()?;
+        ctx.accounts.token_owner_account_b.reload()?;
+    }
+
+    // idle balances are split before any liquidity is pulled into the same accounts
+    let idle_a = mul_div(ctx.accounts.token_owner_account_a.amount, shares, supply)?;
+    let idle_b = mul_div(ctx.accounts.token_owner_account_b.amount, shares, supply)?;
+
+    let mut amount_a = idle_a;
+    let mut amount_b = idle_b;
+
+    let liquidity = mul_div_u128(position_liquidity, shares, supply)?;
+    if liquidity > 0 {
+        let (liquidity_a, liquidity_b) = ctx.accounts.position_accounts(ctx.remaining_accounts)
+            .decrease_liquidity(signer_seeds, liquidity)?;
+
+        ctx.accounts.token_owner_account_a.reload()?;
+        ctx.accounts.token_owner_account_b.reload()?;
+        ctx.accounts.pool_config.remove_liquidity(liquidity);
+
+        amount_a += liquidity_a;
+        amount_b += liquidity_b;
+
+        emit!(LiquidityRemoved {
+            user: vault_key,
+            user_pda: vault_key,
+            whirlpool: whirlpool_key,
+            position: ctx.accounts.vault.position,
+            liquidity,
+            amount_a: liquidity_a,
+            amount_b: liquidity_b,
+            sqrt_price: ctx.accounts.whirlpool.sqrt_price,
+            slot,
+        });
+    }
+
+    // the withdrawer's share of the management fee owed leaves the vault with them
+    let management_fee_a = mul_div(ctx.accounts.vault.management_fee_owed_a, shares, supply)?.min(amount_a);
+    let management_fee_b = mul_div(ctx.accounts.vault.management_fee_owed_b, shares, supply)?.min(amount_b);
+    ctx.accounts.vault.management_fee_owed_a -= management_fee_a;
+    ctx.accounts.vault.management_fee_owed_b -= management_fee_b;
+    amount_a -= management_fee_a;
+    amount_b -= management_fee_b;
+
+    let withdrawn_value = value_in_mint(amount_a, amount_b, reference_sqrt_price, &ctx.accounts.whirlpool, &quote_mint)?;
+    let (performance_fee_a, performance_fee_b) = split_fee_value(amount_a, amount_b, performance_fee_value, withdrawn_value)?;
+    amount_a -= performance_fee_a;
+    amount_b -= performance_fee_b;
+
+    let withdrawal_fee_a = fee_amount(amount_a, discounted_bps(fee_schedule.withdrawal_fee_bps_for(&ctx.accounts.mint_a.key()), discount_bps))?;
+    let withdrawal_fee_b = fee_amount(amount_b, discounted_bps(fee_schedule.withdrawal_fee_bps_for(&ctx.accounts.mint_b.key()), discount_bps))?;
+    amount_a -= withdrawal_fee_a;
+    amount_b -= withdrawal_fee_b;
+
+    require!(amount_a >= minimum_a_amount, AqueductError::AmountBelowMinimum);
+    require!(amount_b >= minimum_b_amount, AqueductError::AmountBelowMinimum);
+
+    // vault deposits are counted in the quote mint, so the payout comes off it at its quote value
+    let payout_value = value_in_mint(amount_a, amount_b, reference_sqrt_price, &ctx.accounts.whirlpool, &quote_mint)?;
+    record_mint_withdraw(&ctx.accounts.mint_config, payout_value)?;
+
+    token::burn(
+        CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            token::Burn {
+                mint: ctx.accounts.share_mint.to_account_info(),
+                from: ctx.accounts.user_share_account.to_account_info(),
+                authority: ctx.accounts.user.to_account_info(),
+            },
+        ),
+        shares,
+    )?;
+
+    ctx.accounts.take_fees(
+        signer_seeds,
+        referred_user.as_ref(),
+        referral_share_bps,
+        management_fee_a + performance_fee_a + withdrawal_fee_a,
+        management_fee_b + performance_fee_b + withdrawal_fee_b,
+        slot,
+    )?;
+
+    if amount_a > 0 {
+        token::transfer(
+            ctx.accounts.into_transfer_context(
+                ctx.accounts.token_owner_account_a.to_account_info(),
+                ctx.accounts.destination_ata_a.to_account_info(),
+            ).with_signer(signer_seeds),
+            amount_a,
+        )?;
+    }
+
+    if amount_b > 0 {
+        token::transfer(
+            ctx.accounts.into_transfer_context(
+                ctx.accounts.token_owner_account_b.to_account_info(),
+                ctx.accounts.destination_ata_b.to_account_info(),
+            ).with_signer(signer_seeds),
+            amount_b,
+        )?;
+    }
+
+    emit!(VaultWithdrawn {
+        user: ctx.accounts.user.key(),
+        vault: vault_key,
+        shares,
+        amount_a,
+        amount_b,
+        share_supply: supply - shares,
+        slot,
+    });
+
+    Ok(())
+}
+
+impl<'info> VaultWithdraw<'info> {
+
+    // moves fees from the vault's pair accounts, the withdrawer's referrer gets their share and the treasury the rest
+    fn take_fees(&self,
+        signer_seeds: &[&[&[u8]]],
+        referred_user: Option<&Account<'info, User>>,
+        referral_share_bps: u16,
+        amount_a: u64,
+        amount_b: u64,
+        slot: u64,
+    ) -> Result<()> {
+        for (from, to, referral_info, amount) in [
+            (&self.token_owner_account_a, &self.token_aqueduct_a, &self.referral_token_a, amount_a),
+            (&self.token_owner_account_b, &self.token_aqueduct_b, &self.referral_token_b, amount_b),
+        ] {
+            if amount == 0 {
+                continue;
+            }
+
+            let amount = match referred_user {
+                Some(user_pda) => {
+                    let referral = referral_token_account(user_pda, referral_info, &from.mint, &PROGRAM_ID)?;
+                    amount - pay_referral_share(
+                        user_pda,
+                        self.vault.to_account_info(),
+                        from.to_account_info(),
+                        referral.as_ref(),
+                        self.token_program.to_account_info(),
+                        signer_seeds,
+                        amount,
+                        referral_share_bps,
+                    )?
+                }
+                None => amount,
+            };
+
+            token::transfer(
+                self.into_transfer_context(from.to_account_info(), to.to_account_info()).with_signer(signer_seeds),
+                amount,
+            )?;
+
+            emit!(ProtocolFeeTaken {
+                user: self.vault.key(),
+                user_pda: self.vault.key(),
+                position: self.vault.position,
+                mint: from.mint,
+                destination: to.key(),
+                amount,
+                slot,
+            });
+        }
+
+        Ok(())
+    }
+
+    fn into_transfer_context(&self, from: AccountInfo<'info>, to: AccountInfo<'info>) -> CpiContext<'_, '_, '_, 'info, token::Transfer<'info>> {
+        let cpi_accounts = token::Transfer {
+            from,
+            to,
+            authority: self.vault.to_account_info(),
+        };
+        let cpi_program = self.token_program.to_account_info();
+        CpiContext::new(cpi_program, cpi_accounts)
+    }
+
+    fn position_accounts(&self, position_accounts: &[AccountInfo<'info>]) -> PositionAccounts<'info> {
+        PositionAccounts {
+            whirlpool_program: self.whirlpool_program.to_account_info(),
+            whirlpool: self.whirlpool.to_account_info(),
+            position_authority: self.vault.to_account_info(),
+            position: position_accounts[0].to_account_info(),
+            position_token_account: position_accounts[1].to_account_info(),
+            token_owner_account_a: self.token_owner_account_a.to_account_info(),
+            token_owner_account_b: self.token_owner_account_b.to_account_info(),
+            token_vault_a: self.token_vault_a.to_account_info(),
+            token_vault_b: self.token_vault_b.to_account_info(),
+            tick_array_lower: position_accounts[2].to_account_info(),
+            tick_array_upper: position_accounts[3].to_account_info(),
+            token_program: self.token_program.to_account_info(),
+        }
+    }
+
+}
@@ -0,0 +1,406 @@
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token;
+use anchor_spl::token::{Mint, Token, TokenAccount};
+use whirlpool::state::{Position, Whirlpool};
+
+use crate::errors::AqueductError;
+use crate::events::*;
+use crate::fees::{discounted_bps, fee_amount, load_fee_schedule, load_fee_tiers, referral_token_account, split_fee_value, take_service_fee, user_fee_discount_bps};
+use crate::math::*;
+use crate::positions::PositionAccounts;
+use crate::state::*;
+use crate::WhirlpoolProgram;
+
+#[derive(Accounts)]
+#[instruction(
+    fraction: u64,
+)]
+pub struct WithdrawInKind<'info> {
+    #[account(
+        mut,
+        constraint = user.key() == user_pda.user
+    )]
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [
+            USER_PDA_PREFIX,
+            user_pda.vault_id.as_ref()
+        ],
+        bump,
+    )]
+    pub user_pda: Box<Account<'info, User>>,
+
+    pub whirlpool_program: Program<'info, WhirlpoolProgram>,
+    #[account(mut)]
+    pub whirlpool: Box<Account<'info, Whirlpool>>,
+    #[account(
+        mut,
+        seeds = [
+            POOL_CONFIG_PDA_PREFIX,
+            whirlpool.key().as_ref()
+        ],
+        bump,
+    )]
+    pub pool_config: Box<Account<'info, PoolConfig>>,
+    /// CHECK: the mint's config PDA, may not have been created in which case nothing is capped
+    #[account(
+        mut,
+        seeds = [
+            MINT_CONFIG_PDA_PREFIX,
+            whirlpool.token_mint_a.as_ref()
+        ],
+        bump,
+    )]
+    pub mint_config_a: UncheckedAccount<'info>,
+    /// CHECK: the mint's config PDA, may not have been created in which case nothing is capped
+    #[account(
+        mut,
+        seeds = [
+            MINT_CONFIG_PDA_PREFIX,
+            whirlpool.token_mint_b.as_ref()
+        ],
+        bump,
+    )]
+    pub mint_config_b: UncheckedAccount<'info>,
+    /// CHECK: the fee schedule PDA, read through load_fee_schedule which falls back to the defaults
+    #[account(
+        seeds = [
+            FEE_SCHEDULE_PDA_PREFIX
+        ],
+        bump,
+    )]
+    pub fee_schedule: UncheckedAccount<'info>,
+    /// CHECK: the fee tiers PDA, read through load_fee_tiers which falls back to no discount
+    #[account(
+        seeds = [
+            FEE_TIERS_PDA_PREFIX
+        ],
+        bump,
+    )]
+    pub fee_tiers: UncheckedAccount<'info>,
+    /// CHECK: the user's stake PDA, matched and only read once it exists in user_fee_discount_bps
+    pub stake: UncheckedAccount<'info>,
+
+    #[account(address = whirlpool.token_mint_a)]
+    pub mint_a: Box<Account<'info, Mint>>,
+    #[account(address = whirlpool.token_mint_b)]
+    pub mint_b: Box<Account<'info, Mint>>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_a,
+        associated_token::authority = user_pda,
+    )]
+    pub token_owner_account_a: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_b,
+        associated_token::authority = user_pda,
+    )]
+    pub token_owner_account_b: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut, address = whirlpool.token_vault_a)]
+    pub token_vault_a: Box<Account<'info, TokenAccount>>,
+    #[account(mut, address = whirlpool.token_vault_b)]
+    pub token_vault_b: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = token_aqueduct_a.mint == whirlpool.token_mint_a,
+        constraint = token_aqueduct_a.owner == B_SERVICE @ AqueductError::InvalidTreasuryAccount,
+    )]
+    pub token_aqueduct_a: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = token_aqueduct_b.mint == whirlpool.token_mint_b,
+        constraint = token_aqueduct_b.owner == B_SERVICE @ AqueductError::InvalidTreasuryAccount,
+    )]
+    pub token_aqueduct_b: Box<Account<'info, TokenAccount>>,
+
+    /// CHECK: the referrer's token account for mint a, only read when the user has a referrer
+    #[account(mut)]
+    pub referral_token_a: UncheckedAccount<'info>,
+    /// CHECK: the referrer's token account for mint b, only read when the user has a referrer
+    #[account(mut)]
+    pub referral_token_b: UncheckedAccount<'info>,
+
+    #[account(
+        init_if_needed,
+        associated_token::mint = mint_a,
+        associated_token::authority = user,
+        payer = user
+    )]
+    pub destination_ata_a: Box<Account<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        associated_token::mint = mint_b,
+        associated_token::authority = user,
+        payer = user
+    )]
+    pub destination_ata_b: Box<Account<'info, TokenAccount>>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    #[account(address = token::ID)]
+    pub token_program: Program<'info, Token>,
+    pub rent: Sysvar<'info, Rent>,
+    pub system_program: Program<'info, System>,
+
+    // remaining_accounts are (position, position_token_account, tick_array_lower, tick_array_upper)
+    // groups for each of the user's positions in the whirlpool
+}
+
+pub fn handler<'info>(ctx: Context<'_, '_, '_, 'info, WithdrawInKind<'info>>,
+    fraction: u64,
+) -> Result<()> {
+
+    let vault_id = ctx.accounts.user_pda.vault_id;
+    let (_user_pda, user_pda_bump) = Pubkey::find_program_address(&[USER_PDA_PREFIX, vault_id.as_ref()], ctx.program_id);
+    let seeds = &[USER_PDA_PREFIX, vault_id.as_ref(), &[user_pda_bump]];
+    let signer_seeds = &[&seeds[..]];
+
+    require!(fraction > 0 && fraction <= RESOLUTION, AqueductError::InvalidFraction);
+    require!(ctx.remaining_accounts.len() % 4 == 0, AqueductError::InvalidPositionAccounts);
+
+    let fee_schedule = load_fee_schedule(&ctx.accounts.fee_schedule)?;
+    let fee_tiers = load_fee_tiers(&ctx.accounts.fee_tiers)?;
+    let discount_bps = user_fee_discount_bps(&fee_tiers, &ctx.accounts.user_pda, &ctx.accounts.stake, ctx.program_id)?;
+
+    let idle_a = ctx.accounts.token_owner_account_a.amount;
+    let idle_b = ctx.accounts.token_owner_account_b.amount;
+
+    let mut liquidity_a: u64 = 0;
+    let mut liquidity_b: u64 = 0;
+    let mut fees_a: u64 = 0;
+    let mut fees_b: u64 = 0;
+    let mut released_basis: u64 = 0;
+    let mut released_value: u64 = 0;
+    let mut management_fee_value: u64 = 0;
+
+    let quote_mint = ctx.accounts.user_pda.effective_quote_mint();
+    // the price the cost basis was booked at, see PoolConfig::valuation_sqrt_price
+    let sqrt_price = ctx.accounts.pool_config.valuation_sqrt_price()?;
+    let slot = Clock::get()?.slot;
+
+    let management_fee_bps = discounted_bps(fee_schedule.management_fee_bps, discount_bps);
+    ctx.accounts.user_pda.accrue_management_fee(management_fee_bps, Clock::get()?.unix_timestamp)?;
+
+    let mut positions: Vec<Pubkey> = Vec::new();
+
+    for position_accounts in ctx.remaining_accounts.chunks(4) {
+        let position: Account<Position> = Account::try_from(&position_accounts[0])?;
+        let position_token_account: Account<TokenAccount> = Account::try_from(&position_accounts[1])?;
+
+        require!(position.whirlpool == ctx.accounts.whirlpool.key(), AqueductError::InvalidPositionAccounts);
+        require!(position_token_account.owner == ctx.accounts.user_pda.key(), AqueductError::InvalidPositionAccounts);
+        require!(position_token_account.mint == position.position_mint, AqueductError::InvalidPositionAccounts);
+        require!(position_token_account.amount == 1, AqueductError::InvalidPositionAccounts);
+        // a position passed twice would be counted twice in the fraction and the cost basis
+        require!(!positions.contains(&position.key()), AqueductError::DuplicatePosition);
+
+        positions.push(position.key());
+
+        let liquidity = mul_div_u128(position.liquidity, fraction, RESOLUTION)?;
+
+        let position_cpi = ctx.accounts.position_accounts(position_accounts);
+        position_cpi.update_fees_and_rewards(position.liquidity)?;
+        let (position_a, position_b) = position_cpi.decrease_liquidity(signer_seeds, liquidity)?;
+
+        ctx.accounts.pool_config.remove_liquidity(liquidity);
+        // handed back in kind, so the cost basis goes with it and no loss is realized
+        management_fee_value += ctx.accounts.user_pda.release_management_fee(liquidity);
+        let released = ctx.accounts.user_pda.release_cost_basis(liquidity);
+
+        liquidity_a += position_a;
+        liquidity_b += position_b;
+
+        if released > 0 {
+            released_basis += released;
+            let value = value_in_user_quote(position_a, position_b, sqrt_price, &ctx.accounts.whirlpool, &quote_mint)?;
+            released_value = released_value.checked_add(value).ok_or(AqueductError::MathOverflow)?;
+        }
+
+        let (position_fees_a, position_fees_b) = position_cpi.collect_fees(signer_seeds)?;
+        fees_a += position_fees_a;
+        fees_b += position_fees_b;
+
+        emit!(LiquidityRemoved {
+            user: ctx.accounts.user_pda.user,
+            user_pda: ctx.accounts.user_pda.key(),
+            whirlpool: ctx.accounts.whirlpool.key(),
+            position: position.key(),
+            liquidity,
+            amount_a: position_a,
+            amount_b: position_b,
+            sqrt_price,
+            slot,
+        });
+
+        emit!(FeesCollected {
+            user: ctx.accounts.user_pda.user,
+            user_pda: ctx.accounts.user_pda.key(),
+            whirlpool: ctx.accounts.whirlpool.key(),
+            position: position.key(),
+            amount_a: position_fees_a,
+            amount_b: position_fees_b,
+            slot,
+        });
+    }
+
+    let mint_a = ctx.accounts.mint_a.key();
+    let mint_b = ctx.accounts.mint_b.key();
+
+    // under the high-water mark mode collected fees are charged as part of the net profit on what is withdrawn
+    let high_water_mark = fee_schedule.high_water_mark_mode();
+    let (service_amount_token_a, service_amount_token_b) = if high_water_mark {
+        (0, 0)
+    } else {
+        (
+            fee_amount(fees_a, discounted_bps(fee_schedule.performance_fee_bps_for(&mint_a), discount_bps))?,
+            fee_amount(fees_b, discounted_bps(fee_schedule.performance_fee_bps_for(&mint_b), discount_bps))?,
+        )
+    };
+
+    let referral_share_bps = fee_schedule.referral_share_bps;
+    let referral_a = referral_token_account(&ctx.accounts.user_pda, &ctx.accounts.referral_token_a, &mint_a, ctx.program_id)?;
+    let referral_b = referral_token_account(&ctx.accounts.user_pda, &ctx.accounts.referral_token_b, &mint_b, ctx.program_id)?;
+
+    // liquidity was already pulled at the requested fraction, idle balances and net fees are split here
+    let amount_a = liquidity_a + mul_div(idle_a + fees_a - service_amount_token_a, fraction, RESOLUTION)?;
+    let amount_b = liquidity_b + mul_div(idle_b + fees_b - service_amount_token_b, fraction, RESOLUTION)?;
+
+    // positions opened before cost basis tracking have nothing to measure profit against
+    let (performance_fee_a, performance_fee_b) = if high_water_mark && released_basis > 0 {
+        let fees_value = value_in_user_quote(fees_a, fees_b, sqrt_price, &ctx.accounts.whirlpool, &quote_mint)?;
+        let value = released_value.checked_add(fees_value).ok_or(AqueductError::MathOverflow)?;
+        let pnl = realized_pnl(value, released_basis)?;
+        let fee_bps = discounted_bps(fee_schedule.performance_fee_bps_for(&quote_mint), discount_bps);
+        let fee_value = ctx.accounts.user_pda.record_realized_pnl(pnl, fee_bps)?;
+        let withdrawn_value = value_in_user_quote(amount_a, amount_b, sqrt_price, &ctx.accounts.whirlpool, &quote_mint)?;
+
+        split_fee_value(amount_a, amount_b, fee_value, withdrawn_value)?
+    } else {
+        (0, 0)
+    };
+
+    let amount_a = amount_a - performance_fee_a;
+    let amount_b = amount_b - performance_fee_b;
+
+    // the share of the management fee owed that goes with the liquidity withdrawn
+    let (management_fee_a, management_fee_b) = if management_fee_value > 0 {
+        let withdrawn_value = value_in_user_quote(amount_a, amount_b, sqrt_price, &ctx.accounts.whirlpool, &quote_mint)?;
+        split_fee_value(amount_a, amount_b, management_fee_value, withdrawn_value)?
+    } else {
+        (0, 0)
+    };
+
+    let amount_a = amount_a - management_fee_a;
+    let amount_b = amount_b - management_fee_b;
+
+    let withdrawal_fee_a = fee_amount(amount_a, discounted_bps(fee_schedule.withdrawal_fee_bps_for(&mint_a), discount_bps))?;
+    let withdrawal_fee_b = fee_amount(amount_b, discounted_bps(fee_schedule.withdrawal_fee_bps_for(&mint_b), discount_bps))?;
+    let amount_a = amount_a - withdrawal_fee_a;
+    let amount_b = amount_b - withdrawal_fee_b;
+
+    // the fees are spread over every position, so their fee events name none
+    for (from, to, referral, amount) in [
+        (
+            &ctx.accounts.token_owner_account_a,
+            &ctx.accounts.token_aqueduct_a,
+            referral_a.as_ref(),
+            service_amount_token_a + performance_fee_a + management_fee_a + withdrawal_fee_a,
+        ),
+        (
+            &ctx.accounts.token_owner_account_b,
+            &ctx.accounts.token_aqueduct_b,
+            referral_b.as_ref(),
+            service_amount_token_b + performance_fee_b + management_fee_b + withdrawal_fee_b,
+        ),
+    ] {
+        take_service_fee(
+            &ctx.accounts.user_pda,
+            from,
+            to,
+            referral,
+            referral_share_bps,
+            ctx.accounts.token_program.to_account_info(),
+            signer_seeds,
+            Pubkey::default(),
+            amount,
+        )?;
+    }
+
+    if amount_a > 0 {
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token::Transfer {
+                    from: ctx.accounts.token_owner_account_a.to_account_info(),
+                    to: ctx.accounts.destination_ata_a.to_account_info(),
+                    authority: ctx.accounts.user_pda.to_account_info(),
+                },
+            ).with_signer(signer_seeds),
+            amount_a,
+        )?;
+    }
+
+    if amount_b > 0 {
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token::Transfer {
+                    from: ctx.accounts.token_owner_account_b.to_account_info(),
+                    to: ctx.accounts.destination_ata_b.to_account_info(),
+                    authority: ctx.accounts.user_pda.to_account_info(),
+                },
+            ).with_signer(signer_seeds),
+            amount_b,
+        )?;
+    }
+
+    record_mint_withdraw(&ctx.accounts.mint_config_a, amount_a)?;
+    record_mint_withdraw(&ctx.accounts.mint_config_b, amount_b)?;
+    let withdrawn_value = value_in_user_quote(amount_a, amount_b, sqrt_price, &ctx.accounts.whirlpool, &quote_mint).ok();
+    ctx.accounts.user_pda.record_withdrawal(withdrawn_value);
+
+    ctx.accounts.token_owner_account_a.reload()?;
+    ctx.accounts.token_owner_account_b.reload()?;
+
+    for (mint, amount, balance) in [
+        (mint_a, amount_a, ctx.accounts.token_owner_account_a.amount),
+        (mint_b, amount_b, ctx.accounts.token_owner_account_b.amount),
+    ] {
+        emit!(WithdrawEvent {
+            user: ctx.accounts.user_pda.user,
+            user_pda: ctx.accounts.user_pda.key(),
+            mint,
+            amount,
+            balance,
+            slot,
+        });
+    }
+
+    Ok(())
+}
+
+impl<'info> WithdrawInKind<'info> {
+
+    fn position_accounts(&self, position_accounts: &[AccountInfo<'info>]) -> PositionAccounts<'info> {
+        PositionAccounts {
+            whirlpool_program: self.whirlpool_program.to_account_info(),
+            whirlpool: self.whirlpool.to_account_info(),
+            position_authority: self.user_pda.to_account_info(),
+            position: position_accounts[0].to_account_info(),
+            position_token_account: position_accounts[1].to_account_info(),
+            token_owner_account_a: self.token_owner_account_a.to_account_info(),
+            token_owner_account_b: self.token_owner_account_b.to_account_info(),
+            token_vault_a: self.token_vault_a.to_account_info(),
+            token_vault_b: self.token_vault_b.to_account_info(),
+            tick_array_lower: position_accounts[2].to_account_info(),
+            tick_array_upper: position_accounts[3].to_account_info(),
+            token_program: self.token_program.to_account_info(),
+        }
+    }
+
+}
@@ -5,11 +5,19 @@ extern crate solana_program;
 use anchor_lang::prelude::*;
 
 use instructions::*;
-use state::{ADMIN, B_SERVICE};
+use state::{FeeTier, RangeSpec, ServiceIntent, ADMIN, B_SERVICE};
 
+pub mod ed25519;
+pub mod errors;
+pub mod events;
+pub mod fees;
 pub mod instructions;
 pub mod integrations;
+pub mod math;
+pub mod positions;
+pub mod rewards;
 pub mod state;
+pub mod ticks;
 
 declare_id!("EU3CcRRS2G4RR5bj7AogBNvErnf1G95gumA9b9fQ2Sco");
 
@@ -28,32 +36,369 @@ pub mod aqueduct {
         )
     }
 
+    #[access_control(
+        admin_access(&ctx.accounts.auth)
+    )]
+    pub fn set_pool_approval(
+        ctx: Context<SetPoolApproval>,
+        approved: bool,
+    ) -> Result<()> {
+        set_pool_approval::handler(
+            ctx,
+            approved
+        )
+    }
+
+    #[access_control(
+        admin_access(&ctx.accounts.auth)
+    )]
+    pub fn add_quote_config(
+        ctx: Context<AddQuoteConfig>,
+    ) -> Result<()> {
+        add_quote_config::handler(
+            ctx,
+        )
+    }
+
+    #[access_control(
+        admin_access(&ctx.accounts.auth)
+    )]
+    pub fn set_mint_config(
+        ctx: Context<SetMintConfig>,
+        user_deposit_cap: u64,
+        protocol_deposit_cap: u64,
+        max_allocation_percent: u8,
+    ) -> Result<()> {
+        set_mint_config::handler(
+            ctx,
+            user_deposit_cap,
+            protocol_deposit_cap,
+            max_allocation_percent
+        )
+    }
+
+    #[access_control(
+        admin_access(&ctx.accounts.auth)
+    )]
+    pub fn set_pool_config_caps(
+        ctx: Context<SetPoolConfigCaps>,
+        liquidity_cap: u128,
+    ) -> Result<()> {
+        set_pool_config_caps::handler(
+            ctx,
+            liquidity_cap
+        )
+    }
+
+    #[access_control(
+        admin_access(&ctx.accounts.auth)
+    )]
+    pub fn set_config(
+        ctx: Context<SetConfig>,
+        attestation_required: bool,
+    ) -> Result<()> {
+        set_config::handler(
+            ctx,
+            attestation_required
+        )
+    }
+
+    #[access_control(
+        admin_access(&ctx.accounts.auth)
+    )]
+    pub fn set_rate_limits(
+        ctx: Context<SetRateLimits>,
+        rebalance_cooldown_seconds: i64,
+        max_rebalances_per_day: u16,
+        max_swaps_per_day: u16,
+        max_swap_notional_per_day: u64,
+    ) -> Result<()> {
+        set_rate_limits::handler(
+            ctx,
+            rebalance_cooldown_seconds,
+            max_rebalances_per_day,
+            max_swaps_per_day,
+            max_swap_notional_per_day
+        )
+    }
+
+    #[access_control(
+        admin_access(&ctx.accounts.auth)
+    )]
+    pub fn set_fee_schedule(
+        ctx: Context<SetFeeSchedule>,
+        management_fee_bps: u16,
+        performance_fee_bps: u16,
+        withdrawal_fee_bps: u16,
+        performance_fee_mode: u8,
+    ) -> Result<()> {
+        set_fee_schedule::handler(
+            ctx,
+            management_fee_bps,
+            performance_fee_bps,
+            withdrawal_fee_bps,
+            performance_fee_mode
+        )
+    }
+
+    #[access_control(
+        admin_access(&ctx.accounts.auth)
+    )]
+    pub fn set_fee_override(
+        ctx: Context<SetFeeOverride>,
+        mint: Pubkey,
+        performance_fee_bps: u16,
+        withdrawal_fee_bps: u16,
+        enabled: bool,
+    ) -> Result<()> {
+        set_fee_override::handler(
+            ctx,
+            mint,
+            performance_fee_bps,
+            withdrawal_fee_bps,
+            enabled
+        )
+    }
+
+    #[access_control(
+        admin_access(&ctx.accounts.auth)
+    )]
+    pub fn set_referral_share(
+        ctx: Context<SetReferralShare>,
+        referral_share_bps: u16,
+    ) -> Result<()> {
+        set_referral_share::handler(
+            ctx,
+            referral_share_bps
+        )
+    }
+
+    #[access_control(
+        admin_access(&ctx.accounts.auth)
+    )]
+    pub fn set_fee_tiers(
+        ctx: Context<SetFeeTiers>,
+        stake_mint: Pubkey,
+        tiers: Vec<FeeTier>,
+    ) -> Result<()> {
+        set_fee_tiers::handler(
+            ctx,
+            stake_mint,
+            tiers
+        )
+    }
+
+    #[access_control(
+        admin_access(&ctx.accounts.auth)
+    )]
+    pub fn set_reward_route(
+        ctx: Context<SetRewardRoute>,
+        approved: bool,
+    ) -> Result<()> {
+        set_reward_route::handler(
+            ctx,
+            approved
+        )
+    }
+
+    #[access_control(
+        admin_access(&ctx.accounts.auth)
+    )]
+    pub fn allowlist_add(
+        ctx: Context<AllowlistAdd>,
+    ) -> Result<()> {
+        allowlist_add::handler(
+            ctx,
+        )
+    }
+
+    #[access_control(
+        admin_access(&ctx.accounts.auth)
+    )]
+    pub fn allowlist_remove(
+        ctx: Context<AllowlistRemove>,
+    ) -> Result<()> {
+        allowlist_remove::handler(
+            ctx,
+        )
+    }
+
+    #[access_control(
+        admin_access(&ctx.accounts.auth)
+    )]
+    pub fn user_set_exit_only(
+        ctx: Context<UserSetExitOnly>,
+        exit_only: bool,
+    ) -> Result<()> {
+        user_set_exit_only::handler(
+            ctx,
+            exit_only
+        )
+    }
+
     pub fn user_create(
         ctx: Context<UserCreate>,
+        vault_id: Pubkey,
+        referrer: Option<Pubkey>,
+        attestation_expiry_slot: u64,
     ) -> Result<()> {
         user_create::handler(
             ctx,
+            vault_id,
+            referrer,
+            attestation_expiry_slot,
+        )
+    }
+
+    pub fn referral_claim(
+        ctx: Context<ReferralClaim>,
+    ) -> Result<()> {
+        referral_claim::handler(
+            ctx,
+        )
+    }
+
+    pub fn stake_deposit(
+        ctx: Context<StakeDeposit>,
+        amount: u64,
+    ) -> Result<()> {
+        stake_deposit::handler(
+            ctx,
+            amount
+        )
+    }
+
+    pub fn stake_withdraw(
+        ctx: Context<StakeWithdraw>,
+        amount: u64,
+    ) -> Result<()> {
+        stake_withdraw::handler(
+            ctx,
+            amount
+        )
+    }
+
+    pub fn user_set_reward_conversion(
+        ctx: Context<UserSetRewardConversion>,
+        reward_conversion: u8,
+        reward_max_slippage: u64,
+    ) -> Result<()> {
+        user_set_reward_conversion::handler(
+            ctx,
+            reward_conversion,
+            reward_max_slippage
         )
     }
 
     pub fn user_settings(
         ctx: Context<UserSettings>,
         is_paused: bool,
+        emergency_exit_slots: u64,
     ) -> Result<()> {
         user_settings::handler(
             ctx,
-            is_paused
+            is_paused,
+            emergency_exit_slots
         )
     }
 
-    pub fn user_delete(
-        ctx: Context<UserDelete>,
+    pub fn user_delete<'info>(
+        ctx: Context<'_, '_, '_, 'info, UserDelete<'info>>,
     ) -> Result<()> {
         user_delete::handler(
             ctx,
         )
     }
 
+    pub fn user_migrate(
+        ctx: Context<UserMigrate>,
+    ) -> Result<()> {
+        user_migrate::handler(
+            ctx,
+        )
+    }
+
+    #[access_control(
+        service_access(&ctx.accounts.auth)
+    )]
+    pub fn user_count_positions<'info>(
+        ctx: Context<'_, '_, '_, 'info, UserCountPositions<'info>>,
+    ) -> Result<()> {
+        user_count_positions::handler(
+            ctx,
+        )
+    }
+
+    pub fn user_transfer_ownership(
+        ctx: Context<UserTransferOwnership>,
+        new_owner: Pubkey,
+    ) -> Result<()> {
+        user_transfer_ownership::handler(
+            ctx,
+            new_owner
+        )
+    }
+
+    pub fn user_accept_ownership(
+        ctx: Context<UserAcceptOwnership>,
+    ) -> Result<()> {
+        user_accept_ownership::handler(
+            ctx,
+        )
+    }
+
+    pub fn user_set_delegate(
+        ctx: Context<UserSetDelegate>,
+        delegate: Pubkey,
+        permissions: u8,
+    ) -> Result<()> {
+        user_set_delegate::handler(
+            ctx,
+            delegate,
+            permissions
+        )
+    }
+
+    pub fn user_set_quote_mint(
+        ctx: Context<UserSetQuoteMint>,
+    ) -> Result<()> {
+        user_set_quote_mint::handler(
+            ctx,
+        )
+    }
+
+    pub fn user_set_intents_required(
+        ctx: Context<UserSetIntentsRequired>,
+        intents_required: bool,
+    ) -> Result<()> {
+        user_set_intents_required::handler(
+            ctx,
+            intents_required
+        )
+    }
+
+    pub fn user_set_loss_guard(
+        ctx: Context<UserSetLossGuard>,
+        loss_guard_threshold: u64,
+        loss_guard_window_seconds: i64,
+    ) -> Result<()> {
+        user_set_loss_guard::handler(
+            ctx,
+            loss_guard_threshold,
+            loss_guard_window_seconds
+        )
+    }
+
+    pub fn user_set_range(
+        ctx: Context<UserSetRange>,
+        range_spec: Option<RangeSpec>,
+    ) -> Result<()> {
+        user_set_range::handler(
+            ctx,
+            range_spec
+        )
+    }
+
     #[access_control(
         service_access(&ctx.accounts.auth)
     )]
@@ -62,12 +407,50 @@ pub mod aqueduct {
         position_bump: u8,
         tick_lower_index: i32,
         tick_upper_index: i32,
+        intent: Option<ServiceIntent>,
+        metadata_bump: Option<u8>,
+        range_spec: Option<RangeSpec>,
     ) -> Result<()> {
         position_open::handler(
             ctx,
             position_bump,
             tick_lower_index,
             tick_upper_index,
+            intent,
+            metadata_bump,
+            range_spec,
+        )
+    }
+
+    #[access_control(
+        service_access(&ctx.accounts.auth)
+    )]
+    pub fn position_bundle_init<'info>(
+        ctx: Context<'_, '_, '_, 'info, PositionBundleInit<'info>>,
+    ) -> Result<()> {
+        position_bundle_init::handler(
+            ctx,
+        )
+    }
+
+    #[access_control(
+        service_access(&ctx.accounts.auth)
+    )]
+    pub fn position_open_bundled<'info>(
+        ctx: Context<'_, '_, '_, 'info, PositionOpenBundled<'info>>,
+        bundle_index: u16,
+        tick_lower_index: i32,
+        tick_upper_index: i32,
+        intent: Option<ServiceIntent>,
+        range_spec: Option<RangeSpec>,
+    ) -> Result<()> {
+        position_open_bundled::handler(
+            ctx,
+            bundle_index,
+            tick_lower_index,
+            tick_upper_index,
+            intent,
+            range_spec,
         )
     }
 
@@ -78,11 +461,13 @@ pub mod aqueduct {
         ctx: Context<'_, '_, '_, 'info, PositionSwap<'info>>,
         a_to_b: bool,
         amount: u64,
+        intent: Option<ServiceIntent>,
     ) -> Result<()> {
         position_swap::handler(
             ctx,
             a_to_b,
             amount,
+            intent,
         )
     }
 
@@ -116,14 +501,27 @@ pub mod aqueduct {
         )
     }
 
+    #[access_control(
+        service_access(&ctx.accounts.auth)
+    )]
+    pub fn reward_swap<'info>(
+        ctx: Context<'_, '_, '_, 'info, RewardSwap<'info>>,
+    ) -> Result<()> {
+        reward_swap::handler(
+            ctx,
+        )
+    }
+
     #[access_control(
         service_access(&ctx.accounts.auth)
     )]
     pub fn position_close<'info>(
         ctx: Context<'_, '_, '_, 'info, PositionClose<'info>>,
+        bundle_index: Option<u16>,
     ) -> Result<()> {
         position_close::handler(
             ctx,
+            bundle_index,
         )
     }
 
@@ -137,6 +535,30 @@ pub mod aqueduct {
         )
     }
 
+    pub fn funds_deposit_sol(
+        ctx: Context<FundsDepositSol>,
+        lamports: u64,
+    ) -> Result<()> {
+        funds_deposit_sol::handler(
+            ctx,
+            lamports
+        )
+    }
+
+    pub fn funds_deposit_swap<'info>(
+        ctx: Context<'_, '_, '_, 'info, FundsDepositSwap<'info>>,
+        amount: u64,
+        minimum_amount_out: u64,
+        wrap_sol: bool,
+    ) -> Result<()> {
+        funds_deposit_swap::handler(
+            ctx,
+            amount,
+            minimum_amount_out,
+            wrap_sol
+        )
+    }
+
     pub fn funds_withdraw(
         ctx: Context<FundsWithdraw>,
         amount: u64,
@@ -147,6 +569,171 @@ pub mod aqueduct {
         )
     }
 
+    pub fn funds_withdraw_swap<'info>(
+        ctx: Context<'_, '_, '_, 'info, FundsWithdrawSwap<'info>>,
+        amount: u64,
+        minimum_amount_out: u64,
+    ) -> Result<()> {
+        funds_withdraw_swap::handler(
+            ctx,
+            amount,
+            minimum_amount_out
+        )
+    }
+
+    pub fn withdraw_in_kind<'info>(
+        ctx: Context<'_, '_, '_, 'info, WithdrawInKind<'info>>,
+        fraction: u64,
+    ) -> Result<()> {
+        withdraw_in_kind::handler(
+            ctx,
+            fraction
+        )
+    }
+
+    pub fn emergency_exit<'info>(
+        ctx: Context<'_, '_, '_, 'info, EmergencyExit<'info>>,
+        bundle_index: Option<u16>,
+    ) -> Result<()> {
+        emergency_exit::handler(
+            ctx,
+            bundle_index,
+        )
+    }
+
+    #[access_control(
+        admin_access(&ctx.accounts.auth)
+    )]
+    pub fn vault_create(
+        ctx: Context<VaultCreate>,
+    ) -> Result<()> {
+        vault_create::handler(
+            ctx,
+        )
+    }
+
+    pub fn vault_deposit<'info>(
+        ctx: Context<'_, '_, '_, 'info, VaultDeposit<'info>>,
+        amount: u64,
+        minimum_shares_out: u64,
+    ) -> Result<()> {
+        vault_deposit::handler(
+            ctx,
+            amount,
+            minimum_shares_out
+        )
+    }
+
+    pub fn vault_withdraw<'info>(
+        ctx: Context<'_, '_, '_, 'info, VaultWithdraw<'info>>,
+        shares: u64,
+        minimum_a_amount: u64,
+        minimum_b_amount: u64,
+    ) -> Result<()> {
+        vault_withdraw::handler(
+            ctx,
+            shares,
+            minimum_a_amount,
+            minimum_b_amount
+        )
+    }
+
+    #[access_control(
+        service_access(&ctx.accounts.auth)
+    )]
+    pub fn vault_position_open<'info>(
+        ctx: Context<'_, '_, '_, 'info, VaultPositionOpen<'info>>,
+        position_bump: u8,
+        tick_lower_index: i32,
+        tick_upper_index: i32,
+    ) -> Result<()> {
+        vault_position_open::handler(
+            ctx,
+            position_bump,
+            tick_lower_index,
+            tick_upper_index,
+        )
+    }
+
+    #[access_control(
+        service_access(&ctx.accounts.auth)
+    )]
+    pub fn vault_position_fund<'info>(
+        ctx: Context<'_, '_, '_, 'info, VaultPositionFund<'info>>,
+        minimum_a_amount: u64,
+        minimum_b_amount: u64
+    ) -> Result<()> {
+        vault_position_fund::handler(
+            ctx,
+            minimum_a_amount,
+            minimum_b_amount
+        )
+    }
+
+    #[access_control(
+        service_access(&ctx.accounts.auth)
+    )]
+    pub fn vault_position_swap<'info>(
+        ctx: Context<'_, '_, '_, 'info, VaultPositionSwap<'info>>,
+        a_to_b: bool,
+        amount: u64,
+    ) -> Result<()> {
+        vault_position_swap::handler(
+            ctx,
+            a_to_b,
+            amount,
+        )
+    }
+
+    #[access_control(
+        service_access(&ctx.accounts.auth)
+    )]
+    pub fn vault_reward_swap<'info>(
+        ctx: Context<'_, '_, '_, 'info, VaultRewardSwap<'info>>,
+    ) -> Result<()> {
+        vault_reward_swap::handler(
+            ctx,
+        )
+    }
+
+    #[access_control(
+        service_access(&ctx.accounts.auth)
+    )]
+    pub fn vault_position_close<'info>(
+        ctx: Context<'_, '_, '_, 'info, VaultPositionClose<'info>>,
+    ) -> Result<()> {
+        vault_position_close::handler(
+            ctx,
+        )
+    }
+
+    #[access_control(
+        service_access(&ctx.accounts.auth)
+    )]
+    pub fn vault_refresh_price(
+        ctx: Context<VaultRefreshPrice>,
+    ) -> Result<()> {
+        vault_refresh_price::handler(
+            ctx,
+        )
+    }
+
+    pub fn pool_refresh_price(
+        ctx: Context<PoolRefreshPrice>,
+    ) -> Result<()> {
+        pool_refresh_price::handler(
+            ctx,
+        )
+    }
+
+    pub fn reward_route_refresh_price(
+        ctx: Context<RewardRouteRefreshPrice>,
+    ) -> Result<()> {
+        reward_route_refresh_price::handler(
+            ctx,
+        )
+    }
+
 }
 
 fn admin_access<'info>(auth: &Signer<'info>) -> Result<()> {
@@ -0,0 +1,201 @@
+use anchor_lang::prelude::*;
+use whirlpool::manager::liquidity_manager::calculate_liquidity_token_deltas;
+use whirlpool::math::{mul_u256, tick_index_from_sqrt_price, U256Muldiv};
+use whirlpool::state::{Position, Whirlpool};
+
+use crate::errors::AqueductError;
+use crate::state::{FEE_BPS_DENOMINATOR, REFERENCE_PRICE_MAX_DEVIATION_BPS, REFERENCE_PRICE_MAX_STEP_SLOTS, REFERENCE_PRICE_STEP_BPS, RESOLUTION};
+
+// price = (sqrt_price_x64 / 2^64)^2, applied one sqrt_price at a time so the product stays inside 256 bits
+pub fn value_a_in_b(amount_a: u64, sqrt_price_x64: u128) -> Result<u64> {
+    let step = mul_u256(amount_a as u128, sqrt_price_x64)
+        .shift_word_right()
+        .try_into_u128()?;
+    let value = mul_u256(step, sqrt_price_x64)
+        .shift_word_right()
+        .try_into_u128()?;
+
+    u64::try_from(value).map_err(|_| error!(AqueductError::MathOverflow))
+}
+
+pub fn value_b_in_a(amount_b: u64, sqrt_price_x64: u128) -> Result<u64> {
+    let (step, _) = U256Muldiv::new(0, amount_b as u128)
+        .shift_word_left()
+        .div(U256Muldiv::new(0, sqrt_price_x64), false);
+    let (value, _) = U256Muldiv::new(0, step.try_into_u128()?)
+        .shift_word_left()
+        .div(U256Muldiv::new(0, sqrt_price_x64), false);
+
+    u64::try_from(value.try_into_u128()?).map_err(|_| error!(AqueductError::MathOverflow))
+}
+
+// values a pair of token amounts in whichever side of the pool is the quote asset
+pub fn value_in_quote(amount_a: u64, amount_b: u64, sqrt_price_x64: u128, quote_is_a: bool) -> Result<u64> {
+    let value = if quote_is_a {
+        (amount_a as u128) + (value_b_in_a(amount_b, sqrt_price_x64)? as u128)
+    } else {
+        (value_a_in_b(amount_a, sqrt_price_x64)? as u128) + (amount_b as u128)
+    };
+
+    u64::try_from(value).map_err(|_| error!(AqueductError::MathOverflow))
+}
+
+// values a pair of token amounts in `quote_mint`, which has to be one side of the whirlpool
+pub fn value_in_mint(amount_a: u64, amount_b: u64, sqrt_price_x64: u128, whirlpool: &Whirlpool, quote_mint: &Pubkey) -> Result<u64> {
+    if *quote_mint == whirlpool.token_mint_a {
+        value_in_quote(amount_a, amount_b, sqrt_price_x64, true)
+    } else if *quote_mint == whirlpool.token_mint_b {
+        value_in_quote(amount_a, amount_b, sqrt_price_x64, false)
+    } else {
+        Err(error!(AqueductError::QuoteMintNotInPool))
+    }
+}
+
+// values amounts in the user's quote mint, a pool without it has no value the user's books can use
+pub fn value_in_user_quote(amount_a: u64, amount_b: u64, sqrt_price_x64: u128, whirlpool: &Whirlpool, quote_mint: &Pubkey) -> Result<u64> {
+    value_in_mint(amount_a, amount_b, sqrt_price_x64, whirlpool, quote_mint)
+}
+
+// value of `amount` of `mint` in `quote_mint` at `sqrt_price_x64`, None when the pool cannot price it
+pub fn mint_value_in_quote(amount: u64, mint: &Pubkey, sqrt_price_x64: u128, whirlpool: &Whirlpool, quote_mint: &Pubkey) -> Option<u64> {
+    if mint == quote_mint {
+        return Some(amount);
+    }
+
+    let (amount_a, amount_b) = if *mint == whirlpool.token_mint_a {
+        (amount, 0)
+    } else if *mint == whirlpool.token_mint_b {
+        (0, amount)
+    } else {
+        return None;
+    };
+
+    value_in_mint(amount_a, amount_b, sqrt_price_x64, whirlpool, quote_mint).ok()
+}
+
+// profit or loss of `value` against `cost_basis`
+pub fn realized_pnl(value: u64, cost_basis: u64) -> Result<i64> {
+    let value = i64::try_from(value).map_err(|_| error!(AqueductError::MathOverflow))?;
+    let cost_basis = i64::try_from(cost_basis).map_err(|_| error!(AqueductError::MathOverflow))?;
+
+    value.checked_sub(cost_basis).ok_or_else(|| error!(AqueductError::MathOverflow))
+}
+
+// expected output at the current price less max_slippage (out of RESOLUTION), pool fees eat into the slippage
+pub fn minimum_swap_output(amount_in: u64, sqrt_price_x64: u128, a_to_b: bool, max_slippage: u64) -> Result<u64> {
+    let expected = if a_to_b {
+        value_a_in_b(amount_in, sqrt_price_x64)?
+    } else {
+        value_b_in_a(amount_in, sqrt_price_x64)?
+    };
+
+    mul_div(expected, RESOLUTION.saturating_sub(max_slippage), RESOLUTION)
+}
+
+// token amounts the position would return if all of its liquidity were removed at sqrt_price_x64
+pub fn position_token_amounts(position: &Position, sqrt_price_x64: u128) -> Result<(u64, u64)> {
+    if position.liquidity == 0 {
+        return Ok((0, 0));
+    }
+
+    let liquidity_delta = i128::try_from(position.liquidity)
+        .map_err(|_| error!(AqueductError::MathOverflow))?;
+
+    let (amount_a, amount_b) = calculate_liquidity_token_deltas(
+        tick_index_from_sqrt_price(&sqrt_price_x64),
+        sqrt_price_x64,
+        position,
+        -liquidity_delta
+    )?;
+
+    Ok((amount_a, amount_b))
+}
+
+// moves `reference` towards `sqrt_price` by at most REFERENCE_PRICE_STEP_BPS per slot since `reference_slot`,
+// so a price pushed around within a few slots barely moves it; an unset reference takes the price as is
+pub fn step_reference_sqrt_price(reference: u128, reference_slot: u64, sqrt_price: u128, slot: u64) -> Result<u128> {
+    if reference == 0 {
+        return Ok(sqrt_price);
+    }
+
+    let slots = slot.saturating_sub(reference_slot).min(REFERENCE_PRICE_MAX_STEP_SLOTS);
+    let max_step = mul_div_u128(reference, slots * REFERENCE_PRICE_STEP_BPS, FEE_BPS_DENOMINATOR)?;
+
+    Ok(if sqrt_price > reference {
+        sqrt_price.min(reference.saturating_add(max_step))
+    } else {
+        sqrt_price.max(reference.saturating_sub(max_step))
+    })
+}
+
+// whether `sqrt_price` is within REFERENCE_PRICE_MAX_DEVIATION_BPS of a set `reference`
+pub fn near_reference_sqrt_price(reference: u128, sqrt_price: u128) -> Result<bool> {
+    if reference == 0 {
+        return Ok(false);
+    }
+
+    let max_deviation = mul_div_u128(reference, REFERENCE_PRICE_MAX_DEVIATION_BPS, FEE_BPS_DENOMINATOR)?;
+    let deviation = if sqrt_price > reference {
+        sqrt_price - reference
+    } else {
+        reference - sqrt_price
+    };
+
+    Ok(deviation <= max_deviation)
+}
+
+pub fn mul_div(amount: u64, numerator: u64, denominator: u64) -> Result<u64> {
+    (amount as u128)
+        .checked_mul(numerator as u128)
+        .and_then(|v| v.checked_div(denominator as u128))
+        .and_then(|v| u64::try_from(v).ok())
+        .ok_or_else(|| error!(AqueductError::MathOverflow))
+}
+
+pub fn mul_div_u128(amount: u128, numerator: u64, denominator: u64) -> Result<u128> {
+    if denominator == 0 {
+        return Err(error!(AqueductError::MathOverflow));
+    }
+
+    let (quotient, _) = mul_u256(amount, numerator as u128)
+        .div(U256Muldiv::new(0, denominator as u128), false);
+
+    Ok(quotient.try_into_u128()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn realized_pnl_signs() {
+        assert_eq!(realized_pnl(150, 100).unwrap(), 50);
+        assert_eq!(realized_pnl(100, 150).unwrap(), -50);
+        assert_eq!(realized_pnl(0, 0).unwrap(), 0);
+    }
+
+    #[test]
+    fn realized_pnl_out_of_range() {
+        assert_eq!(realized_pnl(u64::MAX, 0).unwrap_err(), AqueductError::MathOverflow.into());
+        assert_eq!(realized_pnl(0, u64::MAX).unwrap_err(), AqueductError::MathOverflow.into());
+        assert_eq!(realized_pnl(i64::MAX as u64, i64::MAX as u64).unwrap(), 0);
+    }
+
+    #[test]
+    fn step_reference_sqrt_price_is_bounded_per_slot() {
+        assert_eq!(step_reference_sqrt_price(0, 0, 5_000, 7).unwrap(), 5_000);
+        assert_eq!(step_reference_sqrt_price(1_000_000, 100, 2_000_000, 101).unwrap(), 1_001_000);
+        assert_eq!(step_reference_sqrt_price(1_000_000, 100, 0, 102).unwrap(), 998_000);
+        assert_eq!(step_reference_sqrt_price(1_000_000, 100, 1_000_500, 101).unwrap(), 1_000_500);
+        // a long gap still only counts REFERENCE_PRICE_MAX_STEP_SLOTS slots
+        assert_eq!(step_reference_sqrt_price(1_000_000, 100, 2_000_000, 10_000).unwrap(), 1_025_000);
+    }
+
+    #[test]
+    fn near_reference_sqrt_price_deviation() {
+        assert!(near_reference_sqrt_price(1_000_000, 1_005_000).unwrap());
+        assert!(near_reference_sqrt_price(1_000_000, 995_000).unwrap());
+        assert!(!near_reference_sqrt_price(1_000_000, 1_005_001).unwrap());
+        assert!(!near_reference_sqrt_price(0, 0).unwrap());
+    }
+}
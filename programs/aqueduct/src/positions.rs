@@ -0,0 +1,154 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::accessor;
+use whirlpool::cpi::accounts::{CollectFees, CollectReward, ModifyLiquidity, UpdateFeesAndRewards};
+
+use crate::errors::AqueductError;
+
+// The accounts of a whirlpool position held by a user or vault PDA. Every path that harvests or
+// unwinds a position goes through these, so the whirlpool CPIs are only wired up here.
+pub struct PositionAccounts<'info> {
+    pub whirlpool_program: AccountInfo<'info>,
+    pub whirlpool: AccountInfo<'info>,
+    pub position_authority: AccountInfo<'info>,
+    pub position: AccountInfo<'info>,
+    pub position_token_account: AccountInfo<'info>,
+    pub token_owner_account_a: AccountInfo<'info>,
+    pub token_owner_account_b: AccountInfo<'info>,
+    pub token_vault_a: AccountInfo<'info>,
+    pub token_vault_b: AccountInfo<'info>,
+    pub tick_array_lower: AccountInfo<'info>,
+    pub tick_array_upper: AccountInfo<'info>,
+    pub token_program: AccountInfo<'info>,
+}
+
+impl<'info> PositionAccounts<'info> {
+
+    pub fn update_fees_and_rewards(&self, liquidity: u128) -> Result<()> {
+        update_fees_and_rewards(
+            &self.whirlpool_program,
+            &self.whirlpool,
+            &self.position,
+            &self.tick_array_lower,
+            &self.tick_array_upper,
+            liquidity
+        )
+    }
+
+    // collects the fees owed into the owner accounts, returns the amounts collected
+    pub fn collect_fees(&self, signer_seeds: &[&[&[u8]]]) -> Result<(u64, u64)> {
+        let (start_a, start_b) = self.owner_balances()?;
+
+        let cpi_accounts = CollectFees {
+            whirlpool: self.whirlpool.clone(),
+            position_authority: self.position_authority.clone(),
+            position: self.position.clone(),
+            position_token_account: self.position_token_account.clone(),
+            token_owner_account_a: self.token_owner_account_a.clone(),
+            token_vault_a: self.token_vault_a.clone(),
+            token_owner_account_b: self.token_owner_account_b.clone(),
+            token_vault_b: self.token_vault_b.clone(),
+            token_program: self.token_program.clone(),
+        };
+        whirlpool::cpi::collect_fees(
+            CpiContext::new(self.whirlpool_program.clone(), cpi_accounts).with_signer(signer_seeds)
+        )?;
+
+        self.owner_balance_changes(start_a, start_b)
+    }
+
+    // removes liquidity into the owner accounts, returns the amounts received
+    pub fn decrease_liquidity(&self, signer_seeds: &[&[&[u8]]], liquidity: u128) -> Result<(u64, u64)> {
+        if liquidity == 0 {
+            return Ok((0, 0));
+        }
+
+        let (start_a, start_b) = self.owner_balances()?;
+
+        let cpi_accounts = ModifyLiquidity {
+            whirlpool: self.whirlpool.clone(),
+            token_program: self.token_program.clone(),
+            position_authority: self.position_authority.clone(),
+            position: self.position.clone(),
+            position_token_account: self.position_token_account.clone(),
+            token_owner_account_a: self.token_owner_account_a.clone(),
+            token_owner_account_b: self.token_owner_account_b.clone(),
+            token_vault_a: self.token_vault_a.clone(),
+            token_vault_b: self.token_vault_b.clone(),
+            tick_array_lower: self.tick_array_lower.clone(),
+            tick_array_upper: self.tick_array_upper.clone(),
+        };
+        whirlpool::cpi::decrease_liquidity(
+            CpiContext::new(self.whirlpool_program.clone(), cpi_accounts).with_signer(signer_seeds),
+            liquidity,
+            0u64,
+            0u64
+        )?;
+
+        self.owner_balance_changes(start_a, start_b)
+    }
+
+    // collects one reward slot into reward_owner_account, returns the amount collected
+    pub fn collect_reward(&self,
+        signer_seeds: &[&[&[u8]]],
+        reward_index: usize,
+        reward_owner_account: &AccountInfo<'info>,
+        reward_vault: &AccountInfo<'info>,
+    ) -> Result<u64> {
+        let start = accessor::amount(reward_owner_account)?;
+
+        let cpi_accounts = CollectReward {
+            whirlpool: self.whirlpool.clone(),
+            position_authority: self.position_authority.clone(),
+            position: self.position.clone(),
+            position_token_account: self.position_token_account.clone(),
+            reward_owner_account: reward_owner_account.clone(),
+            reward_vault: reward_vault.clone(),
+            token_program: self.token_program.clone(),
+        };
+        whirlpool::cpi::collect_reward(
+            CpiContext::new(self.whirlpool_program.clone(), cpi_accounts).with_signer(signer_seeds),
+            reward_index as u8
+        )?;
+
+        accessor::amount(reward_owner_account)?
+            .checked_sub(start)
+            .ok_or_else(|| error!(AqueductError::MathOverflow))
+    }
+
+    fn owner_balances(&self) -> Result<(u64, u64)> {
+        Ok((accessor::amount(&self.token_owner_account_a)?, accessor::amount(&self.token_owner_account_b)?))
+    }
+
+    fn owner_balance_changes(&self, start_a: u64, start_b: u64) -> Result<(u64, u64)> {
+        let (end_a, end_b) = self.owner_balances()?;
+
+        Ok((
+            end_a.checked_sub(start_a).ok_or(AqueductError::MathOverflow)?,
+            end_b.checked_sub(start_b).ok_or(AqueductError::MathOverflow)?,
+        ))
+    }
+
+}
+
+// brings the fees and rewards owed up to date, the whirlpool rejects this without liquidity;
+// needs no position authority, so paths that only value a position can call it too
+pub fn update_fees_and_rewards<'info>(
+    whirlpool_program: &AccountInfo<'info>,
+    whirlpool: &AccountInfo<'info>,
+    position: &AccountInfo<'info>,
+    tick_array_lower: &AccountInfo<'info>,
+    tick_array_upper: &AccountInfo<'info>,
+    liquidity: u128,
+) -> Result<()> {
+    if liquidity == 0 {
+        return Ok(());
+    }
+
+    let cpi_accounts = UpdateFeesAndRewards {
+        whirlpool: whirlpool.clone(),
+        position: position.clone(),
+        tick_array_lower: tick_array_lower.clone(),
+        tick_array_upper: tick_array_upper.clone(),
+    };
+    whirlpool::cpi::update_fees_and_rewards(CpiContext::new(whirlpool_program.clone(), cpi_accounts))
+}
@@ -0,0 +1,65 @@
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::get_associated_token_address;
+use anchor_spl::token::TokenAccount;
+use whirlpool::state::Whirlpool;
+
+use crate::errors::AqueductError;
+use crate::state::B_SERVICE;
+
+// (reward_mint, reward_owner_account, reward_vault, service_ata, referral_ata) per whirlpool reward slot
+pub const REWARD_ACCOUNTS_LEN: usize = 5;
+// (reward_mint, reward_owner_account, reward_vault, user_reward_ata) per whirlpool reward slot on the emergency exit
+pub const EMERGENCY_REWARD_ACCOUNTS_LEN: usize = 4;
+
+pub struct RewardSlotAccounts<'a, 'info> {
+    pub mint: &'a AccountInfo<'info>,
+    // the owner's ATA for the reward mint, may not have been created yet
+    pub owner_account: &'a AccountInfo<'info>,
+    pub vault: &'a AccountInfo<'info>,
+    pub treasury: Account<'info, TokenAccount>,
+    pub referral: &'a AccountInfo<'info>,
+}
+
+// the accounts for reward slot `index`, checked against the whirlpool's reward info for that slot
+pub fn reward_slot_accounts<'a, 'info>(
+    whirlpool: &Whirlpool,
+    index: usize,
+    owner: &Pubkey,
+    remaining_accounts: &'a [AccountInfo<'info>],
+) -> Result<RewardSlotAccounts<'a, 'info>> {
+    let group = reward_slot_group(whirlpool, index, owner, remaining_accounts, REWARD_ACCOUNTS_LEN)?;
+    let reward_info = &whirlpool.reward_infos[index];
+
+    let treasury: Account<TokenAccount> = Account::try_from(&group[3])?;
+    require!(treasury.owner == B_SERVICE, AqueductError::InvalidTreasuryAccount);
+    require!(treasury.mint == reward_info.mint, AqueductError::InvalidTreasuryAccount);
+
+    Ok(RewardSlotAccounts {
+        mint: &group[0],
+        owner_account: &group[1],
+        vault: &group[2],
+        treasury,
+        referral: &group[4],
+    })
+}
+
+// the `group_len` accounts for reward slot `index`, the first three (reward_mint, reward_owner_account,
+// reward_vault) checked against the whirlpool's reward info and the rest left to the caller
+pub fn reward_slot_group<'a, 'info>(
+    whirlpool: &Whirlpool,
+    index: usize,
+    owner: &Pubkey,
+    remaining_accounts: &'a [AccountInfo<'info>],
+    group_len: usize,
+) -> Result<&'a [AccountInfo<'info>]> {
+    let reward_info = whirlpool.reward_infos.get(index).ok_or(AqueductError::InvalidRewardAccounts)?;
+    let group = remaining_accounts
+        .get(index * group_len..(index + 1) * group_len)
+        .ok_or(AqueductError::InvalidRewardAccounts)?;
+
+    require!(group[0].key() == reward_info.mint, AqueductError::InvalidRewardAccounts);
+    require!(group[1].key() == get_associated_token_address(owner, &reward_info.mint), AqueductError::InvalidRewardAccounts);
+    require!(group[2].key() == reward_info.vault, AqueductError::InvalidRewardAccounts);
+
+    Ok(group)
+}
@@ -0,0 +1,15 @@
+use anchor_lang::prelude::*;
+use spl_token::solana_program::pubkey::Pubkey;
+
+#[account]
+pub struct Allowlist {
+    pub user: Pubkey,
+}
+
+impl Default for Allowlist {
+    fn default() -> Self {
+        Allowlist {
+            user: Pubkey::default(),
+        }
+    }
+}
@@ -0,0 +1,27 @@
+use anchor_lang::prelude::*;
+
+use crate::state::PROGRAM_ID;
+
+#[account]
+pub struct Config {
+    // user_create requires an allowlist entry or an admin-signed attestation
+    pub attestation_required: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            attestation_required: false,
+        }
+    }
+}
+
+// the config PDA, or the defaults while the admin has not run set_config, so onboarding never depends on it
+pub fn load_config(info: &AccountInfo) -> Result<Config> {
+    if *info.owner != PROGRAM_ID || info.data_is_empty() {
+        return Ok(Config::default());
+    }
+
+    let data = info.try_borrow_data()?;
+    Config::try_deserialize(&mut &data[..])
+}
@@ -0,0 +1,55 @@
+use anchor_lang::prelude::*;
+use spl_token::solana_program::pubkey::Pubkey;
+
+use super::{DEFAULT_PERFORMANCE_FEE_BPS, MAX_FEE_OVERRIDES, PERFORMANCE_FEE_MODE_GROSS, PERFORMANCE_FEE_MODE_HIGH_WATER_MARK};
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct MintFeeOverride {
+    pub mint: Pubkey,
+    pub performance_fee_bps: u16,
+    pub withdrawal_fee_bps: u16,
+}
+
+// all rates in basis points, the management fee is annual and accrues on NAV
+#[account]
+pub struct FeeSchedule {
+    pub management_fee_bps: u16,
+    pub performance_fee_bps: u16,
+    pub withdrawal_fee_bps: u16,
+    // gross takes the performance fee off every collection, high-water mark only off net profit above the mark
+    pub performance_fee_mode: u8,
+    // share of every performance fee that goes to the user's referrer, if any
+    pub referral_share_bps: u16,
+    pub overrides: [MintFeeOverride; MAX_FEE_OVERRIDES],
+}
+
+impl Default for FeeSchedule {
+    fn default() -> Self {
+        FeeSchedule {
+            management_fee_bps: 0,
+            performance_fee_bps: DEFAULT_PERFORMANCE_FEE_BPS,
+            withdrawal_fee_bps: 0,
+            performance_fee_mode: PERFORMANCE_FEE_MODE_GROSS,
+            referral_share_bps: 0,
+            overrides: [MintFeeOverride::default(); MAX_FEE_OVERRIDES],
+        }
+    }
+}
+
+impl FeeSchedule {
+    fn mint_override(&self, mint: &Pubkey) -> Option<&MintFeeOverride> {
+        self.overrides.iter().find(|o| o.mint != Pubkey::default() && o.mint == *mint)
+    }
+
+    pub fn performance_fee_bps_for(&self, mint: &Pubkey) -> u16 {
+        self.mint_override(mint).map_or(self.performance_fee_bps, |o| o.performance_fee_bps)
+    }
+
+    pub fn high_water_mark_mode(&self) -> bool {
+        self.performance_fee_mode == PERFORMANCE_FEE_MODE_HIGH_WATER_MARK
+    }
+
+    pub fn withdrawal_fee_bps_for(&self, mint: &Pubkey) -> u16 {
+        self.mint_override(mint).map_or(self.withdrawal_fee_bps, |o| o.withdrawal_fee_bps)
+    }
+}
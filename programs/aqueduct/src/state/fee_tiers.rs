@@ -0,0 +1,77 @@
+use anchor_lang::prelude::*;
+use spl_token::solana_program::pubkey::Pubkey;
+
+use super::MAX_FEE_TIERS;
+
+// a tier is reached by either threshold, a zero threshold is not checked and a tier with both at zero is unused
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct FeeTier {
+    // quote value the user has deposited and not withdrawn
+    pub min_deposit: u64,
+    // amount of the stake mint locked in the user's stake PDA
+    pub min_stake: u64,
+    // taken off every fee rate the user pays, in basis points of the rate
+    pub discount_bps: u16,
+}
+
+#[account]
+pub struct FeeTiers {
+    pub stake_mint: Pubkey,
+    pub tiers: [FeeTier; MAX_FEE_TIERS],
+}
+
+impl Default for FeeTiers {
+    fn default() -> Self {
+        FeeTiers {
+            stake_mint: Pubkey::default(),
+            tiers: [FeeTier::default(); MAX_FEE_TIERS],
+        }
+    }
+}
+
+impl FeeTiers {
+    // best discount among the tiers the user reaches
+    pub fn discount_bps_for(&self, deposit: u64, stake: u64) -> u16 {
+        self.tiers.iter()
+            .filter(|t| (t.min_deposit > 0 && deposit >= t.min_deposit) || (t.min_stake > 0 && stake >= t.min_stake))
+            .map(|t| t.discount_bps)
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fee_tiers() -> FeeTiers {
+        let mut fee_tiers = FeeTiers::default();
+        fee_tiers.tiers[0] = FeeTier { min_deposit: 1_000, min_stake: 0, discount_bps: 1_000 };
+        fee_tiers.tiers[1] = FeeTier { min_deposit: 10_000, min_stake: 500, discount_bps: 2_500 };
+        fee_tiers
+    }
+
+    #[test]
+    fn no_tiers_no_discount() {
+        assert_eq!(FeeTiers::default().discount_bps_for(u64::MAX, u64::MAX), 0);
+    }
+
+    #[test]
+    fn best_tier_reached_by_either_threshold() {
+        let fee_tiers = fee_tiers();
+
+        assert_eq!(fee_tiers.discount_bps_for(999, 0), 0);
+        assert_eq!(fee_tiers.discount_bps_for(1_000, 0), 1_000);
+        assert_eq!(fee_tiers.discount_bps_for(10_000, 0), 2_500);
+        assert_eq!(fee_tiers.discount_bps_for(0, 500), 2_500);
+        assert_eq!(fee_tiers.discount_bps_for(1_000, 499), 1_000);
+    }
+
+    #[test]
+    fn zero_thresholds_are_not_reached() {
+        let mut fee_tiers = FeeTiers::default();
+        fee_tiers.tiers[0] = FeeTier { min_deposit: 0, min_stake: 0, discount_bps: 5_000 };
+
+        assert_eq!(fee_tiers.discount_bps_for(u64::MAX, u64::MAX), 0);
+    }
+}
@@ -0,0 +1,56 @@
+use anchor_lang::prelude::*;
+use spl_token::solana_program::pubkey::Pubkey;
+
+use crate::ed25519::verify_signed_message;
+use crate::errors::AqueductError;
+
+use super::{User, INTENT_MESSAGE_PREFIX};
+
+pub const INTENT_ACTION_OPEN: u8 = 0;
+pub const INTENT_ACTION_SWAP: u8 = 1;
+
+// Signed by the owner wallet as INTENT_MESSAGE_PREFIX || borsh(intent) and passed by the service
+// alongside the matching ed25519 instruction. Fields that do not apply to the action are zero.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct ServiceIntent {
+    pub user_pda: Pubkey,
+    pub whirlpool: Pubkey,
+    pub nonce: u64,
+    pub action: u8,
+    pub tick_lower_index: i32,
+    pub tick_upper_index: i32,
+    pub a_to_b: bool,
+    pub amount: u64,
+    // absolute swap bounds handed to the whirlpool as is, a zero price limit leaves the price unbounded
+    pub min_amount_out: u64,
+    pub sqrt_price_limit: u128,
+    pub valid_until_slot: u64,
+}
+
+impl ServiceIntent {
+    pub fn message(&self) -> Result<Vec<u8>> {
+        Ok([INTENT_MESSAGE_PREFIX, &self.try_to_vec()?].concat())
+    }
+
+    // checks the intent against the user and the owner's signature, then burns its nonce
+    pub fn consume(
+        &self,
+        user: &mut User,
+        user_pda: &Pubkey,
+        whirlpool: &Pubkey,
+        action: u8,
+        instructions_sysvar: &AccountInfo,
+    ) -> Result<()> {
+        require!(self.user_pda == *user_pda, AqueductError::IntentMismatch);
+        require!(self.whirlpool == *whirlpool, AqueductError::IntentMismatch);
+        require!(self.action == action, AqueductError::IntentMismatch);
+        require!(self.nonce == user.intent_nonce, AqueductError::IntentNonceMismatch);
+        require!(Clock::get()?.slot <= self.valid_until_slot, AqueductError::IntentExpired);
+
+        verify_signed_message(instructions_sysvar, &user.user, &self.message()?)?;
+
+        user.intent_nonce = user.intent_nonce.checked_add(1).ok_or(AqueductError::MathOverflow)?;
+
+        Ok(())
+    }
+}
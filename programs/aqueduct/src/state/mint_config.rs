@@ -0,0 +1,101 @@
+use anchor_lang::prelude::*;
+use spl_token::solana_program::pubkey::Pubkey;
+
+use crate::errors::AqueductError;
+use crate::state::PROGRAM_ID;
+
+// caps for a single mint, zero disables a cap
+#[account]
+pub struct MintConfig {
+    pub mint: Pubkey,
+    // max net deposit a user may reach with deposits of the mint, valued in their quote asset like User::net_deposit
+    pub user_deposit_cap: u64,
+    // max net amount deposited across all users
+    pub protocol_deposit_cap: u64,
+    pub total_deposited: u64,
+    // max share of a whirlpool's reserves of the mint a single position_fund may supply
+    pub max_allocation_percent: u8,
+}
+
+impl Default for MintConfig {
+    fn default() -> Self {
+        MintConfig {
+            mint: Pubkey::default(),
+            user_deposit_cap: 0,
+            protocol_deposit_cap: 0,
+            total_deposited: 0,
+            max_allocation_percent: 0,
+        }
+    }
+}
+
+impl MintConfig {
+    // `user_net_deposit` is the user's net deposit after this one, None when the deposit could not be valued
+    pub fn record_deposit(&mut self, amount: u64, user_net_deposit: Option<u64>) -> Result<()> {
+        if self.user_deposit_cap > 0 {
+            // a deposit that cannot be valued cannot be measured against the cap either
+            let user_net_deposit = user_net_deposit.ok_or(AqueductError::UserDepositCapExceeded)?;
+            require!(user_net_deposit <= self.user_deposit_cap, AqueductError::UserDepositCapExceeded);
+        }
+
+        let total_deposited = self.total_deposited.checked_add(amount).ok_or(AqueductError::MathOverflow)?;
+        if self.protocol_deposit_cap > 0 {
+            require!(total_deposited <= self.protocol_deposit_cap, AqueductError::ProtocolDepositCapExceeded);
+        }
+        self.total_deposited = total_deposited;
+
+        Ok(())
+    }
+
+    // withdrawals can include yield that was never deposited, so this floors at zero
+    pub fn record_withdraw(&mut self, amount: u64) {
+        self.total_deposited = self.total_deposited.saturating_sub(amount);
+    }
+
+    pub fn check_allocation(&self, amount: u64, pool_reserve: u64) -> Result<()> {
+        if self.max_allocation_percent > 0 && amount > 0 {
+            let reserve_after = (pool_reserve as u128) + (amount as u128);
+            require!(
+                (amount as u128) * 100 <= reserve_after * (self.max_allocation_percent as u128),
+                AqueductError::MaxAllocationExceeded
+            );
+        }
+
+        Ok(())
+    }
+}
+
+// a mint only has caps once its config PDA has been created, `info` is that seed-checked PDA
+pub fn load_mint_config<'info>(info: &AccountInfo<'info>) -> Result<Option<Account<'info, MintConfig>>> {
+    if *info.owner != PROGRAM_ID || info.data_is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some(Account::try_from(info)?))
+}
+
+pub fn record_mint_deposit(info: &AccountInfo, amount: u64, user_net_deposit: Option<u64>) -> Result<()> {
+    if let Some(mut mint_config) = load_mint_config(info)? {
+        mint_config.record_deposit(amount, user_net_deposit)?;
+        mint_config.exit(&PROGRAM_ID)?;
+    }
+
+    Ok(())
+}
+
+// caps only limit inflows, this just keeps the protocol total in step and never fails a withdrawal
+pub fn record_mint_withdraw(info: &AccountInfo, amount: u64) -> Result<()> {
+    if let Ok(Some(mut mint_config)) = load_mint_config(info) {
+        mint_config.record_withdraw(amount);
+        mint_config.exit(&PROGRAM_ID)?;
+    }
+
+    Ok(())
+}
+
+pub fn check_mint_allocation(info: &AccountInfo, amount: u64, pool_reserve: u64) -> Result<()> {
+    match load_mint_config(info)? {
+        Some(mint_config) => mint_config.check_allocation(amount, pool_reserve),
+        None => Ok(()),
+    }
+}
@@ -1,23 +1,74 @@
 use solana_program::pubkey;
 use spl_token::solana_program::pubkey::Pubkey;
 
+pub use allowlist::*;
+pub use config::*;
+pub use fee_schedule::*;
+pub use fee_tiers::*;
+pub use intent::*;
+pub use mint_config::*;
 pub use pool_config::*;
+pub use quote_config::*;
+pub use range_spec::*;
+pub use rate_limits::*;
+pub use reward_route::*;
+pub use stake::*;
 pub use user::*;
+pub use vault::*;
 
+mod allowlist;
+mod config;
+mod fee_schedule;
+mod fee_tiers;
+mod intent;
+mod mint_config;
 mod pool_config;
+mod quote_config;
+mod range_spec;
+mod rate_limits;
+mod reward_route;
+mod stake;
 mod user;
+mod vault;
 
+pub const CONFIG_PDA_PREFIX: &[u8] = b"glob";
+pub const ALLOWLIST_PDA_PREFIX: &[u8] = b"allo";
 pub const POOL_CONFIG_PDA_PREFIX: &[u8] = b"conf";
+pub const QUOTE_CONFIG_PDA_PREFIX: &[u8] = b"quot";
+pub const MINT_CONFIG_PDA_PREFIX: &[u8] = b"mint";
+pub const FEE_SCHEDULE_PDA_PREFIX: &[u8] = b"fees";
+pub const FEE_TIERS_PDA_PREFIX: &[u8] = b"tier";
+pub const STAKE_PDA_PREFIX: &[u8] = b"stak";
+pub const RATE_LIMITS_PDA_PREFIX: &[u8] = b"rate";
+pub const REFERRAL_PDA_PREFIX: &[u8] = b"refr";
+pub const REWARD_ROUTE_PDA_PREFIX: &[u8] = b"rwrd";
 pub const POSITION_OWNER_PDA_PREFIX: &[u8] = b"posi";
 pub const USER_PDA_PREFIX: &[u8] = b"user";
+pub const VAULT_PDA_PREFIX: &[u8] = b"vaul";
+pub const VAULT_SHARE_MINT_PDA_PREFIX: &[u8] = b"shar";
+
+// narrowest position the service may open, in whirlpool tick spacings
+pub const MIN_POSITION_TICK_SPACINGS: i32 = 2;
+pub const RANGE_SKEW_MAX_BPS: i16 = 10_000;
+// whirlpool-owned authority that signs position NFT metadata
+pub const WHIRLPOOL_METADATA_UPDATE_AUTH: Pubkey = pubkey!("3axbTs2z5GBy6usVbNVoqEgZMng3vZvMnAoX29BFfwhr");
 
 pub const MAX_SQRT_PRICE: u128 = 79226673515401279992447579055;
 pub const MIN_SQRT_PRICE: u128 = 4295048016;
 
-pub const AQUEDUCT_FEE_NUMERATOR: u64 = 20;
-pub const AQUEDUCT_FEE_DENOMINATOR: u64 = 100;
+pub const FEE_BPS_DENOMINATOR: u64 = 10_000;
+pub const DEFAULT_PERFORMANCE_FEE_BPS: u16 = 1_500;
+pub const MAX_FEE_OVERRIDES: usize = 8;
+pub const PERFORMANCE_FEE_MODE_GROSS: u8 = 0;
+pub const PERFORMANCE_FEE_MODE_HIGH_WATER_MARK: u8 = 1;
+pub const MAX_FEE_TIERS: usize = 4;
 
-pub const AQUEDUCT_FEE_PERCENT: u64 = 15;
+// what happens to collected rewards with a whitelisted route
+pub const REWARD_CONVERSION_NONE: u8 = 0;
+pub const REWARD_CONVERSION_QUOTE: u8 = 1;
+pub const REWARD_CONVERSION_PAIR: u8 = 2;
+// out of RESOLUTION, used until the user sets their own
+pub const DEFAULT_REWARD_MAX_SLIPPAGE: u64 = 100;
 
 pub const PROGRAM_ID: Pubkey = pubkey!("EU3CcRRS2G4RR5bj7AogBNvErnf1G95gumA9b9fQ2Sco");
 
@@ -27,4 +78,37 @@ pub const C_SERVICE: Pubkey = pubkey!("AQuCcjumeuJMCWjmGf28DLkC6UXA1VuaGemBqiVso
 
 pub const USDC: Pubkey = pubkey!("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v");
 
-pub const RESOLUTION: u64 = 10000;
\ No newline at end of file
+// quote mint for users that have not picked one, selected by the cluster feature at build time
+#[cfg(feature = "localnet")]
+pub const DEFAULT_QUOTE_MINT: Pubkey = pubkey!("CpgtrNyowF2pDfLTwbiXqCpRYELvPA82CoS1GY2vNPUz");
+#[cfg(all(feature = "devnet", not(feature = "localnet")))]
+pub const DEFAULT_QUOTE_MINT: Pubkey = pubkey!("4zMMC9srt5Ri5X14GAgXhaHii3GnPAEERYPJgZJDncDU");
+#[cfg(not(any(feature = "localnet", feature = "devnet")))]
+pub const DEFAULT_QUOTE_MINT: Pubkey = USDC;
+
+pub const RESOLUTION: u64 = 10000;
+
+pub const SECONDS_PER_DAY: i64 = 86_400;
+pub const SECONDS_PER_YEAR: i64 = 365 * SECONDS_PER_DAY;
+pub const DEFAULT_LOSS_GUARD_WINDOW_SECONDS: i64 = 7 * SECONDS_PER_DAY;
+// stake can not be pulled for this long after it was last topped up
+pub const STAKE_LOCK_SECONDS: i64 = 7 * SECONDS_PER_DAY;
+
+// admin-signed onboarding attestations are ONBOARDING_ATTESTATION_PREFIX || program id || user || expiry slot (le)
+pub const ONBOARDING_ATTESTATION_PREFIX: &[u8] = b"aqueduct:onboard:";
+pub const INTENT_MESSAGE_PREFIX: &[u8] = b"aqueduct:intent:";
+
+// slots without a service action before the user can exit on their own (~3 days at 400ms slots)
+pub const DEFAULT_EMERGENCY_EXIT_SLOTS: u64 = 648_000;
+pub const MIN_EMERGENCY_EXIT_SLOTS: u64 = 216_000;
+
+// shares minted to the vault itself on the first deposit and never redeemable
+pub const VAULT_DEAD_SHARES: u64 = 1_000;
+pub const VAULT_MIN_INITIAL_DEPOSIT: u64 = 1_000_000;
+// vault and pool reference sqrt prices move at most STEP_BPS per slot and MAX_STEP_SLOTS slots per refresh,
+// vault deposits need the pool sqrt price within MAX_DEVIATION_BPS of it
+pub const REFERENCE_PRICE_STEP_BPS: u64 = 10;
+pub const REFERENCE_PRICE_MAX_STEP_SLOTS: u64 = 25;
+pub const REFERENCE_PRICE_MAX_DEVIATION_BPS: u64 = 50;
+// fixed point scale of the vault NAV per share high-water mark
+pub const VAULT_NAV_PER_SHARE_SCALE: u64 = 1_000_000_000_000;
\ No newline at end of file
@@ -1,12 +1,22 @@
 use anchor_lang::prelude::*;
 use spl_token::solana_program::pubkey::Pubkey;
 
+use crate::errors::AqueductError;
+use crate::math::step_reference_sqrt_price;
+
 #[account]
 pub struct PoolConfig {
     pub task: Pubkey,
     pub user: Pubkey,
     pub approved: bool,
     pub rejected: bool,
+    // zero disables the cap
+    pub liquidity_cap: u128,
+    // liquidity currently provided through aqueduct positions
+    pub total_liquidity: u128,
+    // sqrt price user positions in the pool are valued at, trails the pool price like Vault::reference_sqrt_price
+    pub reference_sqrt_price: u128,
+    pub reference_slot: u64,
 }
 
 impl Default for PoolConfig {
@@ -16,6 +26,41 @@ impl Default for PoolConfig {
             user: Pubkey::default(),
             approved: false,
             rejected: false,
+            liquidity_cap: 0,
+            total_liquidity: 0,
+            reference_sqrt_price: 0,
+            reference_slot: 0,
         }
     }
 }
+
+impl PoolConfig {
+    pub fn add_liquidity(&mut self, liquidity: u128) -> Result<()> {
+        let total_liquidity = self.total_liquidity.checked_add(liquidity).ok_or(AqueductError::MathOverflow)?;
+        if self.liquidity_cap > 0 {
+            require!(total_liquidity <= self.liquidity_cap, AqueductError::PoolLiquidityCapExceeded);
+        }
+        self.total_liquidity = total_liquidity;
+
+        Ok(())
+    }
+
+    pub fn remove_liquidity(&mut self, liquidity: u128) {
+        self.total_liquidity = self.total_liquidity.saturating_sub(liquidity);
+    }
+
+    // moves the reference towards the pool price by a bounded step, see step_reference_sqrt_price
+    pub fn refresh_reference_price(&mut self, sqrt_price: u128, slot: u64) -> Result<()> {
+        self.reference_sqrt_price = step_reference_sqrt_price(self.reference_sqrt_price, self.reference_slot, sqrt_price, slot)?;
+        self.reference_slot = slot;
+
+        Ok(())
+    }
+
+    // the price losses and cost basis are measured at, never the spot price a single transaction can move
+    pub fn valuation_sqrt_price(&self) -> Result<u128> {
+        require!(self.reference_sqrt_price > 0, AqueductError::PoolPriceNotSet);
+
+        Ok(self.reference_sqrt_price)
+    }
+}
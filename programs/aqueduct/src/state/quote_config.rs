@@ -0,0 +1,17 @@
+use anchor_lang::prelude::*;
+use spl_token::solana_program::pubkey::Pubkey;
+
+#[account]
+pub struct QuoteConfig {
+    pub mint: Pubkey,
+    pub approved: bool,
+}
+
+impl Default for QuoteConfig {
+    fn default() -> Self {
+        QuoteConfig {
+            mint: Pubkey::default(),
+            approved: false,
+        }
+    }
+}
@@ -0,0 +1,24 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::AqueductError;
+
+use super::RANGE_SKEW_MAX_BPS;
+
+// Position range relative to the current price instead of raw ticks. One whirlpool tick moves the price
+// by one basis point, so width_bps is the range width in ticks before it is aligned to the tick spacing.
+// skew_bps shifts the range around the current price: zero centers it, RANGE_SKEW_MAX_BPS puts all of it
+// above the price and -RANGE_SKEW_MAX_BPS all of it below.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RangeSpec {
+    pub width_bps: u32,
+    pub skew_bps: i16,
+}
+
+impl RangeSpec {
+    pub fn validate(&self) -> Result<()> {
+        require!(self.width_bps > 0, AqueductError::InvalidRangeSpec);
+        require!(self.skew_bps.unsigned_abs() <= RANGE_SKEW_MAX_BPS as u16, AqueductError::InvalidRangeSpec);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,181 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::AqueductError;
+
+use super::SECONDS_PER_DAY;
+
+// limits on service actions per user or vault, zero disables a limit
+#[account]
+pub struct RateLimits {
+    // min seconds between two rebalances
+    pub rebalance_cooldown_seconds: i64,
+    pub max_rebalances_per_day: u16,
+    pub max_swaps_per_day: u16,
+    // valued in the quote mint when it is one side of the pool, in raw input units otherwise
+    pub max_swap_notional_per_day: u64,
+}
+
+impl Default for RateLimits {
+    fn default() -> Self {
+        RateLimits {
+            rebalance_cooldown_seconds: 0,
+            max_rebalances_per_day: 0,
+            max_swaps_per_day: 0,
+            max_swap_notional_per_day: 0,
+        }
+    }
+}
+
+// service action counters kept on each user and vault, reset when the unix day changes
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct RateLimitCounters {
+    pub day: i64,
+    pub rebalances_today: u16,
+    pub swaps_today: u16,
+    pub swapped_notional_today: u64,
+    pub last_rebalance_timestamp: i64,
+}
+
+impl RateLimitCounters {
+    fn roll_day(&mut self, now: i64) {
+        let day = now / SECONDS_PER_DAY;
+        if day != self.day {
+            self.day = day;
+            self.rebalances_today = 0;
+            self.swaps_today = 0;
+            self.swapped_notional_today = 0;
+        }
+    }
+
+    // whether a rebalance would be allowed now, without counting one
+    pub fn check_rebalance(&mut self, limits: &RateLimits, now: i64) -> Result<()> {
+        self.roll_day(now);
+
+        if limits.rebalance_cooldown_seconds > 0 && self.last_rebalance_timestamp > 0 {
+            require!(
+                now >= self.last_rebalance_timestamp.saturating_add(limits.rebalance_cooldown_seconds),
+                AqueductError::RebalanceCooldown
+            );
+        }
+
+        if limits.max_rebalances_per_day > 0 {
+            require!(self.rebalances_today < limits.max_rebalances_per_day, AqueductError::RebalanceLimitReached);
+        }
+
+        Ok(())
+    }
+
+    pub fn record_rebalance(&mut self, limits: &RateLimits, now: i64) -> Result<()> {
+        self.check_rebalance(limits, now)?;
+
+        self.rebalances_today = self.rebalances_today.saturating_add(1);
+        self.last_rebalance_timestamp = now;
+
+        Ok(())
+    }
+
+    pub fn record_swap(&mut self, limits: &RateLimits, now: i64, notional: u64) -> Result<()> {
+        self.roll_day(now);
+
+        let swaps_today = self.swaps_today.saturating_add(1);
+        if limits.max_swaps_per_day > 0 {
+            require!(swaps_today <= limits.max_swaps_per_day, AqueductError::SwapLimitReached);
+        }
+
+        let swapped_notional_today = self.swapped_notional_today.saturating_add(notional);
+        if limits.max_swap_notional_per_day > 0 {
+            require!(swapped_notional_today <= limits.max_swap_notional_per_day, AqueductError::SwapNotionalLimitReached);
+        }
+
+        self.swaps_today = swaps_today;
+        self.swapped_notional_today = swapped_notional_today;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: i64 = 100 * SECONDS_PER_DAY + 1_000;
+
+    fn limits() -> RateLimits {
+        RateLimits {
+            rebalance_cooldown_seconds: 60,
+            max_rebalances_per_day: 2,
+            max_swaps_per_day: 2,
+            max_swap_notional_per_day: 1_000,
+        }
+    }
+
+    #[test]
+    fn zero_limits_allow_everything() {
+        let mut counters = RateLimitCounters::default();
+
+        for i in 0..10 {
+            counters.record_rebalance(&RateLimits::default(), NOW + i).unwrap();
+            counters.record_swap(&RateLimits::default(), NOW + i, u64::MAX).unwrap();
+        }
+    }
+
+    #[test]
+    fn rebalance_cooldown() {
+        let mut counters = RateLimitCounters::default();
+
+        counters.record_rebalance(&limits(), NOW).unwrap();
+        assert_eq!(counters.record_rebalance(&limits(), NOW + 59).unwrap_err(), AqueductError::RebalanceCooldown.into());
+        counters.record_rebalance(&limits(), NOW + 60).unwrap();
+        assert_eq!(counters.rebalances_today, 2);
+        assert_eq!(counters.last_rebalance_timestamp, NOW + 60);
+    }
+
+    #[test]
+    fn rebalances_per_day_reset_with_the_day() {
+        let mut counters = RateLimitCounters::default();
+
+        counters.record_rebalance(&limits(), NOW).unwrap();
+        counters.record_rebalance(&limits(), NOW + 60).unwrap();
+        assert_eq!(counters.record_rebalance(&limits(), NOW + 120).unwrap_err(), AqueductError::RebalanceLimitReached.into());
+
+        counters.record_rebalance(&limits(), NOW + SECONDS_PER_DAY).unwrap();
+        assert_eq!(counters.rebalances_today, 1);
+    }
+
+    #[test]
+    fn check_rebalance_does_not_count() {
+        let mut counters = RateLimitCounters::default();
+
+        counters.check_rebalance(&limits(), NOW).unwrap();
+        counters.check_rebalance(&limits(), NOW).unwrap();
+        assert_eq!(counters.rebalances_today, 0);
+        assert_eq!(counters.last_rebalance_timestamp, 0);
+    }
+
+    #[test]
+    fn swaps_per_day() {
+        let mut counters = RateLimitCounters::default();
+
+        counters.record_swap(&limits(), NOW, 10).unwrap();
+        counters.record_swap(&limits(), NOW, 10).unwrap();
+        assert_eq!(counters.record_swap(&limits(), NOW, 10).unwrap_err(), AqueductError::SwapLimitReached.into());
+
+        counters.record_swap(&limits(), NOW + SECONDS_PER_DAY, 10).unwrap();
+        assert_eq!(counters.swaps_today, 1);
+    }
+
+    #[test]
+    fn swap_notional_per_day() {
+        let mut counters = RateLimitCounters::default();
+
+        counters.record_swap(&limits(), NOW, 600).unwrap();
+        assert_eq!(counters.record_swap(&limits(), NOW, 401).unwrap_err(), AqueductError::SwapNotionalLimitReached.into());
+
+        // a refused swap is not counted
+        assert_eq!(counters.swaps_today, 1);
+        assert_eq!(counters.swapped_notional_today, 600);
+
+        counters.record_swap(&limits(), NOW, 400).unwrap();
+        assert_eq!(counters.swapped_notional_today, 1_000);
+    }
+}
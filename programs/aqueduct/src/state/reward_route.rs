@@ -0,0 +1,45 @@
+use anchor_lang::prelude::*;
+use spl_token::solana_program::pubkey::Pubkey;
+
+use crate::errors::AqueductError;
+use crate::math::step_reference_sqrt_price;
+
+// whitelisted reward mint and the whirlpool its rewards are sold through
+#[account]
+pub struct RewardRoute {
+    pub reward_mint: Pubkey,
+    pub whirlpool: Pubkey,
+    pub approved: bool,
+    // sqrt price of the route whirlpool sales are bounded by, trails the pool price like Vault::reference_sqrt_price
+    pub reference_sqrt_price: u128,
+    pub reference_slot: u64,
+}
+
+impl Default for RewardRoute {
+    fn default() -> Self {
+        RewardRoute {
+            reward_mint: Pubkey::default(),
+            whirlpool: Pubkey::default(),
+            approved: false,
+            reference_sqrt_price: 0,
+            reference_slot: 0,
+        }
+    }
+}
+
+impl RewardRoute {
+    // moves the reference towards the pool price by a bounded step, see step_reference_sqrt_price
+    pub fn refresh_reference_price(&mut self, sqrt_price: u128, slot: u64) -> Result<()> {
+        self.reference_sqrt_price = step_reference_sqrt_price(self.reference_sqrt_price, self.reference_slot, sqrt_price, slot)?;
+        self.reference_slot = slot;
+
+        Ok(())
+    }
+
+    // the price a sale's minimum output is worked out at, never the spot price the sale itself can move
+    pub fn valuation_sqrt_price(&self) -> Result<u128> {
+        require!(self.reference_sqrt_price > 0, AqueductError::PoolPriceNotSet);
+
+        Ok(self.reference_sqrt_price)
+    }
+}
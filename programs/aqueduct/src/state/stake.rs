@@ -0,0 +1,23 @@
+use anchor_lang::prelude::*;
+use spl_token::solana_program::pubkey::Pubkey;
+
+// stake mint tokens a user locked for a fee tier, held in the stake PDA's token account
+#[account]
+pub struct Stake {
+    pub user_pda: Pubkey,
+    pub amount: u64,
+    pub locked_until: i64,
+    // the fee tiers' stake mint when the tokens were locked, a stake in any other mint earns no discount
+    pub mint: Pubkey,
+}
+
+impl Default for Stake {
+    fn default() -> Self {
+        Stake {
+            user_pda: Pubkey::default(),
+            amount: 0,
+            locked_until: 0,
+            mint: Pubkey::default(),
+        }
+    }
+}
@@ -1,11 +1,62 @@
 use anchor_lang::prelude::*;
 use spl_token::solana_program::pubkey::Pubkey;
 
+use crate::errors::AqueductError;
+use crate::fees::{fee_amount, management_fee_amount};
+
+use super::{RangeSpec, RateLimitCounters, RateLimits, DEFAULT_EMERGENCY_EXIT_SLOTS, DEFAULT_LOSS_GUARD_WINDOW_SECONDS, DEFAULT_QUOTE_MINT, DEFAULT_REWARD_MAX_SLIPPAGE, REWARD_CONVERSION_NONE};
+
+pub const DELEGATE_PERMISSION_PAUSE: u8 = 1 << 0;
+pub const DELEGATE_PERMISSION_CHANGE_POOL: u8 = 1 << 1;
+pub const DELEGATE_PERMISSION_WITHDRAW: u8 = 1 << 2;
+
 #[account]
 pub struct User {
     pub user: Pubkey,
     pub paused: bool,
     pub whirlpool: Pubkey,
+    pub last_service_action_slot: u64,
+    pub emergency_exit_slots: u64,
+    pub open_positions: u16,
+    // PDA seed, fixed at creation so that `user` can change hands
+    pub vault_id: Pubkey,
+    pub pending_owner: Pubkey,
+    pub delegate: Pubkey,
+    pub delegate_permissions: u8,
+    pub quote_mint: Pubkey,
+    // set by the admin on revocation, only exits are allowed afterwards
+    pub exit_only: bool,
+    // position_open and position_swap need an owner-signed ServiceIntent
+    pub intents_required: bool,
+    pub intent_nonce: u64,
+    pub rate_limit_counters: RateLimitCounters,
+    // realized loss in the quote mint, at the pool reference price, above which the user is paused; zero disables
+    pub loss_guard_threshold: u64,
+    pub loss_guard_window_seconds: i64,
+    pub loss_window_start: i64,
+    pub loss_in_window: u64,
+    // quote value and liquidity put into the user's one open position, released as liquidity comes out
+    pub position_cost_basis: u64,
+    pub position_liquidity: u128,
+    pub management_fee_accrued_at: i64,
+    // cumulative realized profit in the quote asset and the level performance fees were last charged up to
+    pub realized_pnl: i64,
+    pub pnl_high_water_mark: i64,
+    pub referrer: Pubkey,
+    pub reward_conversion: u8,
+    // out of RESOLUTION, zero falls back to DEFAULT_REWARD_MAX_SLIPPAGE
+    pub reward_max_slippage: u64,
+    // whirlpool position bundle owned by the user_pda, default until one is initialized
+    pub position_bundle_mint: Pubkey,
+    // range the service has to open positions with, zero width lets it pick raw ticks
+    pub range_width_bps: u32,
+    pub range_skew_bps: i16,
+    // open_positions is only trusted once set, accounts from before the counter are recounted by the service
+    pub open_positions_counted: bool,
+    // management fee accrued on the cost basis and not charged yet, in the quote asset
+    pub management_fee_owed: u64,
+    // quote value deposited and not withdrawn, what the deposit fee tiers are measured against
+    pub net_deposit: u64,
 }
 
 impl Default for User {
@@ -13,7 +64,357 @@ impl Default for User {
         User {
             user: Pubkey::default(),
             paused: false,
-            whirlpool: Pubkey::default()
+            whirlpool: Pubkey::default(),
+            last_service_action_slot: 0,
+            emergency_exit_slots: 0,
+            open_positions: 0,
+            vault_id: Pubkey::default(),
+            pending_owner: Pubkey::default(),
+            delegate: Pubkey::default(),
+            delegate_permissions: 0,
+            quote_mint: Pubkey::default(),
+            exit_only: false,
+            intents_required: false,
+            intent_nonce: 0,
+            rate_limit_counters: RateLimitCounters::default(),
+            loss_guard_threshold: 0,
+            loss_guard_window_seconds: 0,
+            loss_window_start: 0,
+            loss_in_window: 0,
+            position_cost_basis: 0,
+            position_liquidity: 0,
+            management_fee_accrued_at: 0,
+            realized_pnl: 0,
+            pnl_high_water_mark: 0,
+            referrer: Pubkey::default(),
+            reward_conversion: REWARD_CONVERSION_NONE,
+            reward_max_slippage: 0,
+            position_bundle_mint: Pubkey::default(),
+            range_width_bps: 0,
+            range_skew_bps: 0,
+            open_positions_counted: false,
+            management_fee_owed: 0,
+            net_deposit: 0,
+        }
+    }
+}
+
+impl User {
+    pub fn emergency_exit_available_slot(&self) -> u64 {
+        let inactivity_slots = if self.emergency_exit_slots == 0 {
+            DEFAULT_EMERGENCY_EXIT_SLOTS
+        } else {
+            self.emergency_exit_slots
+        };
+
+        self.last_service_action_slot.saturating_add(inactivity_slots)
+    }
+
+    pub fn is_delegate_with(&self, key: &Pubkey, permission: u8) -> bool {
+        self.delegate != Pubkey::default()
+            && self.delegate == *key
+            && self.delegate_permissions & permission == permission
+    }
+
+    pub fn record_rebalance(&mut self, limits: &RateLimits, now: i64) -> Result<()> {
+        self.rate_limit_counters.record_rebalance(limits, now)
+    }
+
+    pub fn record_swap(&mut self, limits: &RateLimits, now: i64, notional: u64) -> Result<()> {
+        self.rate_limit_counters.record_swap(limits, now, notional)
+    }
+
+    // a deposit that cannot be valued in the quote asset adds nothing, returns the net deposit it was counted in
+    pub fn record_deposit(&mut self, value: Option<u64>) -> Option<u64> {
+        let value = value?;
+        self.net_deposit = self.net_deposit.saturating_add(value);
+
+        Some(self.net_deposit)
+    }
+
+    // a withdrawal that cannot be valued in the quote asset clears the net deposit rather than leave it overstated
+    pub fn record_withdrawal(&mut self, value: Option<u64>) {
+        self.net_deposit = match value {
+            Some(value) => self.net_deposit.saturating_sub(value),
+            None => 0,
+        };
+    }
+
+    // capital added part way through an accrual period pulls management_fee_accrued_at forward,
+    // so the fee pending on the earlier cost basis is unchanged and none accrues on the new one yet
+    pub fn add_cost_basis(&mut self, value: u64, liquidity: u128, now: i64) {
+        if self.management_fee_accrued_at == 0 || self.position_cost_basis == 0 {
+            self.management_fee_accrued_at = now;
+        } else if value > 0 {
+            let elapsed = now.saturating_sub(self.management_fee_accrued_at).max(0) as u128;
+            let weighted = elapsed * (self.position_cost_basis as u128) / (self.position_cost_basis as u128 + value as u128);
+            self.management_fee_accrued_at = now - weighted as i64;
+        }
+
+        self.position_cost_basis = self.position_cost_basis.saturating_add(value);
+        self.position_liquidity = self.position_liquidity.saturating_add(liquidity);
+    }
+
+    // brings the management fee owed up to date, it accrues on the cost basis working in positions
+    pub fn accrue_management_fee(&mut self, fee_bps: u16, now: i64) -> Result<()> {
+        if self.management_fee_accrued_at > 0 {
+            let accrued = management_fee_amount(self.position_cost_basis, fee_bps, now - self.management_fee_accrued_at)?;
+            self.management_fee_owed = self.management_fee_owed.saturating_add(accrued);
+        }
+        self.management_fee_accrued_at = now;
+
+        Ok(())
+    }
+
+    // share of the management fee owed attributed to `liquidity` leaving the positions, taken before
+    // release_cost_basis so it is measured against the same liquidity
+    pub fn release_management_fee(&mut self, liquidity: u128) -> u64 {
+        if self.position_liquidity == 0 {
+            return 0;
+        }
+
+        let liquidity = liquidity.min(self.position_liquidity);
+        let released = (self.management_fee_owed as u128)
+            .saturating_mul(liquidity)
+            .checked_div(self.position_liquidity)
+            .unwrap_or(0) as u64;
+
+        self.management_fee_owed -= released;
+
+        released
+    }
+
+    // share of the cost basis attributed to `liquidity` leaving the positions
+    pub fn release_cost_basis(&mut self, liquidity: u128) -> u64 {
+        if self.position_liquidity == 0 {
+            return 0;
+        }
+
+        let liquidity = liquidity.min(self.position_liquidity);
+        let released = (self.position_cost_basis as u128)
+            .saturating_mul(liquidity)
+            .checked_div(self.position_liquidity)
+            .unwrap_or(0) as u64;
+
+        self.position_cost_basis = self.position_cost_basis.saturating_sub(released);
+        self.position_liquidity -= liquidity;
+
+        released
+    }
+
+    // returns true when this loss trips the guard, the user is then paused until they unpause themselves
+    pub fn record_loss(&mut self, loss: u64, now: i64) -> bool {
+        let window = if self.loss_guard_window_seconds == 0 {
+            DEFAULT_LOSS_GUARD_WINDOW_SECONDS
+        } else {
+            self.loss_guard_window_seconds
+        };
+
+        if now >= self.loss_window_start.saturating_add(window) {
+            self.loss_window_start = now;
+            self.loss_in_window = 0;
+        }
+
+        self.loss_in_window = self.loss_in_window.saturating_add(loss);
+
+        if self.loss_guard_threshold > 0 && self.loss_in_window > self.loss_guard_threshold && !self.paused {
+            self.paused = true;
+            return true;
+        }
+
+        false
+    }
+
+    // books realized profit or loss and returns the fee owed on the part above the high-water mark,
+    // the fee comes off the books as well so the new mark is what the user kept
+    pub fn record_realized_pnl(&mut self, pnl: i64, fee_bps: u16) -> Result<u64> {
+        self.realized_pnl = self.realized_pnl.saturating_add(pnl);
+
+        if self.realized_pnl <= self.pnl_high_water_mark {
+            return Ok(0);
+        }
+
+        let gain = self.realized_pnl
+            .checked_sub(self.pnl_high_water_mark)
+            .and_then(|gain| u64::try_from(gain).ok())
+            .ok_or(AqueductError::MathOverflow)?;
+        let fee = fee_amount(gain, fee_bps)?;
+
+        // the fee is at most the gain, which came out of an i64
+        self.realized_pnl -= fee as i64;
+        self.pnl_high_water_mark = self.realized_pnl;
+
+        Ok(fee)
+    }
+
+    pub fn range_spec(&self) -> Option<RangeSpec> {
+        if self.range_width_bps == 0 {
+            None
+        } else {
+            Some(RangeSpec { width_bps: self.range_width_bps, skew_bps: self.range_skew_bps })
+        }
+    }
+
+    pub fn effective_reward_max_slippage(&self) -> u64 {
+        if self.reward_max_slippage == 0 {
+            DEFAULT_REWARD_MAX_SLIPPAGE
+        } else {
+            self.reward_max_slippage
+        }
+    }
+
+    pub fn effective_quote_mint(&self) -> Pubkey {
+        if self.quote_mint == Pubkey::default() {
+            DEFAULT_QUOTE_MINT
+        } else {
+            self.quote_mint
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::SECONDS_PER_YEAR;
+
+    const NOW: i64 = 1_700_000_000;
+
+    #[test]
+    fn release_cost_basis_pro_rata() {
+        let mut user = User::default();
+        user.add_cost_basis(1_000, 100, NOW);
+
+        assert_eq!(user.release_cost_basis(25), 250);
+        assert_eq!(user.position_cost_basis, 750);
+        assert_eq!(user.position_liquidity, 75);
+
+        // more liquidity than is left releases the rest
+        assert_eq!(user.release_cost_basis(1_000), 750);
+        assert_eq!(user.position_cost_basis, 0);
+        assert_eq!(user.position_liquidity, 0);
+
+        assert_eq!(user.release_cost_basis(10), 0);
+    }
+
+    #[test]
+    fn record_loss_trips_the_guard() {
+        let mut user = User { loss_guard_threshold: 100, ..User::default() };
+
+        assert!(!user.record_loss(60, NOW));
+        assert!(!user.paused);
+        assert!(user.record_loss(50, NOW + 1));
+        assert!(user.paused);
+        // the owner can unpause, the admin-only flag is left alone
+        assert!(!user.exit_only);
+
+        // already paused, nothing left to trip
+        assert!(!user.record_loss(500, NOW + 2));
+    }
+
+    #[test]
+    fn record_loss_window_resets() {
+        let mut user = User { loss_guard_threshold: 100, ..User::default() };
+
+        assert!(!user.record_loss(60, NOW));
+        assert!(!user.record_loss(60, NOW + DEFAULT_LOSS_GUARD_WINDOW_SECONDS));
+        assert_eq!(user.loss_in_window, 60);
+        assert!(!user.paused);
+
+        user.loss_guard_window_seconds = 10;
+        assert!(!user.record_loss(30, NOW + DEFAULT_LOSS_GUARD_WINDOW_SECONDS + 9));
+        assert!(user.record_loss(20, NOW + DEFAULT_LOSS_GUARD_WINDOW_SECONDS + 9));
+    }
+
+    #[test]
+    fn record_loss_without_threshold() {
+        let mut user = User::default();
+
+        assert!(!user.record_loss(u64::MAX, NOW));
+        assert!(!user.paused);
+    }
+
+    #[test]
+    fn add_cost_basis_time_weights_the_accrual_clock() {
+        let mut user = User::default();
+        user.add_cost_basis(1_000, 10, NOW);
+        assert_eq!(user.management_fee_accrued_at, NOW);
+
+        // doubling the cost basis halves the time already accrued
+        user.add_cost_basis(1_000, 10, NOW + 100);
+        assert_eq!(user.management_fee_accrued_at, NOW + 50);
+        assert_eq!(user.position_cost_basis, 2_000);
+        assert_eq!(user.position_liquidity, 20);
+    }
+
+    #[test]
+    fn management_fee_accrues_and_releases_with_liquidity() {
+        let mut user = User::default();
+        user.add_cost_basis(1_000_000, 100, NOW);
+
+        user.accrue_management_fee(200, NOW + SECONDS_PER_YEAR).unwrap();
+        assert_eq!(user.management_fee_owed, 20_000);
+        assert_eq!(user.management_fee_accrued_at, NOW + SECONDS_PER_YEAR);
+
+        assert_eq!(user.release_management_fee(25), 5_000);
+        assert_eq!(user.management_fee_owed, 15_000);
+
+        user.release_cost_basis(25);
+        assert_eq!(user.release_management_fee(1_000), 15_000);
+        assert_eq!(user.management_fee_owed, 0);
+    }
+
+    #[test]
+    fn management_fee_does_not_accrue_before_the_first_position() {
+        let mut user = User::default();
+
+        user.accrue_management_fee(200, NOW).unwrap();
+        assert_eq!(user.management_fee_owed, 0);
+        assert_eq!(user.management_fee_accrued_at, NOW);
+        assert_eq!(user.release_management_fee(10), 0);
+    }
+
+    #[test]
+    fn record_realized_pnl_charges_above_the_high_water_mark() {
+        let mut user = User::default();
+
+        assert_eq!(user.record_realized_pnl(1_000, 1_000).unwrap(), 100);
+        assert_eq!(user.realized_pnl, 900);
+        assert_eq!(user.pnl_high_water_mark, 900);
+
+        // a loss and the recovery up to the mark are free
+        assert_eq!(user.record_realized_pnl(-500, 1_000).unwrap(), 0);
+        assert_eq!(user.record_realized_pnl(500, 1_000).unwrap(), 0);
+        assert_eq!(user.pnl_high_water_mark, 900);
+
+        assert_eq!(user.record_realized_pnl(200, 1_000).unwrap(), 20);
+        assert_eq!(user.realized_pnl, 1_080);
+        assert_eq!(user.pnl_high_water_mark, 1_080);
+    }
+
+    #[test]
+    fn record_realized_pnl_without_fee() {
+        let mut user = User::default();
+
+        assert_eq!(user.record_realized_pnl(1_000, 0).unwrap(), 0);
+        assert_eq!(user.realized_pnl, 1_000);
+        assert_eq!(user.pnl_high_water_mark, 1_000);
+    }
+
+    #[test]
+    fn net_deposit_follows_deposits_and_withdrawals() {
+        let mut user = User::default();
+
+        user.record_deposit(Some(1_000));
+        user.record_deposit(None);
+        assert_eq!(user.net_deposit, 1_000);
+
+        user.record_withdrawal(Some(400));
+        assert_eq!(user.net_deposit, 600);
+        user.record_withdrawal(Some(1_000));
+        assert_eq!(user.net_deposit, 0);
+
+        user.record_deposit(Some(1_000));
+        user.record_withdrawal(None);
+        assert_eq!(user.net_deposit, 0);
+    }
+}
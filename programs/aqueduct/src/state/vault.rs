@@ -0,0 +1,166 @@
+use anchor_lang::prelude::*;
+use spl_token::solana_program::pubkey::Pubkey;
+
+use whirlpool::state::Whirlpool;
+
+use crate::errors::AqueductError;
+use crate::fees::{discounted_bps, fee_amount, management_fee_amount};
+use crate::math::{mul_div_u128, near_reference_sqrt_price, step_reference_sqrt_price, value_in_mint};
+
+use super::{FeeSchedule, FeeTiers, RateLimitCounters, FEE_BPS_DENOMINATOR, MAX_SQRT_PRICE, MIN_SQRT_PRICE, REFERENCE_PRICE_MAX_DEVIATION_BPS, VAULT_NAV_PER_SHARE_SCALE};
+
+// A pooled vault shares a single service-managed position between every share holder.
+#[account]
+pub struct Vault {
+    pub whirlpool: Pubkey,
+    pub share_mint: Pubkey,
+    pub quote_mint: Pubkey,
+    pub position: Pubkey,
+    pub position_mint: Pubkey,
+    // sqrt price shares are valued at, trails the pool price by a bounded step per slot
+    pub reference_sqrt_price: u128,
+    pub reference_slot: u64,
+    pub rate_limit_counters: RateLimitCounters,
+    // management fee accrued on the vault's holdings and not paid out yet, in token units
+    pub management_fee_owed_a: u64,
+    pub management_fee_owed_b: u64,
+    pub management_fee_accrued_at: i64,
+    // NAV per share, scaled by VAULT_NAV_PER_SHARE_SCALE, performance fees were last charged up to
+    pub nav_per_share_high_water_mark: u128,
+}
+
+impl Default for Vault {
+    fn default() -> Self {
+        Vault {
+            whirlpool: Pubkey::default(),
+            share_mint: Pubkey::default(),
+            quote_mint: Pubkey::default(),
+            position: Pubkey::default(),
+            position_mint: Pubkey::default(),
+            reference_sqrt_price: 0,
+            reference_slot: 0,
+            rate_limit_counters: RateLimitCounters::default(),
+            management_fee_owed_a: 0,
+            management_fee_owed_b: 0,
+            management_fee_accrued_at: 0,
+            nav_per_share_high_water_mark: 0,
+        }
+    }
+}
+
+impl Vault {
+    pub fn has_position(&self) -> bool {
+        self.position != Pubkey::default()
+    }
+
+    // moves the reference towards the pool price by a bounded step, see step_reference_sqrt_price
+    pub fn refresh_reference_price(&mut self, sqrt_price: u128, slot: u64) -> Result<()> {
+        self.reference_sqrt_price = step_reference_sqrt_price(self.reference_sqrt_price, self.reference_slot, sqrt_price, slot)?;
+        self.reference_slot = slot;
+
+        Ok(())
+    }
+
+    // shares are only priced while the pool price is close to the reference
+    pub fn check_sqrt_price(&self, sqrt_price: u128) -> Result<()> {
+        require!(near_reference_sqrt_price(self.reference_sqrt_price, sqrt_price)?, AqueductError::VaultPriceDeviation);
+
+        Ok(())
+    }
+
+    // how far a swap may push the pool price, the edge of the band check_sqrt_price accepts
+    pub fn swap_sqrt_price_limit(&self, a_to_b: bool) -> Result<u128> {
+        require!(self.reference_sqrt_price > 0, AqueductError::VaultPriceDeviation);
+
+        let max_deviation = mul_div_u128(self.reference_sqrt_price, REFERENCE_PRICE_MAX_DEVIATION_BPS, FEE_BPS_DENOMINATOR)?;
+
+        Ok(if a_to_b {
+            self.reference_sqrt_price.saturating_sub(max_deviation).max(MIN_SQRT_PRICE)
+        } else {
+            self.reference_sqrt_price.saturating_add(max_deviation).min(MAX_SQRT_PRICE)
+        })
+    }
+
+    // accrues the management fee on the vault's holdings, `amount_a` and `amount_b` include what is owed already
+    pub fn accrue_management_fee(&mut self, amount_a: u64, amount_b: u64, fee_bps: u16, now: i64) -> Result<()> {
+        if self.management_fee_accrued_at > 0 {
+            let elapsed = now - self.management_fee_accrued_at;
+            let fee_a = management_fee_amount(amount_a.saturating_sub(self.management_fee_owed_a), fee_bps, elapsed)?;
+            let fee_b = management_fee_amount(amount_b.saturating_sub(self.management_fee_owed_b), fee_bps, elapsed)?;
+
+            self.management_fee_owed_a = self.management_fee_owed_a.saturating_add(fee_a);
+            self.management_fee_owed_b = self.management_fee_owed_b.saturating_add(fee_b);
+        }
+        self.management_fee_accrued_at = now;
+
+        Ok(())
+    }
+
+    // vaults reach fee tiers by the value they hold, they have no stake
+    pub fn fee_discount_bps(&self, fee_tiers: &FeeTiers, value: u64) -> u16 {
+        fee_tiers.discount_bps_for(value, 0)
+    }
+
+    // value of the holdings net of the management fee owed, in the quote mint at the reference price
+    pub fn holdings_value(&self, whirlpool: &Whirlpool, amount_a: u64, amount_b: u64) -> Result<u64> {
+        value_in_mint(
+            amount_a.saturating_sub(self.management_fee_owed_a),
+            amount_b.saturating_sub(self.management_fee_owed_b),
+            self.reference_sqrt_price,
+            whirlpool,
+            &self.quote_mint
+        )
+    }
+
+    // NAV shares are priced at: the holdings value less the performance fee the vault's gains will pay,
+    // `fees_owed_a` and `fees_owed_b` are the part of the holdings still owed by the position
+    #[allow(clippy::too_many_arguments)]
+    pub fn net_asset_value(&self,
+        whirlpool: &Whirlpool,
+        amount_a: u64,
+        amount_b: u64,
+        fees_owed_a: u64,
+        fees_owed_b: u64,
+        fee_schedule: &FeeSchedule,
+        fee_tiers: &FeeTiers,
+        supply: u64,
+    ) -> Result<u64> {
+        let value = self.holdings_value(whirlpool, amount_a, amount_b)?;
+        let discount_bps = self.fee_discount_bps(fee_tiers, value);
+
+        if fee_schedule.high_water_mark_mode() {
+            let fee_bps = discounted_bps(fee_schedule.performance_fee_bps_for(&self.quote_mint), discount_bps);
+            return Ok(value - self.high_water_mark_fee(value, supply, fee_bps)?);
+        }
+
+        // under the gross mode the performance fee comes off the position's fees as they are collected
+        let fee_a = fee_amount(fees_owed_a, discounted_bps(fee_schedule.performance_fee_bps_for(&whirlpool.token_mint_a), discount_bps))?;
+        let fee_b = fee_amount(fees_owed_b, discounted_bps(fee_schedule.performance_fee_bps_for(&whirlpool.token_mint_b), discount_bps))?;
+
+        Ok(value.saturating_sub(value_in_mint(fee_a, fee_b, self.reference_sqrt_price, whirlpool, &self.quote_mint)?))
+    }
+
+    // performance fee, in the quote mint, on the NAV per share gained above the high-water mark; nothing
+    // is charged until a mark has been set
+    pub fn high_water_mark_fee(&self, value: u64, supply: u64, fee_bps: u16) -> Result<u64> {
+        if supply == 0 || self.nav_per_share_high_water_mark == 0 {
+            return Ok(0);
+        }
+
+        let nav_per_share = mul_div_u128(value as u128, VAULT_NAV_PER_SHARE_SCALE, supply)?;
+        if nav_per_share <= self.nav_per_share_high_water_mark {
+            return Ok(0);
+        }
+
+        let gain = mul_div_u128(nav_per_share - self.nav_per_share_high_water_mark, supply, VAULT_NAV_PER_SHARE_SCALE)?;
+        fee_amount(u64::try_from(gain).map_err(|_| AqueductError::MathOverflow)?, fee_bps)
+    }
+
+    pub fn set_high_water_mark(&mut self, value: u64, supply: u64) -> Result<()> {
+        if supply > 0 {
+            self.nav_per_share_high_water_mark = mul_div_u128(value as u128, VAULT_NAV_PER_SHARE_SCALE, supply)?;
+        }
+
+        Ok(())
+    }
+}
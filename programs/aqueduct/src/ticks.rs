@@ -0,0 +1,157 @@
+use anchor_lang::prelude::*;
+use whirlpool::state::{Whirlpool, MAX_TICK_INDEX, MIN_TICK_INDEX, TICK_ARRAY_SIZE};
+
+use crate::errors::AqueductError;
+use crate::state::{RangeSpec, User, MIN_POSITION_TICK_SPACINGS, RANGE_SKEW_MAX_BPS};
+
+// ticks the whirlpool would accept for a position, and at least MIN_POSITION_TICK_SPACINGS apart
+pub fn validate_tick_range(whirlpool: &Whirlpool, tick_lower_index: i32, tick_upper_index: i32) -> Result<()> {
+    let tick_spacing = whirlpool.tick_spacing as i32;
+
+    require!(tick_lower_index >= MIN_TICK_INDEX, AqueductError::TickOutOfBounds);
+    require!(tick_upper_index <= MAX_TICK_INDEX, AqueductError::TickOutOfBounds);
+    require!(tick_lower_index % tick_spacing == 0, AqueductError::TickNotAligned);
+    require!(tick_upper_index % tick_spacing == 0, AqueductError::TickNotAligned);
+
+    let width = (tick_upper_index as i64) - (tick_lower_index as i64);
+    require!(width >= (MIN_POSITION_TICK_SPACINGS as i64) * (tick_spacing as i64), AqueductError::PositionRangeTooNarrow);
+
+    Ok(())
+}
+
+// aligned ticks for a range spec around the whirlpool's current tick, clamped to the usable tick bounds
+pub fn range_spec_ticks(whirlpool: &Whirlpool, range_spec: &RangeSpec) -> Result<(i32, i32)> {
+    range_spec.validate()?;
+
+    let tick_spacing = whirlpool.tick_spacing as i64;
+    let tick_current_index = whirlpool.tick_current_index as i64;
+    let width = range_spec.width_bps as i64;
+
+    // part of the width above the current tick, half of it when there is no skew
+    let above = width * (RANGE_SKEW_MAX_BPS as i64 + range_spec.skew_bps as i64) / (2 * RANGE_SKEW_MAX_BPS as i64);
+
+    let tick_upper_index = ceil_to_spacing(tick_current_index + above, tick_spacing)
+        .min(floor_to_spacing(MAX_TICK_INDEX as i64, tick_spacing));
+    let tick_lower_index = floor_to_spacing(tick_current_index + above - width, tick_spacing)
+        .max(ceil_to_spacing(MIN_TICK_INDEX as i64, tick_spacing));
+
+    Ok((tick_lower_index as i32, tick_upper_index as i32))
+}
+
+// the ticks to open with: derived from the range spec when one is given, and the user's range settings
+// are binding on the service when they have set any
+pub fn resolve_tick_range(
+    user: &User,
+    whirlpool: &Whirlpool,
+    tick_lower_index: i32,
+    tick_upper_index: i32,
+    range_spec: Option<RangeSpec>,
+) -> Result<(i32, i32)> {
+    if let Some(user_range_spec) = user.range_spec() {
+        require!(range_spec == Some(user_range_spec), AqueductError::RangeSpecMismatch);
+    }
+
+    match range_spec {
+        Some(range_spec) => range_spec_ticks(whirlpool, &range_spec),
+        None => Ok((tick_lower_index, tick_upper_index)),
+    }
+}
+
+fn floor_to_spacing(tick_index: i64, tick_spacing: i64) -> i64 {
+    tick_index.div_euclid(tick_spacing) * tick_spacing
+}
+
+fn ceil_to_spacing(tick_index: i64, tick_spacing: i64) -> i64 {
+    -floor_to_spacing(-tick_index, tick_spacing)
+}
+
+// start tick of the whirlpool tick array holding tick_index
+pub fn tick_array_start_index(tick_index: i32, tick_spacing: u16) -> i32 {
+    let ticks_in_array = TICK_ARRAY_SIZE * tick_spacing as i32;
+    tick_index.div_euclid(ticks_in_array) * ticks_in_array
+}
+
+// the tick array account has to be the whirlpool PDA for this start index,
+// returns whether it still has to be initialized
+pub fn check_tick_array(whirlpool: &Pubkey, tick_array: &AccountInfo, start_tick_index: i32) -> Result<bool> {
+    let (tick_array_address, _bump) = Pubkey::find_program_address(
+        &[b"tick_array", whirlpool.as_ref(), start_tick_index.to_string().as_bytes()],
+        &whirlpool::id(),
+    );
+    require!(tick_array.key() == tick_array_address, AqueductError::InvalidTickArray);
+
+    Ok(tick_array.data_is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn whirlpool(tick_spacing: u16, tick_current_index: i32) -> Whirlpool {
+        Whirlpool { tick_spacing, tick_current_index, ..Whirlpool::default() }
+    }
+
+    #[test]
+    fn validate_tick_range_accepts_aligned_ranges() {
+        let whirlpool = whirlpool(64, 0);
+
+        validate_tick_range(&whirlpool, -128, 128).unwrap();
+        validate_tick_range(&whirlpool, 0, 128).unwrap();
+    }
+
+    #[test]
+    fn validate_tick_range_rejects_bad_ranges() {
+        let whirlpool = whirlpool(64, 0);
+
+        assert_eq!(validate_tick_range(&whirlpool, -100, 128).unwrap_err(), AqueductError::TickNotAligned.into());
+        assert_eq!(validate_tick_range(&whirlpool, -128, 100).unwrap_err(), AqueductError::TickNotAligned.into());
+        assert_eq!(validate_tick_range(&whirlpool, 0, 64).unwrap_err(), AqueductError::PositionRangeTooNarrow.into());
+        assert_eq!(validate_tick_range(&whirlpool, 128, -128).unwrap_err(), AqueductError::PositionRangeTooNarrow.into());
+        assert_eq!(validate_tick_range(&whirlpool, -443_648, 0).unwrap_err(), AqueductError::TickOutOfBounds.into());
+        assert_eq!(validate_tick_range(&whirlpool, 0, 443_648).unwrap_err(), AqueductError::TickOutOfBounds.into());
+    }
+
+    #[test]
+    fn range_spec_ticks_around_the_current_tick() {
+        let centered = RangeSpec { width_bps: 1_000, skew_bps: 0 };
+
+        assert_eq!(range_spec_ticks(&whirlpool(64, 0), &centered).unwrap(), (-512, 512));
+        assert_eq!(range_spec_ticks(&whirlpool(64, 100), &centered).unwrap(), (-448, 640));
+    }
+
+    #[test]
+    fn range_spec_ticks_skewed() {
+        let whirlpool = whirlpool(64, 0);
+
+        assert_eq!(range_spec_ticks(&whirlpool, &RangeSpec { width_bps: 1_000, skew_bps: RANGE_SKEW_MAX_BPS }).unwrap(), (0, 1_024));
+        assert_eq!(range_spec_ticks(&whirlpool, &RangeSpec { width_bps: 1_000, skew_bps: -RANGE_SKEW_MAX_BPS }).unwrap(), (-1_024, 0));
+    }
+
+    #[test]
+    fn range_spec_ticks_clamped_to_the_usable_bounds() {
+        let range_spec = RangeSpec { width_bps: 1_000, skew_bps: 0 };
+
+        assert_eq!(range_spec_ticks(&whirlpool(64, 443_600), &range_spec).unwrap(), (443_072, 443_584));
+        assert_eq!(range_spec_ticks(&whirlpool(64, -443_600), &range_spec).unwrap(), (-443_584, -443_072));
+    }
+
+    #[test]
+    fn range_spec_ticks_rejects_invalid_specs() {
+        let whirlpool = whirlpool(64, 0);
+
+        assert_eq!(range_spec_ticks(&whirlpool, &RangeSpec { width_bps: 0, skew_bps: 0 }).unwrap_err(), AqueductError::InvalidRangeSpec.into());
+        assert_eq!(range_spec_ticks(&whirlpool, &RangeSpec { width_bps: 1_000, skew_bps: RANGE_SKEW_MAX_BPS + 1 }).unwrap_err(), AqueductError::InvalidRangeSpec.into());
+    }
+
+    #[test]
+    fn tick_array_start_index_rounds_towards_negative_infinity() {
+        assert_eq!(tick_array_start_index(0, 64), 0);
+        assert_eq!(tick_array_start_index(5_631, 64), 0);
+        assert_eq!(tick_array_start_index(5_632, 64), 5_632);
+        assert_eq!(tick_array_start_index(-1, 64), -5_632);
+        assert_eq!(tick_array_start_index(-5_632, 64), -5_632);
+        assert_eq!(tick_array_start_index(-5_633, 64), -11_264);
+        assert_eq!(tick_array_start_index(87, 1), 0);
+        assert_eq!(tick_array_start_index(88, 1), 88);
+    }
+}